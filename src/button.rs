@@ -5,7 +5,7 @@
 #[derive(PartialEq, Clone)]
 pub(crate) enum TempoKind {
     /// Short+Long press
-    /// "second line pulse length is equal to T_Tgl value in no matter to real depressing time"
+    /// "second line pulse length is equal to `T_Tgl` value in no matter to real depressing time"
     _Tempo1,
     /// Short+Long press
     /// "second line pulse length is equal to button depressing time"
@@ -120,6 +120,20 @@ pub(crate) enum VirtualButtonKind {
     Tempo(VirtualTempoKind),
 }
 
+impl VirtualButtonKind {
+    /// eg "SHIFT1 + press", "long press"
+    pub(crate) fn get_modifier_path(&self) -> &'static str {
+        match self {
+            VirtualButtonKind::Momentary(None) => "press",
+            VirtualButtonKind::Momentary(Some(VirtualShiftKind::Shift1)) => "SHIFT1 + press",
+            VirtualButtonKind::Momentary(Some(VirtualShiftKind::Shift2)) => "SHIFT2 + press",
+            VirtualButtonKind::Tempo(VirtualTempoKind::Short) => "short press",
+            VirtualButtonKind::Tempo(VirtualTempoKind::Long) => "long press",
+            VirtualButtonKind::Tempo(VirtualTempoKind::Double) => "double press",
+        }
+    }
+}
+
 /// Intermediate struct only needed because that way we can have eg `Vec<VirtualButton>`
 /// which is better than having `Vec<ButtonKind>` when we known they are all `Virtual` variants
 ///
@@ -158,7 +172,7 @@ impl core::fmt::Debug for VirtualButton {
                 write!(f, "VirtualButton [{} ({:?})]", self.id, shift)
             }
             VirtualButtonKind::Tempo(tempo_kind) => {
-                write!(f, "VirtualButton [{} ({:?})]", self.id, tempo_kind,)
+                write!(f, "VirtualButton [{} ({:?})]", self.id, tempo_kind)
             }
         }
     }
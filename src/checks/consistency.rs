@@ -0,0 +1,390 @@
+//! Cross-check the joystick configuration against the game keybinds, for ONE stick
+//! - dead binds: "`js2_button{N}`" bound in-game but N is NOT produced by any physical button of the stick
+//! - free slots: physical buttons (and their SHIFT/TEMPO layers) without any game action,
+//!   and the "No defined function" physical buttons
+//! - virtual buttons above the "Number of logical buttons" of the device: the game does NOT see them
+//! - SHIFT layers whose SHIFT button is NOT defined on the stick: they can NOT be reached
+//!
+//! NOTE: a virtual button is bound if ANY joystick rebind of ANY action uses it, not only the 1st one
+//!
+
+use serde::Serialize;
+
+use crate::button::{VirtualButtonKind, VirtualShiftKind};
+use crate::sc::parse_keybind_xml::GameButtonsMapping;
+use crate::vkb::vkb_button::JoystickButtonsMapping;
+
+/// A game bind that can NOT be triggered from the stick
+#[derive(Debug, PartialEq, Serialize)]
+pub struct DeadBind {
+    pub virtual_button_id: u8,
+    /// eg "`js2_button77`"
+    pub game_input: String,
+    pub game_actions: Vec<String>,
+}
+
+/// A way to press a physical button which does nothing in-game
+#[derive(Debug, PartialEq, Serialize)]
+pub struct FreeSlot {
+    pub physical_button_id: u8,
+    /// eg "(A2)"
+    pub info: String,
+    /// eg "Thumb red button"; empty if not given
+    pub user_desc: String,
+    /// eg "press", "SHIFT1 + press", "long press"; cf `VirtualButtonKind`
+    /// For the "No defined function" buttons: "undefined"
+    pub modifier_path: String,
+    /// None for the "No defined function" buttons
+    pub virtual_button_id: Option<u8>,
+}
+
+/// A virtual button the game will never see cf `JoystickButtonsMapping::logical_buttons_count`
+#[derive(Debug, PartialEq, Serialize)]
+pub struct OutOfRangeVirtualButton {
+    pub virtual_button_id: u8,
+    pub physical_button_id: u8,
+    pub info: String,
+}
+
+/// A SHIFT layer on a physical button, but NO physical button acts as this SHIFT
+#[derive(Debug, PartialEq, Serialize)]
+pub struct UndefinedShiftLayer {
+    /// "SHIFT1" or "SHIFT2"
+    pub shift: String,
+    pub virtual_button_id: u8,
+    pub physical_button_id: u8,
+    pub info: String,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ConsistencyReport {
+    pub game_device_id: u8,
+    /// cf `JoystickButtonsMapping::logical_buttons_count`
+    pub logical_buttons_count: Option<u8>,
+    pub dead_binds: Vec<DeadBind>,
+    pub free_slots: Vec<FreeSlot>,
+    pub out_of_range_virtual_buttons: Vec<OutOfRangeVirtualButton>,
+    pub undefined_shift_layers: Vec<UndefinedShiftLayer>,
+}
+
+impl ConsistencyReport {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.dead_binds.is_empty()
+            && self.free_slots.is_empty()
+            && self.out_of_range_virtual_buttons.is_empty()
+            && self.undefined_shift_layers.is_empty()
+    }
+}
+
+impl core::fmt::Display for ConsistencyReport {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        writeln!(
+            f,
+            "dead binds (js{}_buttonN bound in-game, NOT produced by the stick): {}",
+            self.game_device_id,
+            self.dead_binds.len()
+        )?;
+        for dead_bind in &self.dead_binds {
+            writeln!(
+                f,
+                "  - {} : {}",
+                dead_bind.game_input,
+                dead_bind.game_actions.join(", ")
+            )?;
+        }
+
+        writeln!(f, "free slots (no game action): {}", self.free_slots.len())?;
+        for free_slot in &self.free_slots {
+            write!(
+                f,
+                "  - physical #{} {}",
+                free_slot.physical_button_id, free_slot.info
+            )?;
+            if !free_slot.user_desc.is_empty() {
+                write!(f, " \"{}\"", free_slot.user_desc)?;
+            }
+            write!(f, " : {}", free_slot.modifier_path)?;
+            if let Some(virtual_button_id) = free_slot.virtual_button_id {
+                write!(f, " (virtual {virtual_button_id})")?;
+            }
+            writeln!(f)?;
+        }
+
+        writeln!(
+            f,
+            "virtual buttons above the number of logical buttons ({}): {}",
+            self.logical_buttons_count
+                .map_or_else(|| "unknown".to_string(), |count| count.to_string()),
+            self.out_of_range_virtual_buttons.len()
+        )?;
+        for out_of_range in &self.out_of_range_virtual_buttons {
+            writeln!(
+                f,
+                "  - virtual {} from physical #{} {}",
+                out_of_range.virtual_button_id, out_of_range.physical_button_id, out_of_range.info
+            )?;
+        }
+
+        writeln!(
+            f,
+            "SHIFT layers without a SHIFT button: {}",
+            self.undefined_shift_layers.len()
+        )?;
+        for undefined_shift_layer in &self.undefined_shift_layers {
+            writeln!(
+                f,
+                "  - {} on physical #{} {} (virtual {})",
+                undefined_shift_layer.shift,
+                undefined_shift_layer.physical_button_id,
+                undefined_shift_layer.info,
+                undefined_shift_layer.virtual_button_id
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Build the `ConsistencyReport` of ONE stick
+///
+/// params:
+/// - `game_device_id`: the "js{N}_" of this stick in the game mapping
+#[must_use]
+pub fn check_consistency(
+    joysticks_mappings: &JoystickButtonsMapping,
+    game_buttons_mapping: &GameButtonsMapping,
+    game_device_id: u8,
+) -> ConsistencyReport {
+    let dead_binds = game_buttons_mapping
+        .get_all_virtual_buttons_bound(game_device_id)
+        .into_iter()
+        .filter(|(virtual_button_id, _)| {
+            !joysticks_mappings
+                .map_virtual_button_id_to_parent_physical_buttons
+                .contains_key(virtual_button_id)
+        })
+        .map(|(virtual_button_id, game_actions)| DeadBind {
+            virtual_button_id,
+            game_input: format!("js{game_device_id}_button{virtual_button_id}"),
+            game_actions,
+        })
+        .collect();
+
+    let mut free_slots = vec![];
+    let mut out_of_range_virtual_buttons = vec![];
+    let mut undefined_shift_layers = vec![];
+
+    let mut parents: Vec<_> = joysticks_mappings
+        .map_virtual_button_id_to_parent_physical_buttons
+        .values()
+        .flatten()
+        .collect();
+    // By ID: the children are per physical ID, and the same one can have several lines
+    // eg #21 "(A1 right)" POV and #21 "(A3 down)"; else its free slots are listed twice
+    parents.sort_by_key(|parent| (*parent.get_id(), parent.get_info()));
+    parents.dedup_by_key(|parent| *parent.get_id());
+
+    for parent in parents {
+        let Some(children) = joysticks_mappings
+            .map_physical_button_id_to_children_virtual_buttons
+            .get(parent.get_id())
+        else {
+            continue;
+        };
+
+        for child in children {
+            let virtual_button_id = *child.get_id();
+
+            if game_buttons_mapping
+                .get_rebinds_from_virtual_button_id(virtual_button_id, game_device_id)
+                .is_empty()
+            {
+                free_slots.push(FreeSlot {
+                    physical_button_id: *parent.get_id(),
+                    info: parent.get_info().clone(),
+                    user_desc: parent.get_user_desc().clone(),
+                    modifier_path: child.kind.get_modifier_path().to_string(),
+                    virtual_button_id: Some(virtual_button_id),
+                });
+            }
+
+            if joysticks_mappings
+                .logical_buttons_count
+                .is_some_and(|logical_buttons_count| virtual_button_id > logical_buttons_count)
+            {
+                out_of_range_virtual_buttons.push(OutOfRangeVirtualButton {
+                    virtual_button_id,
+                    physical_button_id: *parent.get_id(),
+                    info: parent.get_info().clone(),
+                });
+            }
+
+            if let VirtualButtonKind::Momentary(Some(shift_kind)) = &child.kind {
+                if joysticks_mappings
+                    .get_shift_buttons_infos(shift_kind)
+                    .is_empty()
+                {
+                    undefined_shift_layers.push(UndefinedShiftLayer {
+                        shift: match shift_kind {
+                            VirtualShiftKind::Shift1 => "SHIFT1".to_string(),
+                            VirtualShiftKind::Shift2 => "SHIFT2".to_string(),
+                        },
+                        virtual_button_id,
+                        physical_button_id: *parent.get_id(),
+                        info: parent.get_info().clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    free_slots.extend(get_free_slots_without_virtual_button(joysticks_mappings));
+
+    ConsistencyReport {
+        game_device_id,
+        logical_buttons_count: joysticks_mappings.logical_buttons_count,
+        dead_binds,
+        free_slots,
+        out_of_range_virtual_buttons,
+        undefined_shift_layers,
+    }
+}
+
+/// The free slots of the physical buttons WITHOUT any virtual button ie ALL the "No defined function" buttons
+fn get_free_slots_without_virtual_button(
+    joysticks_mappings: &JoystickButtonsMapping,
+) -> Vec<FreeSlot> {
+    let mut free_slots = vec![];
+
+    for undefined_physical_button in &joysticks_mappings.undefined_physical_buttons {
+        free_slots.push(FreeSlot {
+            physical_button_id: *undefined_physical_button.get_id(),
+            info: undefined_physical_button.get_info().clone(),
+            user_desc: undefined_physical_button.get_user_desc().clone(),
+            modifier_path: "undefined".to_string(),
+            virtual_button_id: None,
+        });
+    }
+
+    free_slots
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::button::{PhysicalButton, PhysicalButtonKind};
+
+    use super::*;
+
+    #[test]
+    fn test_check_consistency_report_l() {
+        let mut joysticks_mappings = crate::vkb::parse_and_check_vkb_both_sticks(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/vkb_report_L.fp3").as_ref(),
+            &None,
+            None,
+        )
+        .unwrap();
+        let game_buttons_mapping = crate::sc::parse_keybind_xml::parse_keybind(
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/data/layout_vkb_exported.xml"
+            )
+            .into(),
+            None,
+        )
+        .unwrap();
+
+        let report = check_consistency(&joysticks_mappings, &game_buttons_mapping, 2);
+        assert_eq!(report.logical_buttons_count, Some(128));
+        assert!(report.out_of_range_virtual_buttons.is_empty());
+        assert!(report.undefined_shift_layers.is_empty());
+        // "(Fire 2-nd stage)" is js2_button2 and bound in-game
+        assert!(!report
+            .dead_binds
+            .iter()
+            .any(|dead_bind| dead_bind.virtual_button_id == 2));
+        assert!(!report
+            .free_slots
+            .iter()
+            .any(|free_slot| free_slot.virtual_button_id == Some(2)));
+
+        // Same stick, but as if it had fewer logical buttons and no SHIFT1 button
+        joysticks_mappings.logical_buttons_count = Some(32);
+        joysticks_mappings
+            .map_special_buttons
+            .retain(|_, special_kind| *special_kind != crate::button::SpecialButtonKind::Shift1);
+        let report = check_consistency(&joysticks_mappings, &game_buttons_mapping, 2);
+        assert!(report
+            .out_of_range_virtual_buttons
+            .iter()
+            .all(|out_of_range| out_of_range.virtual_button_id > 32));
+        assert!(!report.out_of_range_virtual_buttons.is_empty());
+        assert!(report
+            .undefined_shift_layers
+            .iter()
+            .all(|undefined_shift_layer| undefined_shift_layer.shift == "SHIFT1"));
+        assert!(!report.undefined_shift_layers.is_empty());
+    }
+
+    #[test]
+    fn test_check_consistency_secondary_rebind_and_undefined() {
+        let mut joysticks_mappings = crate::vkb::parse_and_check_vkb_both_sticks(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/vkb_report_L.fp3").as_ref(),
+            &None,
+            None,
+        )
+        .unwrap();
+        // #21 is BOTH "(A1 right)" POV and "(A3 down)"; ie 2 different parents with the same ID and children
+        joysticks_mappings
+            .map_virtual_button_id_to_parent_physical_buttons
+            .get_mut(&8)
+            .unwrap()
+            .push(PhysicalButton::new(
+                21,
+                PhysicalButtonKind::Momentary { shift: None },
+                "(A1 right)".to_string(),
+                "<b>Point of view Switch</b> POV1  Right".to_string(),
+                String::new(),
+            ));
+        // "v_attack_group2": js2_button108 then js2_button100
+        let game_buttons_mapping = crate::sc::parse_keybind_xml::parse_keybind(
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/data/layout_activation_modes.xml"
+            )
+            .into(),
+            None,
+        )
+        .unwrap();
+
+        let report = check_consistency(&joysticks_mappings, &game_buttons_mapping, 2);
+        for virtual_button_id in [100, 108] {
+            assert!(!report
+                .free_slots
+                .iter()
+                .any(|free_slot| free_slot.virtual_button_id == Some(virtual_button_id)));
+        }
+        // "#37 No defined function"
+        assert!(report.free_slots.iter().any(|free_slot| {
+            free_slot.physical_button_id == 37
+                && free_slot.modifier_path == "undefined"
+                && free_slot.virtual_button_id.is_none()
+        }));
+        // NO duplicates eg #21 above
+        let mut free_slots_keys: Vec<_> = report
+            .free_slots
+            .iter()
+            .map(|free_slot| {
+                (
+                    free_slot.physical_button_id,
+                    free_slot.virtual_button_id,
+                    free_slot.modifier_path.as_str(),
+                )
+            })
+            .collect();
+        let free_slots_count = free_slots_keys.len();
+        free_slots_keys.sort_unstable();
+        free_slots_keys.dedup();
+        assert_eq!(free_slots_keys.len(), free_slots_count);
+    }
+}
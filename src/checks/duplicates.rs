@@ -0,0 +1,259 @@
+//! Virtual buttons reached from more than one physical line
+//! eg with SHIFT/TEMPO two different physical buttons can end up bound to the same virtual button in-game,
+//! cf `JoystickButtonsMapping::map_virtual_button_id_to_parent_physical_buttons`.
+//!
+//! NOT necessarily a bug, but this is NOT detected by `VkbDevCfg` and is usually a "waste of space".
+//!
+
+use crate::sc::parse_keybind_xml::GameButtonsMapping;
+use crate::vkb::vkb_button::JoystickButtonsMapping;
+
+/// One of the physical buttons (and how) leading to a duplicated virtual button
+#[derive(Debug, PartialEq)]
+pub struct DuplicateOrigin {
+    pub physical_button_id: u8,
+    /// eg "(A2)"
+    pub info: String,
+    /// eg "Thumb red button"; empty if not given
+    pub user_desc: String,
+    /// eg "SHIFT1 + press", "long press"; cf `VirtualButtonKind`
+    pub modifier_path: String,
+}
+
+/// A virtual button with ALL its origins, and the game actions reachable through it
+#[derive(Debug, PartialEq)]
+pub struct DuplicatedVirtualButton {
+    pub virtual_button_id: u8,
+    pub origins: Vec<DuplicateOrigin>,
+    /// From ALL the joystick rebinds of each action, sorted and deduplicated
+    /// Empty if not bound in-game, or if no game mapping was given
+    pub game_actions: Vec<String>,
+}
+
+impl core::fmt::Display for DuplicatedVirtualButton {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        writeln!(
+            f,
+            "virtual button #{} is reached from {} physical buttons; game actions: {}",
+            self.virtual_button_id,
+            self.origins.len(),
+            if self.game_actions.is_empty() {
+                "NO BINDING".to_string()
+            } else {
+                self.game_actions.join(", ")
+            }
+        )?;
+        for origin in &self.origins {
+            write!(
+                f,
+                "  - physical #{} {}",
+                origin.physical_button_id, origin.info
+            )?;
+            if !origin.user_desc.is_empty() {
+                write!(f, " \"{}\"", origin.user_desc)?;
+            }
+            writeln!(f, " : {}", origin.modifier_path)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Find ALL the virtual buttons reached from more than one physical button, sorted by virtual ID
+///
+/// params:
+/// - `game_buttons_mapping` and `game_device_id`: optional, only used to fill `game_actions`
+#[must_use]
+pub fn find_duplicated_virtual_buttons(
+    joysticks_mappings: &JoystickButtonsMapping,
+    game_buttons_mapping: Option<&GameButtonsMapping>,
+    game_device_id: Option<u8>,
+) -> Vec<DuplicatedVirtualButton> {
+    let mut duplicated_virtual_buttons: Vec<DuplicatedVirtualButton> = joysticks_mappings
+        .map_virtual_button_id_to_parent_physical_buttons
+        .iter()
+        .filter(|(_, parents)| parents.len() > 1)
+        .map(|(virtual_button_id, parents)| {
+            let origins = parents
+                .iter()
+                .map(|parent| {
+                    // The "how": the kind of THIS child under THIS parent
+                    let modifier_path = joysticks_mappings
+                        .map_physical_button_id_to_children_virtual_buttons
+                        .get(parent.get_id())
+                        .and_then(|children| {
+                            children
+                                .iter()
+                                .find(|child| child.get_id() == virtual_button_id)
+                        })
+                        .map_or_else(
+                            || "?".to_string(),
+                            |child| child.kind.get_modifier_path().to_string(),
+                        );
+
+                    DuplicateOrigin {
+                        physical_button_id: *parent.get_id(),
+                        info: parent.get_info().clone(),
+                        user_desc: parent.get_user_desc().clone(),
+                        modifier_path,
+                    }
+                })
+                .collect();
+
+            // NOT only the first rebind of each action: eg "`v_attack_group2`" on its 2nd rebind
+            let mut game_actions: Vec<String> = match (game_buttons_mapping, game_device_id) {
+                (Some(game_buttons_mapping), Some(game_device_id)) => game_buttons_mapping
+                    .get_rebinds_from_virtual_button_id(*virtual_button_id, game_device_id)
+                    .into_iter()
+                    .map(|(action_name, _)| action_name.clone())
+                    .collect(),
+                _ => vec![],
+            };
+            // the same action can be in several actionmaps
+            game_actions.dedup();
+
+            DuplicatedVirtualButton {
+                virtual_button_id: *virtual_button_id,
+                origins,
+                game_actions,
+            }
+        })
+        .collect();
+
+    duplicated_virtual_buttons.sort_by_key(|duplicated| duplicated.virtual_button_id);
+
+    duplicated_virtual_buttons
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::button::{
+        PhysicalButton, PhysicalButtonKind, ShiftKind, VirtualButton, VirtualButtonKind,
+        VirtualShiftKind,
+    };
+
+    use super::*;
+
+    /// `duplicated_virtual_button_id` is reached from #9 (standard press) and from #12 (SHIFT1)
+    fn get_sample_mappings(duplicated_virtual_button_id: u8) -> JoystickButtonsMapping {
+        let physical_9 = PhysicalButton::new(
+            9,
+            PhysicalButtonKind::Momentary { shift: None },
+            "(Fire 2-nd stage)".to_string(),
+            "- Button with momentary action".to_string(),
+            String::new(),
+        );
+        let physical_12 = PhysicalButton::new(
+            12,
+            PhysicalButtonKind::Momentary {
+                shift: Some(ShiftKind::Shift1 {
+                    button_id_shift1: duplicated_virtual_button_id,
+                }),
+            },
+            "(A2)".to_string(),
+            "- Button with momentary action".to_string(),
+            "Thumb red button".to_string(),
+        );
+        JoystickButtonsMapping {
+            map_virtual_button_id_to_parent_physical_buttons: HashMap::from([
+                (
+                    duplicated_virtual_button_id,
+                    vec![physical_9, physical_12.clone()],
+                ),
+                (108, vec![physical_12]),
+            ]),
+            map_physical_button_id_to_children_virtual_buttons: HashMap::from([
+                (
+                    9,
+                    vec![VirtualButton {
+                        id: duplicated_virtual_button_id,
+                        kind: VirtualButtonKind::Momentary(None),
+                    }],
+                ),
+                (
+                    12,
+                    vec![
+                        VirtualButton {
+                            id: 108,
+                            kind: VirtualButtonKind::Momentary(None),
+                        },
+                        VirtualButton {
+                            id: duplicated_virtual_button_id,
+                            kind: VirtualButtonKind::Momentary(Some(VirtualShiftKind::Shift1)),
+                        },
+                    ],
+                ),
+            ]),
+            map_special_buttons: HashMap::new(),
+            undefined_physical_buttons: vec![],
+            map_physical_button_id_to_infos: HashMap::new(),
+            logical_buttons_count: Some(128),
+        }
+    }
+
+    #[test]
+    fn test_find_duplicated_virtual_buttons() {
+        let joysticks_mappings = get_sample_mappings(2);
+        let game_buttons_mapping = crate::sc::parse_keybind_xml::parse_keybind(
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/data/layout_vkb_exported.xml"
+            )
+            .into(),
+            None,
+        )
+        .unwrap();
+
+        let duplicated_virtual_buttons = find_duplicated_virtual_buttons(
+            &joysticks_mappings,
+            Some(&game_buttons_mapping),
+            Some(2),
+        );
+
+        assert_eq!(duplicated_virtual_buttons.len(), 1);
+        let duplicated = &duplicated_virtual_buttons[0];
+        assert_eq!(duplicated.virtual_button_id, 2);
+        assert_eq!(
+            duplicated
+                .origins
+                .iter()
+                .map(|origin| (origin.physical_button_id, origin.modifier_path.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(9, "press"), (12, "SHIFT1 + press")]
+        );
+        assert_eq!(duplicated.origins[1].user_desc, "Thumb red button");
+        assert!(duplicated
+            .game_actions
+            .contains(&"v_weapon_toggle_launch_missile".to_string()));
+    }
+
+    #[test]
+    fn test_find_duplicated_virtual_buttons_secondary_rebind() {
+        // "v_attack_group2" is on js2_button108 THEN on js2_button84
+        let joysticks_mappings = get_sample_mappings(84);
+        let game_buttons_mapping = crate::sc::parse_keybind_xml::parse_keybind(
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/data/layout_secondary_rebind_free_id.xml"
+            )
+            .into(),
+            None,
+        )
+        .unwrap();
+
+        let duplicated_virtual_buttons = find_duplicated_virtual_buttons(
+            &joysticks_mappings,
+            Some(&game_buttons_mapping),
+            Some(2),
+        );
+
+        assert_eq!(duplicated_virtual_buttons.len(), 1);
+        assert_eq!(duplicated_virtual_buttons[0].virtual_button_id, 84);
+        assert_eq!(
+            duplicated_virtual_buttons[0].game_actions,
+            vec!["v_attack_group2".to_string()]
+        );
+    }
+}
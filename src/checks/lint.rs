@@ -0,0 +1,217 @@
+//! Semantic lint: patterns which are valid for both `VkbDevCfg` and the game, but almost always a mistake
+//! - a VKB Tempo virtual button bound in-game with a "hold"/"`double_tap`" activation mode (or "multiTap"):
+//!   the stick ALREADY splits short/long/double presses, the game waiting for another hold/tap on top of it
+//!   means the action fires late, or never
+//! - the same action bound on the base layer of a physical button AND on one of its SHIFT layers:
+//!   the SHIFT layer slot is wasted
+//!
+
+use serde::Serialize;
+
+use crate::button::{VirtualButtonKind, VirtualShiftKind};
+use crate::sc::parse_keybind_xml::GameButtonsMapping;
+use crate::vkb::vkb_button::JoystickButtonsMapping;
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize)]
+pub enum LintRule {
+    TempoWithGameActivationMode,
+    SameActionOnBaseAndShiftLayer,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct LintFinding {
+    pub rule: LintRule,
+    pub physical_button_id: u8,
+    /// eg "(A2)"
+    pub info: String,
+    /// eg "Thumb red button"; empty if not given
+    pub user_desc: String,
+    /// The virtual buttons involved eg the base one and the SHIFT1 one
+    pub virtual_button_ids: Vec<u8>,
+    pub game_action: String,
+    /// Human-readable: why this is flagged
+    pub explanation: String,
+}
+
+impl core::fmt::Display for LintFinding {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "[{:?}] physical #{} {}",
+            self.rule, self.physical_button_id, self.info
+        )?;
+        if !self.user_desc.is_empty() {
+            write!(f, " \"{}\"", self.user_desc)?;
+        }
+        writeln!(f, " / {} : {}", self.game_action, self.explanation)
+    }
+}
+
+/// Run ALL the lint rules on ONE stick; sorted by physical button id
+#[must_use]
+#[allow(clippy::too_many_lines)]
+pub fn lint(
+    joysticks_mappings: &JoystickButtonsMapping,
+    game_buttons_mapping: &GameButtonsMapping,
+    game_device_id: u8,
+) -> Vec<LintFinding> {
+    let mut findings = vec![];
+
+    let mut parents: Vec<_> = joysticks_mappings
+        .map_virtual_button_id_to_parent_physical_buttons
+        .values()
+        .flatten()
+        .collect();
+    parents.sort_by_key(|parent| (*parent.get_id(), parent.get_info()));
+    parents.dedup();
+
+    for parent in parents {
+        let Some(children) = joysticks_mappings
+            .map_physical_button_id_to_children_virtual_buttons
+            .get(parent.get_id())
+        else {
+            continue;
+        };
+        let new_finding = |rule, virtual_button_ids, game_action: &str, explanation| LintFinding {
+            rule,
+            physical_button_id: *parent.get_id(),
+            info: parent.get_info().clone(),
+            user_desc: parent.get_user_desc().clone(),
+            virtual_button_ids,
+            game_action: game_action.to_string(),
+            explanation,
+        };
+
+        // Rule: Tempo + activation mode
+        for child in children {
+            let VirtualButtonKind::Tempo(_) = &child.kind else {
+                continue;
+            };
+            for (action_name, rebind) in game_buttons_mapping
+                .get_rebinds_from_virtual_button_id(*child.get_id(), game_device_id)
+            {
+                let activation_mode = rebind.activation_mode.as_deref().unwrap_or_default();
+                let is_hold_or_multi_tap = ["hold", "double_tap", "delayed_press"]
+                    .iter()
+                    .any(|pattern| activation_mode.contains(pattern))
+                    || rebind.multi_tap.is_some_and(|multi_tap| multi_tap > 1);
+                if !is_hold_or_multi_tap {
+                    continue;
+                }
+
+                let game_activation = rebind
+                    .activation_mode
+                    .iter()
+                    .map(|activation_mode| format!("activationMode=\"{activation_mode}\""))
+                    .chain(
+                        rebind
+                            .multi_tap
+                            .map(|multi_tap| format!("multiTap=\"{multi_tap}\"")),
+                    )
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                findings.push(new_finding(
+                    LintRule::TempoWithGameActivationMode,
+                    vec![*child.get_id()],
+                    action_name,
+                    format!(
+                        "virtual {} is ALREADY a VKB {} but the game ALSO waits for {game_activation}; the action will fire late or never, use the default activation mode",
+                        child.get_id(),
+                        child.kind.get_modifier_path(),
+                    ),
+                ));
+            }
+        }
+
+        // Rule: same action on the base layer and a SHIFT layer
+        let base_children: Vec<_> = children
+            .iter()
+            .filter(|child| child.kind == VirtualButtonKind::Momentary(None))
+            .collect();
+        for (shift_child, shift_kind) in children.iter().filter_map(|child| match &child.kind {
+            VirtualButtonKind::Momentary(Some(shift_kind)) => Some((child, shift_kind)),
+            _ => None,
+        }) {
+            let shift_name = match shift_kind {
+                VirtualShiftKind::Shift1 => "SHIFT1",
+                VirtualShiftKind::Shift2 => "SHIFT2",
+            };
+            let shift_actions: Vec<&String> = game_buttons_mapping
+                .get_rebinds_from_virtual_button_id(*shift_child.get_id(), game_device_id)
+                .into_iter()
+                .map(|(action_name, _)| action_name)
+                .collect();
+
+            for base_child in &base_children {
+                for (action_name, _) in game_buttons_mapping
+                    .get_rebinds_from_virtual_button_id(*base_child.get_id(), game_device_id)
+                {
+                    if !shift_actions.contains(&action_name) {
+                        continue;
+                    }
+                    findings.push(new_finding(
+                        LintRule::SameActionOnBaseAndShiftLayer,
+                        vec![*base_child.get_id(), *shift_child.get_id()],
+                        action_name,
+                        format!(
+                            "bound on both the base press (virtual {}) and the {shift_name} layer (virtual {}); the {shift_name} slot is wasted",
+                            base_child.get_id(),
+                            shift_child.get_id()
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lint_report_l() {
+        let joysticks_mappings = crate::vkb::parse_and_check_vkb_both_sticks(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/vkb_report_L.fp3").as_ref(),
+            &None,
+            None,
+        )
+        .unwrap();
+        let game_buttons_mapping = crate::sc::parse_keybind_xml::parse_keybind(
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/data/layout_activation_modes.xml"
+            )
+            .into(),
+            None,
+        )
+        .unwrap();
+
+        let findings = lint(&joysticks_mappings, &game_buttons_mapping, 2);
+
+        // (F1) long press is virtual 96: "hold" is flagged, but NOT the default activation mode on 13 (short press)
+        let tempo_findings: Vec<_> = findings
+            .iter()
+            .filter(|finding| finding.rule == LintRule::TempoWithGameActivationMode)
+            .collect();
+        assert_eq!(tempo_findings.len(), 2);
+        assert!(tempo_findings
+            .iter()
+            .any(|finding| finding.info == "(F1)" && finding.virtual_button_ids == vec![96]));
+        assert!(tempo_findings
+            .iter()
+            .any(|finding| finding.info == "(F1)" && finding.virtual_button_ids == vec![97]));
+
+        // (A2): base 108 and SHIFT1 100
+        let shift_findings: Vec<_> = findings
+            .iter()
+            .filter(|finding| finding.rule == LintRule::SameActionOnBaseAndShiftLayer)
+            .collect();
+        assert_eq!(shift_findings.len(), 1);
+        assert_eq!(shift_findings[0].info, "(A2)");
+        assert_eq!(shift_findings[0].virtual_button_ids, vec![108, 100]);
+        assert_eq!(shift_findings[0].game_action, "v_attack_group2");
+    }
+}
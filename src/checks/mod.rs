@@ -0,0 +1,7 @@
+//! Checks combining the joystick configuration and the game keybinds
+//! They do NOT change anything, they only return structured reports; printing them is up to the caller.
+//!
+
+pub(crate) mod consistency;
+pub(crate) mod duplicates;
+pub(crate) mod lint;
@@ -0,0 +1,453 @@
+//! Explain the full chain from a physical button to the game actions, and back:
+//! physical "(A2)" -> SHIFT1 virtual button 101 -> "`js1_button101`" -> "`v_weapon_...`"
+//!
+//! Both directions work on SEVERAL sticks at once, each with its own game device id
+//! eg `[(&mapping_r, 1), (&mapping_l, 2)]` cf "--game-device-id".
+//!
+
+use globset::Glob;
+
+use crate::button::{
+    PhysicalButton, PhysicalButtonKind, SpecialButtonKind, VirtualButtonKind, VirtualShiftKind,
+};
+use crate::sc::parse_keybind_xml::GameButtonsMapping;
+use crate::vkb::vkb_button::JoystickButtonsMapping;
+use crate::Error;
+
+/// ONE way to reach the game from a physical button
+/// A physical button usually has several eg a standard press + a SHIFT1 press, or short/long press.
+#[derive(Debug, PartialEq, Clone)]
+pub struct BindingChain {
+    /// eg "2" for "`js2_...`"
+    pub game_device_id: u8,
+    pub physical_button_id: u8,
+    /// eg "(A2)"
+    pub info: String,
+    /// eg "Thumb red button"; empty if not given
+    pub user_desc: String,
+    /// eg "press", "SHIFT1 (D1) + press", "long press"
+    /// For a SHIFT1/SHIFT2 button: "SHIFT1 modifier"
+    pub modifier_path: String,
+    /// None for the modifiers
+    pub virtual_button_id: Option<u8>,
+    /// eg "`js2_button101`"; empty for the modifiers
+    pub game_input: String,
+    /// Empty if not bound in-game, or if no game mapping was given
+    pub game_actions: Vec<String>,
+}
+
+impl core::fmt::Display for BindingChain {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "js{} #{} {}",
+            self.game_device_id, self.physical_button_id, self.info
+        )?;
+        if !self.user_desc.is_empty() {
+            write!(f, " \"{}\"", self.user_desc)?;
+        }
+        write!(f, " : {}", self.modifier_path)?;
+        if self.game_input.is_empty() {
+            return Ok(());
+        }
+        if let Some(virtual_button_id) = self.virtual_button_id {
+            write!(f, " -> virtual {virtual_button_id}")?;
+        }
+        write!(
+            f,
+            " -> {} -> {}",
+            self.game_input,
+            if self.game_actions.is_empty() {
+                "NO BINDING".to_string()
+            } else {
+                self.game_actions.join(", ")
+            }
+        )
+    }
+}
+
+/// Physical label -> game actions
+/// Every stick where `info_or_user_desc` is found is explained, cf `get_virtual_button_ids_from_info_or_user_desc`
+///
+/// params:
+/// - `sticks`: ALL the sticks with their game device id
+/// - `game_buttons_mapping`: optional; without it the chains stop at the game input eg "`js2_button101`"
+///
+/// # Errors
+/// - `Error::ButtonNotFound` if `info_or_user_desc` is not on any of the sticks
+pub fn explain_physical_button(
+    sticks: &[(&JoystickButtonsMapping, u8)],
+    game_buttons_mapping: Option<&GameButtonsMapping>,
+    info_or_user_desc: &str,
+) -> Result<Vec<BindingChain>, Error> {
+    let mut chains = vec![];
+    let mut is_found = false;
+
+    for (joysticks_mappings, game_device_id) in sticks {
+        match explain_physical_button_on_stick(
+            joysticks_mappings,
+            *game_device_id,
+            game_buttons_mapping,
+            info_or_user_desc,
+        ) {
+            Ok(stick_chains) => {
+                is_found = true;
+                chains.extend(stick_chains);
+            }
+            Err(Error::ButtonNotFound { .. }) => {}
+            Err(err) => return Err(err),
+        }
+    }
+
+    if !is_found {
+        return Err(Error::ButtonNotFound {
+            info_or_user_desc: info_or_user_desc.to_string(),
+        });
+    }
+
+    Ok(chains)
+}
+
+/// Game action (or glob eg "`v_weapon_*`") -> ALL the physical buttons reaching it, on ALL the sticks
+/// Sorted by game device id then physical button id.
+///
+/// NOTE: the physical buttons are walked as-is, NOT looked up again by label: many have an empty "info"
+/// and would be mistaken for each other cf `find_physical_button`
+///
+/// # Errors
+/// - `Error::InvalidGlob` if `action_name_or_glob` is not a valid glob
+pub fn find_physical_buttons_for_action(
+    sticks: &[(&JoystickButtonsMapping, u8)],
+    game_buttons_mapping: &GameButtonsMapping,
+    action_name_or_glob: &str,
+) -> Result<Vec<BindingChain>, Error> {
+    let matcher = Glob::new(action_name_or_glob)
+        .map_err(|err| Error::InvalidGlob(err.to_string()))?
+        .compile_matcher();
+
+    let mut chains = vec![];
+    for (joysticks_mappings, game_device_id) in sticks {
+        // a parent of several virtual buttons is listed once per child
+        let mut physical_buttons: Vec<&PhysicalButton> = joysticks_mappings
+            .map_virtual_button_id_to_parent_physical_buttons
+            .values()
+            .flatten()
+            .collect();
+        physical_buttons
+            .sort_by_key(|physical_button| (physical_button.get_id(), physical_button.get_info()));
+        physical_buttons.dedup();
+
+        for physical_button in physical_buttons {
+            chains.extend(
+                explain_physical_button_object(
+                    joysticks_mappings,
+                    *game_device_id,
+                    Some(game_buttons_mapping),
+                    physical_button,
+                )
+                .into_iter()
+                .filter_map(|mut chain| {
+                    chain
+                        .game_actions
+                        .retain(|action_name| matcher.is_match(action_name));
+                    (!chain.game_actions.is_empty()).then_some(chain)
+                }),
+            );
+        }
+    }
+
+    Ok(chains)
+}
+
+fn explain_physical_button_on_stick(
+    joysticks_mappings: &JoystickButtonsMapping,
+    game_device_id: u8,
+    game_buttons_mapping: Option<&GameButtonsMapping>,
+    info_or_user_desc: &str,
+) -> Result<Vec<BindingChain>, Error> {
+    if let Some(physical_button) = joysticks_mappings.find_physical_button(info_or_user_desc) {
+        return Ok(explain_physical_button_object(
+            joysticks_mappings,
+            game_device_id,
+            game_buttons_mapping,
+            physical_button,
+        ));
+    }
+
+    // a "SHIFT only" button is ONLY in `map_special_buttons`, keyed by info
+    let Some(special_kind) = joysticks_mappings
+        .map_special_buttons
+        .get(info_or_user_desc)
+    else {
+        return Err(Error::ButtonNotFound {
+            info_or_user_desc: info_or_user_desc.to_string(),
+        });
+    };
+
+    Ok(vec![BindingChain {
+        game_device_id,
+        physical_button_id: find_physical_button_id_from_info(
+            joysticks_mappings,
+            info_or_user_desc,
+        )
+        .unwrap_or_default(),
+        info: info_or_user_desc.to_string(),
+        user_desc: String::new(),
+        modifier_path: get_modifier_name(special_kind),
+        virtual_button_id: None,
+        game_input: String::new(),
+        game_actions: vec![],
+    }])
+}
+
+/// ALL the chains of `physical_button`: its modifier role if any, then each of its children virtual buttons
+fn explain_physical_button_object(
+    joysticks_mappings: &JoystickButtonsMapping,
+    game_device_id: u8,
+    game_buttons_mapping: Option<&GameButtonsMapping>,
+    physical_button: &PhysicalButton,
+) -> Vec<BindingChain> {
+    let new_chain = |modifier_path, virtual_button_id, game_input, game_actions| BindingChain {
+        game_device_id,
+        physical_button_id: *physical_button.get_id(),
+        info: physical_button.get_info().clone(),
+        user_desc: physical_button.get_user_desc().clone(),
+        modifier_path,
+        virtual_button_id,
+        game_input,
+        game_actions,
+    };
+
+    let special_kind = match physical_button.get_kind() {
+        PhysicalButtonKind::Shift1 => Some(SpecialButtonKind::Shift1),
+        PhysicalButtonKind::Shift2 => Some(SpecialButtonKind::Shift2),
+        _ => None,
+    };
+    let virtual_buttons = joysticks_mappings
+        .map_physical_button_id_to_children_virtual_buttons
+        .get(physical_button.get_id())
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+
+    special_kind
+        .map(|special_kind| {
+            new_chain(
+                get_modifier_name(&special_kind),
+                None,
+                String::new(),
+                vec![],
+            )
+        })
+        .into_iter()
+        .chain(virtual_buttons.iter().map(|virtual_button| {
+            // ALL the rebinds, NOT only the first one of each action cf `get_rebinds_from_virtual_button_id`
+            let mut game_actions: Vec<String> = game_buttons_mapping
+                .map(|game_buttons_mapping| {
+                    game_buttons_mapping
+                        .get_rebinds_from_virtual_button_id(
+                            *virtual_button.get_id(),
+                            game_device_id,
+                        )
+                        .into_iter()
+                        .map(|(action_name, _)| action_name.clone())
+                        .collect()
+                })
+                .unwrap_or_default();
+            game_actions.dedup();
+            new_chain(
+                get_modifier_path_with_shift_buttons(joysticks_mappings, &virtual_button.kind),
+                Some(*virtual_button.get_id()),
+                format!("js{game_device_id}_button{}", virtual_button.get_id()),
+                game_actions,
+            )
+        }))
+        .collect()
+}
+
+/// eg "SHIFT1 modifier"
+fn get_modifier_name(special_kind: &SpecialButtonKind) -> String {
+    match special_kind {
+        SpecialButtonKind::Shift1 => "SHIFT1 modifier".to_string(),
+        SpecialButtonKind::Shift2 => "SHIFT2 modifier".to_string(),
+    }
+}
+
+fn find_physical_button_id_from_info(
+    joysticks_mappings: &JoystickButtonsMapping,
+    info: &str,
+) -> Option<u8> {
+    joysticks_mappings
+        .map_physical_button_id_to_infos
+        .iter()
+        .find(|(_, infos)| infos.iter().any(|other_info| other_info == info))
+        .map(|(physical_button_id, _)| *physical_button_id)
+}
+
+/// Same as `VirtualButtonKind::get_modifier_path` but with the SHIFT button(s) of the stick
+/// eg "SHIFT1 (D1) + press"
+fn get_modifier_path_with_shift_buttons(
+    joysticks_mappings: &JoystickButtonsMapping,
+    kind: &VirtualButtonKind,
+) -> String {
+    let VirtualButtonKind::Momentary(Some(shift_kind)) = kind else {
+        return kind.get_modifier_path().to_string();
+    };
+    let shift_name = match shift_kind {
+        VirtualShiftKind::Shift1 => "SHIFT1",
+        VirtualShiftKind::Shift2 => "SHIFT2",
+    };
+
+    let shift_buttons_infos = joysticks_mappings.get_shift_buttons_infos(shift_kind);
+    if shift_buttons_infos.is_empty() {
+        format!("{shift_name} (undefined) + press")
+    } else {
+        format!(
+            "{shift_name} {} + press",
+            shift_buttons_infos
+                .iter()
+                .map(|info| info.as_str())
+                .collect::<Vec<_>>()
+                .join("/")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_report_l_and_game_mapping() -> (JoystickButtonsMapping, GameButtonsMapping) {
+        let joysticks_mappings = crate::vkb::parse_and_check_vkb_both_sticks(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/vkb_report_L.fp3").as_ref(),
+            &None,
+            None,
+        )
+        .unwrap();
+        let game_buttons_mapping = crate::sc::parse_keybind_xml::parse_keybind(
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/data/layout_vkb_exported.xml"
+            )
+            .into(),
+            None,
+        )
+        .unwrap();
+
+        (joysticks_mappings, game_buttons_mapping)
+    }
+
+    #[test]
+    fn test_explain_physical_button() {
+        let (joysticks_mappings, game_buttons_mapping) = get_report_l_and_game_mapping();
+
+        let chains = explain_physical_button(
+            &[(&joysticks_mappings, 2)],
+            Some(&game_buttons_mapping),
+            "(A2)",
+        )
+        .unwrap();
+
+        assert!(!chains.is_empty());
+        for chain in &chains {
+            assert_eq!(chain.info, "(A2)");
+            assert_eq!(chain.game_device_id, 2);
+        }
+        assert!(chains
+            .iter()
+            .any(|chain| chain.game_input.starts_with("js2_button")
+                && !chain.game_actions.is_empty()));
+
+        assert!(matches!(
+            explain_physical_button(&[(&joysticks_mappings, 2)], None, "NOT A BUTTON"),
+            Err(Error::ButtonNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_find_physical_buttons_for_action() {
+        let (joysticks_mappings, game_buttons_mapping) = get_report_l_and_game_mapping();
+        let sticks = [(&joysticks_mappings, 2)];
+
+        let forward =
+            explain_physical_button(&sticks, Some(&game_buttons_mapping), "(A2)").unwrap();
+        let action_name = forward
+            .iter()
+            .find_map(|chain| chain.game_actions.first())
+            .unwrap();
+
+        // exact name: "(A2)" MUST be in the results
+        let chains =
+            find_physical_buttons_for_action(&sticks, &game_buttons_mapping, action_name).unwrap();
+        assert!(chains.iter().any(|chain| chain.info == "(A2)"));
+        for chain in &chains {
+            assert_eq!(chain.game_actions, vec![action_name.clone()]);
+        }
+
+        // glob: at least as many as the exact name
+        let glob = format!("{}*", &action_name[..3]);
+        let chains_glob =
+            find_physical_buttons_for_action(&sticks, &game_buttons_mapping, &glob).unwrap();
+        assert!(chains_glob.len() >= chains.len());
+
+        assert!(matches!(
+            find_physical_buttons_for_action(&sticks, &game_buttons_mapping, "v_[weapon"),
+            Err(Error::InvalidGlob(_))
+        ));
+    }
+
+    #[test]
+    fn test_find_physical_buttons_for_action_empty_info() {
+        let (joysticks_mappings, game_buttons_mapping) = get_report_l_and_game_mapping();
+        let sticks = [(&joysticks_mappings, 2)];
+
+        // #4 and #65 have NO info; NOT mistaken for another button without info
+        for (action_name, virtual_button_id) in [
+            ("v_transform_cycle", 4),
+            ("v_target_cycle_in_view_reset", 65),
+        ] {
+            let chains =
+                find_physical_buttons_for_action(&sticks, &game_buttons_mapping, action_name)
+                    .unwrap();
+            assert_eq!(
+                chains
+                    .iter()
+                    .map(|chain| (chain.info.as_str(), chain.virtual_button_id))
+                    .collect::<Vec<_>>(),
+                vec![("", Some(virtual_button_id))],
+                "{action_name}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_find_physical_buttons_for_action_secondary_rebind() {
+        let joysticks_mappings = crate::vkb::parse_and_check_vkb_both_sticks(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/vkb_report_L.fp3").as_ref(),
+            &None,
+            None,
+        )
+        .unwrap();
+        // "v_attack_group2": js2_button108 then js2_button100 ie (A2) and SHIFT1 + (A2)
+        let game_buttons_mapping = crate::sc::parse_keybind_xml::parse_keybind(
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/data/layout_activation_modes.xml"
+            )
+            .into(),
+            None,
+        )
+        .unwrap();
+
+        let chains = find_physical_buttons_for_action(
+            &[(&joysticks_mappings, 2)],
+            &game_buttons_mapping,
+            "v_attack_group2",
+        )
+        .unwrap();
+        let mut virtual_button_ids: Vec<Option<u8>> =
+            chains.iter().map(|chain| chain.virtual_button_id).collect();
+        virtual_button_ids.sort_unstable();
+        assert_eq!(virtual_button_ids, vec![Some(100), Some(108)]);
+        assert!(chains.iter().all(|chain| chain.info == "(A2)"));
+    }
+}
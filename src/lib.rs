@@ -14,6 +14,8 @@ use std::num::{ParseIntError, TryFromIntError};
 use thiserror::Error;
 
 mod button;
+mod checks;
+mod explain;
 mod sc;
 mod template_gen;
 mod vkb;
@@ -44,9 +46,37 @@ pub enum Error {
     ReadError { err: std::io::Error },
     #[error("deserialization error")]
     DeError { err: DeError },
+    #[error("unsupported report format : `{0}`")]
+    UnsupportedReportFormat(String),
+    #[error("unsupported report language (only English for now), header : `{0}`")]
+    UnsupportedReportLanguage(String),
+    #[error("report parsing error at byte {offset} : `{msg}`")]
+    ReportParsing { offset: usize, msg: String },
+    #[error("invalid plan request : `{0}`")]
+    InvalidPlanRequest(String),
+    #[error("not enough free virtual buttons (needed {needed}, available {available})")]
+    NotEnoughFreeVirtualButtons { needed: usize, available: usize },
+    #[error("invalid user provided data : {0:?}")]
+    InvalidUserProvidedData(Vec<String>),
+    #[error("invalid glob : `{0}`")]
+    InvalidGlob(String),
+    #[error("unknown theme : `{0}`")]
+    UnknownTheme(String),
+    #[error("invalid layout : {0:?}")]
+    InvalidLayout(Vec<String>),
+    #[error("invalid boxes pattern : {0}")]
+    InvalidBoxesPattern(String),
 }
 
 /// Re-export
+pub use checks::consistency::check_consistency;
+pub use checks::duplicates::find_duplicated_virtual_buttons as check_duplicated_virtual_buttons;
+pub use checks::lint::lint as check_lint;
+pub use explain::{explain_physical_button, find_physical_buttons_for_action, BindingChain};
 pub use sc::parse_keybind_xml::parse_keybind as sc_parse_keybind;
-pub use template_gen::generate_template;
+pub use template_gen::{
+    generate_auto_layout, generate_composite_template, generate_pdf, generate_template,
+    Orientation, OutputFormat, PaperSize, PdfOptions, PdfSheet,
+};
 pub use vkb::parse_and_check_vkb_both_sticks as vkb_parse_and_check_both_sticks;
+pub use vkb::vkb_planner::plan_virtual_buttons as vkb_plan_virtual_buttons;
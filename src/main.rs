@@ -1,5 +1,5 @@
-use clap::Parser;
-use sc_keymap_rs::Error;
+use clap::{Parser, Subcommand};
+use sc_keymap_rs::{Error, Orientation, OutputFormat, PaperSize};
 use std::path::PathBuf;
 
 /// https://github.com/J-F-Liu/lopdf/blob/master/examples/extract_toc.rs
@@ -51,6 +51,11 @@ pub struct Args {
     #[clap(long)]
     pub vkb_user_provided_data_path: Option<PathBuf>,
 
+    /// Optional side of the stick eg "L" or "R"
+    /// Only used to select the rows of --vkb-user-provided-data-path with a "Side" column
+    #[clap(long)]
+    pub vkb_side: Option<String>,
+
     /// Optional path to a "vkb_template_params.json" cf `TemplateJsonParamaters`
     /// NOTE: side-specific!
     #[clap(long)]
@@ -61,9 +66,116 @@ pub struct Args {
     #[clap(long)]
     pub game_device_id: Option<u8>,
 
+    /// The format of the generated template; the extension of "path_to_output_png" is replaced accordingly
+    #[clap(long, value_enum, default_value_t)]
+    pub output_format: OutputFormat,
+
+    /// Optional colors of the generated template: "dark" (default), "light" (or "print"), "high-contrast",
+    /// "colour-blind", or the path to a theme json
+    /// NOTE: has priority over the "theme" of --vkb-template-params-path
+    #[clap(long)]
+    pub theme: Option<String>,
+
+    /// Refuse to generate the template when its layout has issues: boxes overlapping or out of the sheet,
+    /// a connector over the box of another button...
+    /// NOTE: without it, they are only logged (RUST_LOG=warn)
+    #[clap(long)]
+    pub strict: bool,
+
     /// Optional pretty print output.
     #[clap(short, long)]
     pub pretty: bool,
+
+    /// Default (no subcommand): generate the template
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Propose free virtual button IDs for new SHIFT/TEMPO lines, as a step-by-step list for VKBDevCfg
+    /// eg plan "add SHIFT1 and SHIFT2 to (A2), make (F1) a Tempo3"
+    /// NOTE: requires --vkb-report-path; with --sc-mapping and --game-device-id the IDs bound in-game are avoided
+    Plan { request: String },
+    /// List the virtual buttons reached from more than one physical button, with the game actions bound to them
+    /// NOTE: requires --vkb-report-path; the game actions are only listed with --sc-mapping and --game-device-id
+    Duplicates,
+    /// Cross-check the stick against the game mapping: dead binds, free slots,
+    /// virtual buttons above the number of logical buttons, SHIFT layers without a SHIFT button
+    /// NOTE: requires --vkb-report-path, --sc-mapping and --game-device-id
+    Consistency {
+        /// Print the report as json instead of text
+        #[clap(long)]
+        json: bool,
+    },
+    /// Flag the binds which are valid but almost always a mistake eg a VKB Tempo bound in-game with activationMode="hold",
+    /// or the same action on the base layer and the SHIFT layer of a button
+    /// NOTE: requires --vkb-report-path, --sc-mapping and --game-device-id
+    Lint {
+        /// Print the findings as json instead of text
+        #[clap(long)]
+        json: bool,
+    },
+    /// Explain the chain physical button -> virtual button -> game input -> game actions
+    /// eg explain "(A2)" ; or the reverse with --action eg explain --action "v_weapon_*"
+    /// NOTE: requires --vkb-report-path and --game-device-id; --action also requires --sc-mapping
+    Explain {
+        /// eg "(A2)" or a user provided description; with --action: a game action name or glob
+        query: String,
+        /// `query` is a game action name or glob: list ALL the physical buttons reaching it
+        #[clap(long)]
+        action: bool,
+        /// Optional: the other stick, to explain both at once
+        #[clap(long)]
+        other_vkb_report_path: Option<PathBuf>,
+        /// cf --vkb-side
+        #[clap(long)]
+        other_vkb_side: Option<String>,
+        /// cf --game-device-id; REQUIRED with --other-vkb-report-path
+        #[clap(long)]
+        other_game_device_id: Option<u8>,
+    },
+    /// Generate a printable pdf: one page per stick + a legend page
+    /// eg pdf sheets.pdf --paper letter --other-vkb-report-path R.fp3 --other-vkb-template-params-path params_right.json --other-game-device-id 1
+    /// NOTE: requires --vkb-report-path, --sc-mapping, --vkb-template-params-path and --game-device-id
+    Pdf {
+        output_pdf_path: PathBuf,
+        #[clap(long, value_enum, default_value_t)]
+        paper: PaperSize,
+        #[clap(long, value_enum, default_value_t)]
+        orientation: Orientation,
+        #[clap(long, default_value_t = 10.0)]
+        margin_mm: f32,
+        /// The sheets are printed at this resolution; shrunk if they do not fit in the margins
+        #[clap(long, default_value_t = 300.0)]
+        dpi: f32,
+        /// Optional: the other stick, on its own page
+        #[clap(long)]
+        other_vkb_report_path: Option<PathBuf>,
+        /// cf --vkb-side
+        #[clap(long)]
+        other_vkb_side: Option<String>,
+        /// cf --vkb-template-params-path; REQUIRED with --other-vkb-report-path
+        #[clap(long)]
+        other_vkb_template_params_path: Option<PathBuf>,
+        /// cf --game-device-id; REQUIRED with --other-vkb-report-path
+        #[clap(long)]
+        other_game_device_id: Option<u8>,
+        /// Do NOT add the legend page
+        #[clap(long)]
+        no_legend: bool,
+    },
+    /// Place the boxes of the button groups given only by their anchor ie without
+    /// "desired_box_position_relative_to_center_full_png" or "connector_end_line_position_relative_to_center_full_png",
+    /// and write the completed template params, to fine-tune by hand
+    /// eg auto-layout params_left_placed.json
+    /// NOTE: requires --vkb-template-params-path; the groups already placed are kept as-is
+    AutoLayout { output_json_path: PathBuf },
+    /// Generate ONE sheet with several devices eg both sticks of a HOSAS setup; each with its own report,
+    /// template params, game device id and region, listed in a json cf "data/composite_sheet_hosas.json"
+    /// eg composite data/composite_sheet_hosas.json --output-format svg
+    /// NOTE: requires --sc-mapping; --vkb-report-path, --vkb-template-params-path and --game-device-id are NOT used
+    Composite { composite_params_path: PathBuf },
 }
 
 impl Args {
@@ -103,9 +215,11 @@ fn main() -> Result<(), Error> {
 
     let joysticks_mappings = match &args.vkb_report_path {
         Some(vkb_report_path) => sc_keymap_rs::vkb_parse_and_check_both_sticks(
-            vkb_report_path.clone(),
+            vkb_report_path,
             &args.vkb_user_provided_data_path,
+            args.vkb_side.as_deref(),
         )
+        .map_err(|err| println!("ERROR : could not parse the vkb report : {err}"))
         .ok(),
         None => {
             println!("SKIP : no vkb_reports_paths given");
@@ -113,6 +227,247 @@ fn main() -> Result<(), Error> {
         }
     };
 
+    ////////////////////////////////////////////////////////////////////////////
+    // Subcommands: they only need the mappings, NOT the template
+    // (except auto-layout: ONLY the template; and composite: its own devices)
+
+    if let Some(Command::AutoLayout { output_json_path }) = &args.command {
+        let vkb_template_params_path = args.vkb_template_params_path.as_ref().ok_or_else(|| {
+            Error::Other("auto-layout: missing --vkb-template-params-path".to_string())
+        })?;
+        sc_keymap_rs::generate_auto_layout(vkb_template_params_path, output_json_path)?;
+        println!("template params written to {}", output_json_path.display());
+        return Ok(());
+    }
+
+    if let Some(Command::Composite {
+        composite_params_path,
+    }) = &args.command
+    {
+        let game_buttons_mapping = game_buttons_mapping.as_ref().ok_or_else(|| {
+            Error::Other("composite: missing or invalid --sc-mapping".to_string())
+        })?;
+        sc_keymap_rs::generate_composite_template(
+            game_buttons_mapping,
+            composite_params_path,
+            &args.vkb_user_provided_data_path,
+            args.output_format,
+            args.theme.as_deref(),
+            args.strict,
+        )?;
+        return Ok(());
+    }
+
+    if let Some(command) = &args.command {
+        let joysticks_mappings = joysticks_mappings.ok_or_else(|| {
+            Error::Other(format!("{command:?}: missing or invalid --vkb-report-path"))
+        })?;
+
+        match command {
+            Command::Plan { request } => {
+                let steps = sc_keymap_rs::vkb_plan_virtual_buttons(
+                    &joysticks_mappings,
+                    game_buttons_mapping.as_ref(),
+                    args.game_device_id,
+                    request,
+                )?;
+                for (i, step) in steps.iter().enumerate() {
+                    println!("{}. {step}", i + 1);
+                }
+            }
+            Command::Duplicates => {
+                let duplicated_virtual_buttons = sc_keymap_rs::check_duplicated_virtual_buttons(
+                    &joysticks_mappings,
+                    game_buttons_mapping.as_ref(),
+                    args.game_device_id,
+                );
+                if duplicated_virtual_buttons.is_empty() {
+                    println!("no duplicated virtual button");
+                }
+                for duplicated_virtual_button in duplicated_virtual_buttons {
+                    print!("{duplicated_virtual_button}");
+                }
+            }
+            Command::Consistency { json } => {
+                let game_buttons_mapping = game_buttons_mapping.as_ref().ok_or_else(|| {
+                    Error::Other("consistency: missing or invalid --sc-mapping".to_string())
+                })?;
+                let game_device_id = args.game_device_id.ok_or_else(|| {
+                    Error::Other("consistency: missing --game-device-id".to_string())
+                })?;
+                let report = sc_keymap_rs::check_consistency(
+                    &joysticks_mappings,
+                    game_buttons_mapping,
+                    game_device_id,
+                );
+                if *json {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&report)
+                            .map_err(|err| Error::Other(err.to_string()))?
+                    );
+                } else {
+                    print!("{report}");
+                }
+            }
+            Command::Lint { json } => {
+                let game_buttons_mapping = game_buttons_mapping.as_ref().ok_or_else(|| {
+                    Error::Other("lint: missing or invalid --sc-mapping".to_string())
+                })?;
+                let game_device_id = args
+                    .game_device_id
+                    .ok_or_else(|| Error::Other("lint: missing --game-device-id".to_string()))?;
+                let findings = sc_keymap_rs::check_lint(
+                    &joysticks_mappings,
+                    game_buttons_mapping,
+                    game_device_id,
+                );
+                if *json {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&findings)
+                            .map_err(|err| Error::Other(err.to_string()))?
+                    );
+                } else {
+                    if findings.is_empty() {
+                        println!("no lint finding");
+                    }
+                    for finding in findings {
+                        print!("{finding}");
+                    }
+                }
+            }
+            Command::Explain {
+                query,
+                action,
+                other_vkb_report_path,
+                other_vkb_side,
+                other_game_device_id,
+            } => {
+                let game_device_id = args
+                    .game_device_id
+                    .ok_or_else(|| Error::Other("explain: missing --game-device-id".to_string()))?;
+                let other_joysticks_mappings = match other_vkb_report_path {
+                    Some(other_vkb_report_path) => Some((
+                        sc_keymap_rs::vkb_parse_and_check_both_sticks(
+                            other_vkb_report_path,
+                            &args.vkb_user_provided_data_path,
+                            other_vkb_side.as_deref(),
+                        )?,
+                        other_game_device_id.ok_or_else(|| {
+                            Error::Other("explain: missing --other-game-device-id".to_string())
+                        })?,
+                    )),
+                    None => None,
+                };
+                let sticks: Vec<_> = std::iter::once((&joysticks_mappings, game_device_id))
+                    .chain(
+                        other_joysticks_mappings
+                            .as_ref()
+                            .map(|(mappings, game_device_id)| (mappings, *game_device_id)),
+                    )
+                    .collect();
+
+                let chains = if *action {
+                    let game_buttons_mapping = game_buttons_mapping.as_ref().ok_or_else(|| {
+                        Error::Other(
+                            "explain --action: missing or invalid --sc-mapping".to_string(),
+                        )
+                    })?;
+                    sc_keymap_rs::find_physical_buttons_for_action(
+                        &sticks,
+                        game_buttons_mapping,
+                        query,
+                    )?
+                } else {
+                    sc_keymap_rs::explain_physical_button(
+                        &sticks,
+                        game_buttons_mapping.as_ref(),
+                        query,
+                    )?
+                };
+                if chains.is_empty() {
+                    println!("nothing found for \"{query}\"");
+                }
+                for chain in chains {
+                    println!("{chain}");
+                }
+            }
+            Command::Pdf {
+                output_pdf_path,
+                paper,
+                orientation,
+                margin_mm,
+                dpi,
+                other_vkb_report_path,
+                other_vkb_side,
+                other_vkb_template_params_path,
+                other_game_device_id,
+                no_legend,
+            } => {
+                let game_buttons_mapping = game_buttons_mapping.as_ref().ok_or_else(|| {
+                    Error::Other("pdf: missing or invalid --sc-mapping".to_string())
+                })?;
+                let mut sheets = vec![sc_keymap_rs::PdfSheet {
+                    game_buttons_mapping,
+                    joysticks_mappings: &joysticks_mappings,
+                    json_template_params_path: args.vkb_template_params_path.as_ref().ok_or_else(
+                        || Error::Other("pdf: missing --vkb-template-params-path".to_string()),
+                    )?,
+                    game_device_id: args
+                        .game_device_id
+                        .ok_or_else(|| Error::Other("pdf: missing --game-device-id".to_string()))?,
+                }];
+
+                let other_joysticks_mappings = match other_vkb_report_path {
+                    Some(other_vkb_report_path) => {
+                        Some(sc_keymap_rs::vkb_parse_and_check_both_sticks(
+                            other_vkb_report_path,
+                            &args.vkb_user_provided_data_path,
+                            other_vkb_side.as_deref(),
+                        )?)
+                    }
+                    None => None,
+                };
+                if let Some(other_joysticks_mappings) = &other_joysticks_mappings {
+                    sheets.push(sc_keymap_rs::PdfSheet {
+                        game_buttons_mapping,
+                        joysticks_mappings: other_joysticks_mappings,
+                        json_template_params_path: other_vkb_template_params_path
+                            .as_ref()
+                            .ok_or_else(|| {
+                                Error::Other(
+                                    "pdf: missing --other-vkb-template-params-path".to_string(),
+                                )
+                            })?,
+                        game_device_id: other_game_device_id.ok_or_else(|| {
+                            Error::Other("pdf: missing --other-game-device-id".to_string())
+                        })?,
+                    });
+                }
+
+                sc_keymap_rs::generate_pdf(
+                    &sheets,
+                    &sc_keymap_rs::PdfOptions {
+                        paper_size: *paper,
+                        orientation: *orientation,
+                        margin_mm: *margin_mm,
+                        dpi: *dpi,
+                    },
+                    !no_legend,
+                    output_pdf_path,
+                    args.theme.as_deref(),
+                    args.strict,
+                )?;
+                println!("pdf written to {}", output_pdf_path.display());
+            }
+            // cf above: they do NOT need the mappings of --vkb-report-path
+            Command::AutoLayout { .. } | Command::Composite { .. } => {}
+        }
+
+        return Ok(());
+    }
+
     ////////////////////////////////////////////////////////////////////////////
     // Last step:
     // We have the ONE game mappings, and the many devices mappings
@@ -131,6 +486,9 @@ fn main() -> Result<(), Error> {
                     .vkb_template_params_path
                     .expect("missing --vkb-template-params-path"),
                 game_device_id,
+                args.output_format,
+                args.theme.as_deref(),
+                args.strict,
             )?;
         }
         _ => {
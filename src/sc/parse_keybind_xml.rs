@@ -2,15 +2,35 @@
 
 use std::{collections::HashMap, path::PathBuf};
 
+use globset::{Glob, GlobSetBuilder};
 use serde::Deserialize;
 
 use crate::Error;
 
 /// Maps eg "<rebind input="js1_button2"/>"
+/// or "<rebind input="js1_button2" activationMode="double_tap" multiTap="2"/>"
 #[derive(Deserialize, Debug)]
 struct XmlRebindInput {
     #[serde(rename = "@input")]
     input: String,
+    #[serde(rename = "@activationMode", default)]
+    activation_mode: Option<String>,
+    #[serde(rename = "@multiTap", default)]
+    multi_tap: Option<u8>,
+}
+
+/// ONE joystick rebind of an action, with how the game triggers it
+/// NOTE: "`activation_mode`" is NOT given when it is the default for this action
+#[derive(PartialEq, Debug, Clone)]
+pub(crate) struct GameRebind {
+    /// eg "`js1_button2`"
+    pub(crate) input: String,
+    /// eg "`double_tap`", "hold", "`delayed_press`"
+    pub(crate) activation_mode: Option<String>,
+    /// eg "2" for a double tap
+    pub(crate) multi_tap: Option<u8>,
+    /// eg "`spaceship_weapons`"
+    pub(crate) actionmap: String,
 }
 
 /// Maps eg
@@ -47,7 +67,7 @@ struct XmlActionName {
 #[derive(Deserialize, Debug)]
 struct XmlActionMap {
     #[serde(rename = "@name")]
-    _name: String,
+    name: String,
     #[serde(rename = "$value")]
     action: Vec<XmlActionName>,
 }
@@ -99,8 +119,17 @@ pub struct GameButtonsMapping {
     /// (eg same from left stick and right stick) or maybe the same button X is used for two different things
     /// in "flight mode" vs "driving mode", etc
     /// It could also do two different functions in game based on long/short/double press but we can't see it from
-    /// the exported keybinds; eg "v_toggle_quantum_mode" + "v_toggle_qdrive_engagement" are using the same key
+    /// the exported keybinds; eg "`v_toggle_quantum_mode`" + "`v_toggle_qdrive_engagement`" are using the same key
     map_virtual_button_to_actions: HashMap<String, Vec<String>>,
+    /// ALL the joystick rebinds of each action, with their activation mode, etc
+    /// cf `checks::lint`
+    map_action_to_rebinds: HashMap<String, Vec<GameRebind>>,
+    /// Same keys and order as `map_virtual_button_to_actions`: the actionmap of each action eg "`spaceship_missiles`"
+    /// NOTE: per bind, NOT per action; the same action can be in several actionmaps eg "`v_attack_group2`"
+    /// cf `filter_actionmaps`
+    map_virtual_button_to_actionmaps: HashMap<String, Vec<String>>,
+    /// ALL the actionmaps of the xml, in order; even those without any bind
+    actionmaps: Vec<String>,
 }
 
 impl GameButtonsMapping {
@@ -126,10 +155,147 @@ impl GameButtonsMapping {
             None => None,
         }
     }
+
+    /// [Star Citizen] specific:
+    /// ALL the (action, rebind) on "`js{joystick_id}_button{virtual_button_id}`", sorted by action
+    /// Same actions as `get_action_from_virtual_button_id`, but with the rebind eg for its activation mode
+    pub(crate) fn get_rebinds_from_virtual_button_id(
+        &self,
+        virtual_button_id: u8,
+        joystick_id: u8,
+    ) -> Vec<(&String, &GameRebind)> {
+        let input = format!("js{joystick_id}_button{virtual_button_id}");
+        let mut rebinds: Vec<(&String, &GameRebind)> = self
+            .map_action_to_rebinds
+            .iter()
+            .flat_map(|(action_name, rebinds)| {
+                rebinds
+                    .iter()
+                    .filter(|rebind| rebind.input == input)
+                    .map(move |rebind| (action_name, rebind))
+            })
+            .collect();
+        rebinds.sort_by_key(|(action_name, _)| *action_name);
+
+        rebinds
+    }
+
+    /// [Star Citizen] specific:
+    /// ALL the "`js{joystick_id}_button{virtual_button_id}`" bound in-game, sorted by virtual button id
+    /// The actions are sorted and deduplicated.
+    /// NOTE: from ALL the joystick rebinds of each action cf `get_rebinds_from_virtual_button_id`
+    pub(crate) fn get_all_virtual_buttons_bound(&self, joystick_id: u8) -> Vec<(u8, Vec<String>)> {
+        let prefix = format!("js{joystick_id}_button");
+        let mut map_virtual_button_id_to_actions: HashMap<u8, Vec<String>> = HashMap::new();
+        for (action_name, rebinds) in &self.map_action_to_rebinds {
+            for rebind in rebinds {
+                let Some(virtual_button_id) = rebind
+                    .input
+                    .strip_prefix(&prefix)
+                    .and_then(|virtual_button_id| virtual_button_id.parse().ok())
+                else {
+                    continue;
+                };
+                map_virtual_button_id_to_actions
+                    .entry(virtual_button_id)
+                    .or_default()
+                    .push(action_name.clone());
+            }
+        }
+
+        let mut virtual_buttons_bound: Vec<(u8, Vec<String>)> = map_virtual_button_id_to_actions
+            .into_iter()
+            .map(|(virtual_button_id, mut actions_names)| {
+                actions_names.sort();
+                actions_names.dedup();
+                (virtual_button_id, actions_names)
+            })
+            .collect();
+        virtual_buttons_bound.sort_by_key(|(virtual_button_id, _)| *virtual_button_id);
+
+        virtual_buttons_bound
+    }
+
+    /// ONLY the binds of the actionmaps matching ANY of `actionmaps_globs` eg `["spaceship_*", "seat_general"]`
+    /// The order of the actions on each input is kept
+    ///
+    /// # Errors
+    /// - `Error::InvalidGlob` if one of `actionmaps_globs` is not a valid glob
+    /// - the actions and the actionmaps of an input do NOT match
+    pub(crate) fn filter_actionmaps(&self, actionmaps_globs: &[String]) -> Result<Self, Error> {
+        let mut builder = GlobSetBuilder::new();
+        for actionmap_glob in actionmaps_globs {
+            builder
+                .add(Glob::new(actionmap_glob).map_err(|err| Error::InvalidGlob(err.to_string()))?);
+        }
+        let matcher = builder
+            .build()
+            .map_err(|err| Error::InvalidGlob(err.to_string()))?;
+
+        let mut map_virtual_button_to_actions = HashMap::new();
+        let mut map_virtual_button_to_actionmaps = HashMap::new();
+        for (logical_button_name, actions_names) in &self.map_virtual_button_to_actions {
+            // NOTE: both are filled together by `parse_keybind`; a mismatch is a bug, NOT something to skip
+            let actionmaps = self
+                .map_virtual_button_to_actionmaps
+                .get(logical_button_name)
+                .ok_or_else(|| {
+                    Error::Other(format!(
+                        "filter_actionmaps: NO actionmaps for {logical_button_name}"
+                    ))
+                })?;
+            if actionmaps.len() != actions_names.len() {
+                return Err(Error::Other(format!(
+                    "filter_actionmaps: {} actions but {} actionmaps for {logical_button_name}",
+                    actions_names.len(),
+                    actionmaps.len()
+                )));
+            }
+            let (actions_names, actionmaps): (Vec<String>, Vec<String>) = actions_names
+                .iter()
+                .zip(actionmaps)
+                .filter(|(_, actionmap)| matcher.is_match(actionmap))
+                .map(|(action_name, actionmap)| (action_name.clone(), actionmap.clone()))
+                .unzip();
+            if !actions_names.is_empty() {
+                map_virtual_button_to_actions.insert(logical_button_name.clone(), actions_names);
+                map_virtual_button_to_actionmaps.insert(logical_button_name.clone(), actionmaps);
+            }
+        }
+
+        Ok(Self {
+            map_virtual_button_to_actions,
+            map_action_to_rebinds: self
+                .map_action_to_rebinds
+                .iter()
+                .filter_map(|(action_name, rebinds)| {
+                    let rebinds: Vec<GameRebind> = rebinds
+                        .iter()
+                        .filter(|rebind| matcher.is_match(&rebind.actionmap))
+                        .cloned()
+                        .collect();
+                    (!rebinds.is_empty()).then(|| (action_name.clone(), rebinds))
+                })
+                .collect(),
+            map_virtual_button_to_actionmaps,
+            actionmaps: self
+                .actionmaps
+                .iter()
+                .filter(|actionmap| matcher.is_match(actionmap))
+                .cloned()
+                .collect(),
+        })
+    }
+
+    /// ALL the actionmaps eg "`spaceship_missiles`", in the order of the xml
+    pub(crate) fn get_actionmaps(&self) -> &[String] {
+        &self.actionmaps
+    }
 }
 
 /// Each key,value in the csv will be in the result Vec:
 /// - (key,value)
+///
 /// That way when checking with `contains` the order does not matter.
 ///
 /// (Yes, the proper way would be to use a map/set)
@@ -151,7 +317,7 @@ fn parse_csv_binding_pairs_to_ignore(
 
             binding_pairs_to_ignore.push((left.to_string(), right.to_string()));
         }
-    };
+    }
 
     Ok(binding_pairs_to_ignore)
 }
@@ -160,6 +326,7 @@ fn parse_csv_binding_pairs_to_ignore(
 ///
 /// # Errors
 ///
+#[allow(clippy::too_many_lines)]
 pub fn parse_keybind(
     xml_path: PathBuf,
     sc_bindings_to_ignore: Option<csv::Reader<std::fs::File>>,
@@ -174,8 +341,14 @@ pub fn parse_keybind(
     log::debug!("keybinds: {:?}", xml_data);
 
     let mut map_virtual_button_to_actions = HashMap::new();
+    let mut map_action_to_rebinds: HashMap<String, Vec<GameRebind>> = HashMap::new();
+    let mut map_virtual_button_to_actionmaps: HashMap<String, Vec<String>> = HashMap::new();
+    let mut actionmaps: Vec<String> = vec![];
 
     for actionmap in &xml_data.actionmap {
+        if !actionmaps.contains(&actionmap.name) {
+            actionmaps.push(actionmap.name.clone());
+        }
         for action in &actionmap.action {
             let action_name = &action.name;
             // IMPORTANT sometimes even with the JOYSTICK exported keybinds we find eg "<rebind input="kb1_ " />"
@@ -189,6 +362,23 @@ pub fn parse_keybind(
             if all_joystick_keybinds.len() > 1 {
                 log::info!("[sc] parse_keybind: more than one key for \"{action_name}\" : {all_joystick_keybinds:?} ");
             }
+            let rebinds: Vec<GameRebind> = all_joystick_keybinds
+                .iter()
+                .filter(|rebind| !rebind.input.trim_end().ends_with('_'))
+                .map(|rebind| GameRebind {
+                    input: rebind.input.clone(),
+                    activation_mode: rebind.activation_mode.clone(),
+                    multi_tap: rebind.multi_tap,
+                    actionmap: actionmap.name.clone(),
+                })
+                .collect();
+            // NOT `insert`: the same action can be in several actionmaps eg "`v_attack_group2`"
+            if !rebinds.is_empty() {
+                map_action_to_rebinds
+                    .entry(action_name.clone())
+                    .or_default()
+                    .extend(rebinds);
+            }
 
             // IMPORTANT sometimes there is ONLY a mouse or keyboard here for some reason...
             // <action name="selectUnarmedCombat">
@@ -200,58 +390,65 @@ pub fn parse_keybind(
                 continue;
             }
 
-            let logical_button_name = &all_joystick_keybinds[0].input;
-
-            // Finally; sometimes the bind is just empty
-            // <rebind input="js2_ " />
-            // -> skip
-            if logical_button_name
-                .split('_')
-                .last()
-                .ok_or_else(|| {
-                    Error::Other("logical_button_name unexpected number of _".to_string())
-                })?
-                .trim()
-                .is_empty()
-            {
-                log::debug!("[sc] parse_keybind: empty key for \"{action_name}\" = \"{logical_button_name}\"");
-                continue;
-            }
-
-            // insert a new vec if needed
-            map_virtual_button_to_actions
-                .entry(logical_button_name.clone())
-                .or_insert(vec![]);
-
-            if let Some(actions) = map_virtual_button_to_actions.get_mut(logical_button_name) {
-                // update the bindings EVEN if duplicated
-                // we still WANT to print them in the final template!
-                actions.push(action_name.clone());
-
-                // SHORTCUT if there is still only one action: we can stop now
-                if actions.len() < 2 {
+            // NOTE: ALL the joystick binds, NOT only the first one; else the 2nd one is "NO BINDING" on the sheet
+            for keybind in all_joystick_keybinds {
+                let logical_button_name = &keybind.input;
+
+                // Finally; sometimes the bind is just empty
+                // <rebind input="js2_ " />
+                // -> skip
+                if logical_button_name
+                    .split('_')
+                    .next_back()
+                    .ok_or_else(|| {
+                        Error::Other("logical_button_name unexpected number of _".to_string())
+                    })?
+                    .trim()
+                    .is_empty()
+                {
+                    log::debug!("[sc] parse_keybind: empty key for \"{action_name}\" = \"{logical_button_name}\"");
                     continue;
                 }
 
-                // first pair: (0, 1)
-                let new_pair1 = (
-                    actions
-                        .first()
-                        .ok_or_else(|| Error::Other("actions is empty".to_string()))?
-                        .to_string(),
-                    action_name.to_string(),
-                );
-                // same pair but inverted (1, 0)
-                let new_pair2 = (new_pair1.1.to_string(), new_pair1.0.to_string());
-
-                if binding_pairs_to_ignore.contains(&new_pair1)
-                    || binding_pairs_to_ignore.contains(&new_pair2)
-                {
-                    log::info!("skipping {new_pair1:?}");
-                } else {
-                    log::warn!(
-                        "keybind duplicated : {logical_button_name} used for : \"{actions:?}\""
+                // insert a new vec if needed
+                map_virtual_button_to_actions
+                    .entry(logical_button_name.clone())
+                    .or_insert(vec![]);
+                map_virtual_button_to_actionmaps
+                    .entry(logical_button_name.clone())
+                    .or_default()
+                    .push(actionmap.name.clone());
+
+                if let Some(actions) = map_virtual_button_to_actions.get_mut(logical_button_name) {
+                    // update the bindings EVEN if duplicated
+                    // we still WANT to print them in the final template!
+                    actions.push(action_name.clone());
+
+                    // SHORTCUT if there is still only one action: we can stop now
+                    if actions.len() < 2 {
+                        continue;
+                    }
+
+                    // first pair: (0, 1)
+                    let new_pair1 = (
+                        actions
+                            .first()
+                            .ok_or_else(|| Error::Other("actions is empty".to_string()))?
+                            .clone(),
+                        action_name.clone(),
                     );
+                    // same pair but inverted (1, 0)
+                    let new_pair2 = (new_pair1.1.clone(), new_pair1.0.clone());
+
+                    if binding_pairs_to_ignore.contains(&new_pair1)
+                        || binding_pairs_to_ignore.contains(&new_pair2)
+                    {
+                        log::info!("skipping {new_pair1:?}");
+                    } else {
+                        log::warn!(
+                            "keybind duplicated : {logical_button_name} used for : \"{actions:?}\""
+                        );
+                    }
                 }
             }
         }
@@ -259,6 +456,9 @@ pub fn parse_keybind(
 
     Ok(GameButtonsMapping {
         map_virtual_button_to_actions,
+        map_action_to_rebinds,
+        map_virtual_button_to_actionmaps,
+        actionmaps,
     })
 
     //TODO? https://github.com/tafia/quick-xml/blob/9fb797e921d83467c89e78de7de6511801f335b1/examples/read_buffered.rs#L10
@@ -297,6 +497,15 @@ mod tests {
         quick_xml::de::from_str::<XmlRebindInput>(xml_str).unwrap();
     }
 
+    #[test]
+    fn test_parse_xml_rebind_input_activation_mode() {
+        let xml_str = r#"<rebind input="js1_button2" activationMode="double_tap" multiTap="2"/>"#;
+
+        let rebind = quick_xml::de::from_str::<XmlRebindInput>(xml_str).unwrap();
+        assert_eq!(rebind.activation_mode.as_deref(), Some("double_tap"));
+        assert_eq!(rebind.multi_tap, Some(2));
+    }
+
     #[test]
     fn test_parse_xml_action_name() {
         let xml_str = r#"
@@ -378,4 +587,116 @@ mod tests {
         )
         .unwrap();
     }
+
+    #[test]
+    fn test_filter_actionmaps() {
+        let game_buttons_mapping = parse_keybind(
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/data/layout_vkb_exported.xml"
+            )
+            .into(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            game_buttons_mapping.get_action_from_virtual_button_id(108, 2),
+            Some(&vec!["v_attack_group2".to_string()])
+        );
+
+        // "v_attack_group2" is ALSO in "vehicle_general", but NOT bound there
+        let vehicle = game_buttons_mapping
+            .filter_actionmaps(&["vehicle_*".to_string()])
+            .unwrap();
+        assert_eq!(vehicle.get_action_from_virtual_button_id(108, 2), None);
+        assert!(vehicle
+            .get_rebinds_from_virtual_button_id(108, 2)
+            .is_empty());
+        assert!(vehicle
+            .get_actionmaps()
+            .iter()
+            .all(|actionmap| actionmap.starts_with("vehicle_")));
+
+        let flight = game_buttons_mapping
+            .filter_actionmaps(&["spaceship_*".to_string(), "seat_general".to_string()])
+            .unwrap();
+        assert_eq!(
+            flight.get_action_from_virtual_button_id(108, 2),
+            Some(&vec!["v_attack_group2".to_string()])
+        );
+        assert_eq!(
+            flight.get_rebinds_from_virtual_button_id(108, 2)[0]
+                .1
+                .actionmap,
+            "spaceship_weapons"
+        );
+
+        assert!(game_buttons_mapping
+            .filter_actionmaps(&["[".to_string()])
+            .is_err());
+    }
+
+    #[test]
+    fn test_parse_keybind_same_action_in_two_actionmaps() {
+        let game_buttons_mapping = parse_keybind(
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/data/layout_same_action_two_actionmaps.xml"
+            )
+            .into(),
+            None,
+        )
+        .unwrap();
+
+        // the rebinds of BOTH actionmaps are kept
+        assert_eq!(
+            game_buttons_mapping.map_action_to_rebinds["v_attack_group2"]
+                .iter()
+                .map(|rebind| (rebind.input.as_str(), rebind.actionmap.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("js2_button108", "spaceship_weapons"),
+                ("js2_button109", "vehicle_general")
+            ]
+        );
+        assert_eq!(
+            game_buttons_mapping
+                .get_rebinds_from_virtual_button_id(109, 2)
+                .len(),
+            1
+        );
+
+        let vehicle = game_buttons_mapping
+            .filter_actionmaps(&["vehicle_*".to_string()])
+            .unwrap();
+        assert!(vehicle
+            .get_rebinds_from_virtual_button_id(108, 2)
+            .is_empty());
+        assert_eq!(vehicle.get_rebinds_from_virtual_button_id(109, 2).len(), 1);
+    }
+
+    #[test]
+    fn test_filter_actionmaps_out_of_sync() {
+        let mut game_buttons_mapping = GameButtonsMapping {
+            map_virtual_button_to_actions: HashMap::from([(
+                "js2_button108".to_string(),
+                vec!["v_attack_group2".to_string(), "v_eject".to_string()],
+            )]),
+            map_action_to_rebinds: HashMap::new(),
+            map_virtual_button_to_actionmaps: HashMap::from([(
+                "js2_button108".to_string(),
+                vec!["spaceship_weapons".to_string()],
+            )]),
+            actionmaps: vec!["spaceship_weapons".to_string()],
+        };
+        let globs = ["spaceship_*".to_string()];
+
+        // NOT silently truncated
+        assert!(game_buttons_mapping.filter_actionmaps(&globs).is_err());
+        // NOT a panic
+        game_buttons_mapping
+            .map_virtual_button_to_actionmaps
+            .clear();
+        assert!(game_buttons_mapping.filter_actionmaps(&globs).is_err());
+    }
 }
@@ -0,0 +1,310 @@
+//! "`composite_sheet.json`": several devices on ONE sheet eg both sticks of a HOSAS setup
+//!
+//! Each device has its own report, template params and game device id, and is laid out EXACTLY like
+//! a sheet of its own cf `build_scene`; then moved to its region of the composite sheet cf `compose_scenes`.
+//! The size of a region is the `sheet.size` of the template params of its device.
+//!
+//! NOTE: ONE theme, ONE set of modes and ONE `layers_layout` for the whole sheet; those of the template params
+//! of the devices are ignored
+//!
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use super::layout;
+use super::params::{LayersLayout, TemplateJsonModeParameters};
+use super::scene::{Scene, SceneElement};
+use super::theme::Theme;
+use crate::Error;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(super) struct CompositeJsonParameters {
+    /// In pixels; width x height
+    pub(super) size: (u32, u32),
+    /// cf `TemplateJsonParamaters::path_to_output_png`
+    /// NOTE: the `path_to_output_png` of the devices are NOT used
+    pub(super) path_to_output_png: PathBuf,
+    /// cf `TemplateJsonParamaters::theme`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) theme: Option<String>,
+    /// cf `TemplateJsonParamaters::modes`
+    /// NOTE: the `modes` of the devices are NOT used
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(super) modes: Vec<TemplateJsonModeParameters>,
+    /// cf `TemplateJsonParamaters::layers_layout`
+    /// NOTE: the `layers_layout` of the devices are NOT used
+    #[serde(default)]
+    pub(super) layers_layout: LayersLayout,
+    /// In drawing order
+    pub(super) devices: Vec<CompositeDeviceParameters>,
+}
+
+/// cf the CLI args of a single sheet eg `--vkb-report-path`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(super) struct CompositeDeviceParameters {
+    pub(super) vkb_report_path: PathBuf,
+    /// cf `--vkb-side`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) vkb_side: Option<String>,
+    pub(super) vkb_template_params_path: PathBuf,
+    pub(super) game_device_id: u8,
+    /// The top left of the region of the device, in the composite sheet
+    pub(super) position: (i32, i32),
+}
+
+/// Parse the "`composite_sheet.json`"
+pub(super) fn read_composite_json_params(path: &Path) -> Result<CompositeJsonParameters, Error> {
+    let file = std::fs::File::open(path).map_err(|_err| {
+        Error::Other(format!(
+            "failed to open composite_params_path {}",
+            path.display()
+        ))
+    })?;
+    let composite_params: CompositeJsonParameters =
+        serde_json::from_reader(std::io::BufReader::new(file)).map_err(|err| {
+            Error::Other(format!("serde_json error for {} : {err}", path.display()))
+        })?;
+    log::debug!("composite_params : {composite_params:?}");
+
+    Ok(composite_params)
+}
+
+/// The `Scene` of a device, cf `compose_scenes`
+pub(super) struct DeviceScene {
+    /// cf `CompositeDeviceParameters::position`
+    pub(super) position: (i32, i32),
+    pub(super) scene: Scene,
+    /// ie `TemplateJsonParamaters::buttons_params.len()`
+    pub(super) groups_count: usize,
+}
+
+/// The background, then each device moved to its region
+/// The button groups are renumbered across ALL the devices, in order; so that `validate` tells them apart
+///
+/// # Errors
+/// - NO device
+/// - a region out of the sheet, or overlapping another one
+pub(super) fn compose_scenes(
+    size: (u32, u32),
+    theme: &Theme,
+    device_scenes: Vec<DeviceScene>,
+) -> Result<Scene, Error> {
+    if device_scenes.is_empty() {
+        return Err(Error::InvalidLayout(vec![
+            "a composite sheet needs at least one device".to_string(),
+        ]));
+    }
+    let issues = get_regions_issues(
+        size,
+        &device_scenes
+            .iter()
+            .map(|device_scene| (device_scene.position, device_scene.scene.size))
+            .collect::<Vec<_>>(),
+    );
+    if !issues.is_empty() {
+        return Err(Error::InvalidLayout(issues));
+    }
+
+    let mut elements: Vec<SceneElement> =
+        layout::layout_background(size, theme).into_iter().collect();
+    let mut first_group_no = 0;
+    for device_scene in device_scenes {
+        elements.extend(
+            device_scene
+                .scene
+                .elements
+                .into_iter()
+                .map(|element| move_element(element, device_scene.position, first_group_no)),
+        );
+        first_group_no += device_scene.groups_count;
+    }
+
+    Ok(Scene { size, elements })
+}
+
+/// The region of a device: (top left, size)
+type Region = ((i32, i32), (u32, u32));
+
+/// Out of `sheet_size`, or overlapping; one region per device
+fn get_regions_issues(sheet_size: (u32, u32), regions: &[Region]) -> Vec<String> {
+    let get_bottom_right = |(position, size): Region| {
+        (
+            i64::from(position.0) + i64::from(size.0),
+            i64::from(position.1) + i64::from(size.1),
+        )
+    };
+
+    let mut issues = vec![];
+    for (index, region) in regions.iter().enumerate() {
+        let bottom_right = get_bottom_right(*region);
+        if region.0 .0 < 0
+            || region.0 .1 < 0
+            || bottom_right.0 > i64::from(sheet_size.0)
+            || bottom_right.1 > i64::from(sheet_size.1)
+        {
+            issues.push(format!(
+                "device #{index} at {:?} of size {:?} is out of the sheet {sheet_size:?}",
+                region.0, region.1
+            ));
+        }
+        for (other_index, other_region) in regions.iter().enumerate().skip(index + 1) {
+            let other_bottom_right = get_bottom_right(*other_region);
+            // touching is fine
+            if i64::from(region.0 .0) < other_bottom_right.0
+                && i64::from(other_region.0 .0) < bottom_right.0
+                && i64::from(region.0 .1) < other_bottom_right.1
+                && i64::from(other_region.0 .1) < bottom_right.1
+            {
+                issues.push(format!("device #{index} overlaps device #{other_index}"));
+            }
+        }
+    }
+
+    issues
+}
+
+/// `element` moved by `offset`, with its group renumbered from `first_group_no`
+fn move_element(element: SceneElement, offset: (i32, i32), first_group_no: usize) -> SceneElement {
+    let move_point = |point: (i32, i32)| (point.0 + offset.0, point.1 + offset.1);
+
+    match element {
+        SceneElement::Image {
+            path,
+            top_left_position,
+            size,
+        } => SceneElement::Image {
+            path,
+            top_left_position: move_point(top_left_position),
+            size,
+        },
+        SceneElement::Box(mut scene_box) => {
+            scene_box.position = move_point(scene_box.position);
+            if let Some(label) = &mut scene_box.label {
+                label.group_no += first_group_no;
+            }
+            SceneElement::Box(scene_box)
+        }
+        SceneElement::Connector(mut connector) => {
+            connector.points = connector.points.into_iter().map(move_point).collect();
+            if let Some(label) = &mut connector.label {
+                label.group_no += first_group_no;
+            }
+            SceneElement::Connector(connector)
+        }
+        SceneElement::Hotspot(mut hotspot) => {
+            hotspot.anchor_position = move_point(hotspot.anchor_position);
+            SceneElement::Hotspot(hotspot)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::params::ConnectorMarker;
+    use super::super::scene::{SceneBox, SceneConnector, SceneLabel};
+    use super::*;
+
+    fn new_device_scene(position: (i32, i32)) -> DeviceScene {
+        let label = SceneLabel {
+            group_no: 0,
+            user_desc: "Red thumb button".to_string(),
+            physical_name: None,
+        };
+        DeviceScene {
+            position,
+            scene: Scene {
+                size: (1000, 500),
+                elements: vec![
+                    SceneElement::Box(SceneBox {
+                        position: (100, 50),
+                        size: (300, 50),
+                        color: image::Rgba([50, 50, 50, 255]),
+                        border_color: image::Rgba([50, 50, 50, 255]),
+                        stroke_thickness: 2,
+                        text: None,
+                        label: Some(label.clone()),
+                    }),
+                    SceneElement::Connector(SceneConnector {
+                        points: vec![(500, 400), (100, 75)],
+                        thickness: 4,
+                        color: image::Rgba([0, 255, 0, 255]),
+                        marker: ConnectorMarker::None,
+                        label: Some(label),
+                    }),
+                ],
+            },
+            groups_count: 1,
+        }
+    }
+
+    #[test]
+    fn test_read_composite_json_params() {
+        let composite_params = read_composite_json_params(
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/data/composite_sheet_hosas.json"
+            )
+            .as_ref(),
+        )
+        .unwrap();
+
+        assert_eq!(composite_params.size, (8000, 2000));
+        assert_eq!(
+            composite_params
+                .devices
+                .iter()
+                .map(|device| (device.game_device_id, device.position))
+                .collect::<Vec<_>>(),
+            vec![(2, (0, 0)), (1, (4000, 0))]
+        );
+    }
+
+    #[test]
+    fn test_compose_scenes() {
+        let scene = compose_scenes(
+            (2000, 500),
+            &Theme::default(),
+            vec![new_device_scene((0, 0)), new_device_scene((1000, 0))],
+        )
+        .unwrap();
+
+        assert_eq!(scene.size, (2000, 500));
+        // 2 per device; NO background ie the default theme is transparent
+        assert_eq!(scene.elements.len(), 4);
+        let SceneElement::Box(scene_box) = &scene.elements[2] else {
+            panic!("expected a box");
+        };
+        assert_eq!(scene_box.position, (1100, 50));
+        assert_eq!(scene_box.label.as_ref().unwrap().group_no, 1);
+        let SceneElement::Connector(connector) = &scene.elements[3] else {
+            panic!("expected a connector");
+        };
+        assert_eq!(connector.points, vec![(1500, 400), (1100, 75)]);
+    }
+
+    #[test]
+    fn test_compose_scenes_invalid_regions() {
+        assert!(compose_scenes((2000, 500), &Theme::default(), vec![]).is_err());
+
+        let Err(Error::InvalidLayout(issues)) = compose_scenes(
+            (2000, 500),
+            &Theme::default(),
+            vec![
+                new_device_scene((0, 0)),
+                new_device_scene((900, 0)),
+                new_device_scene((1500, 0)),
+            ],
+        ) else {
+            panic!("expected an invalid layout");
+        };
+        assert_eq!(
+            issues,
+            vec![
+                "device #0 overlaps device #1".to_string(),
+                "device #1 overlaps device #2".to_string(),
+                "device #2 at (1500, 0) of size (1000, 500) is out of the sheet (2000, 500)"
+                    .to_string(),
+            ]
+        );
+    }
+}
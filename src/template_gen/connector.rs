@@ -0,0 +1,195 @@
+//! The geometry of the connectors: button on the photo -> box
+//!
+//! - `apply_elbow`: used by `layout`; the corners of the legs which are NOT straight
+//! - `get_line_points` and `get_marker_shape`: used by ALL the renderers so the png and the svg match
+//!
+//! NOTE: the line is cut where the arrow starts, so that the tip of the arrow is EXACTLY on the button
+//!
+
+use super::params::{ConnectorElbow, ConnectorMarker};
+use super::scene::SceneConnector;
+
+/// The marker of a connector, in pixels
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum MarkerShape {
+    Dot {
+        center: (f64, f64),
+        radius: f64,
+    },
+    /// The tip ie on the button, then the 2 corners of the base
+    Arrow([(f64, f64); 3]),
+}
+
+/// `points` with a corner inserted in each leg, unless already horizontal/vertical
+/// eg `HorizontalFirst`: (0, 0) -> (100, 50) becomes (0, 0) -> (100, 0) -> (100, 50)
+pub(super) fn apply_elbow(points: &[(i32, i32)], elbow: ConnectorElbow) -> Vec<(i32, i32)> {
+    let mut elbow_points: Vec<(i32, i32)> = points.first().copied().into_iter().collect();
+    for leg in points.windows(2) {
+        let (from, to) = (leg[0], leg[1]);
+        let corner = match elbow {
+            ConnectorElbow::None => None,
+            ConnectorElbow::HorizontalFirst => Some((to.0, from.1)),
+            ConnectorElbow::VerticalFirst => Some((from.0, to.1)),
+        };
+        if let Some(corner) = corner.filter(|corner| *corner != from && *corner != to) {
+            elbow_points.push(corner);
+        }
+        elbow_points.push(to);
+    }
+
+    elbow_points
+}
+
+/// The marker size is relative to the thickness of the line, with a minimum so it is still visible
+fn get_marker_unit(thickness: i32) -> f64 {
+    f64::from(thickness.max(2))
+}
+
+/// `None` if NO marker, or an arrow without a direction ie ALL the points are the same
+pub(super) fn get_marker_shape(connector: &SceneConnector) -> Option<MarkerShape> {
+    let start = to_f64(*connector.points.first()?);
+    let unit = get_marker_unit(connector.thickness);
+
+    match connector.marker {
+        ConnectorMarker::None => None,
+        ConnectorMarker::Dot => Some(MarkerShape::Dot {
+            center: start,
+            radius: 1.5 * unit,
+        }),
+        ConnectorMarker::Arrow => {
+            let direction = get_start_direction(connector)?;
+            let (length, half_width) = (4.0 * unit, 1.5 * unit);
+            let base_center = (
+                start.0 - direction.0 * length,
+                start.1 - direction.1 * length,
+            );
+            let normal = (-direction.1, direction.0);
+            Some(MarkerShape::Arrow([
+                start,
+                (
+                    base_center.0 + normal.0 * half_width,
+                    base_center.1 + normal.1 * half_width,
+                ),
+                (
+                    base_center.0 - normal.0 * half_width,
+                    base_center.1 - normal.1 * half_width,
+                ),
+            ]))
+        }
+    }
+}
+
+/// The polyline to stroke; the same as `connector.points` except with an arrow: it starts at its base
+pub(super) fn get_line_points(connector: &SceneConnector) -> Vec<(f64, f64)> {
+    let mut points: Vec<(f64, f64)> = connector.points.iter().copied().map(to_f64).collect();
+    if let (Some(MarkerShape::Arrow([_, corner_a, corner_b])), Some(first)) =
+        (get_marker_shape(connector), points.first_mut())
+    {
+        *first = (
+            f64::midpoint(corner_a.0, corner_b.0),
+            f64::midpoint(corner_a.1, corner_b.1),
+        );
+    }
+
+    points
+}
+
+/// The unit vector of the first leg, TOWARD the button ie from the box side to `points[0]`
+fn get_start_direction(connector: &SceneConnector) -> Option<(f64, f64)> {
+    let start = to_f64(*connector.points.first()?);
+    connector
+        .points
+        .iter()
+        .map(|point| to_f64(*point))
+        .find(|point| *point != start)
+        .map(|next| {
+            let (dx, dy) = (start.0 - next.0, start.1 - next.1);
+            let length = dx.hypot(dy);
+            (dx / length, dy / length)
+        })
+}
+
+fn to_f64(point: (i32, i32)) -> (f64, f64) {
+    (f64::from(point.0), f64::from(point.1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_connector(points: Vec<(i32, i32)>, marker: ConnectorMarker) -> SceneConnector {
+        SceneConnector {
+            points,
+            thickness: 4,
+            color: image::Rgba([0, 255, 0, 255]),
+            marker,
+            label: None,
+        }
+    }
+
+    #[test]
+    fn test_apply_elbow() {
+        let points = [(0, 0), (100, 50), (100, 200), (300, 250)];
+
+        assert_eq!(apply_elbow(&points, ConnectorElbow::None), points.to_vec());
+        // NO corner for the leg already vertical
+        assert_eq!(
+            apply_elbow(&points, ConnectorElbow::HorizontalFirst),
+            vec![
+                (0, 0),
+                (100, 0),
+                (100, 50),
+                (100, 200),
+                (300, 200),
+                (300, 250)
+            ]
+        );
+        assert_eq!(
+            apply_elbow(&points, ConnectorElbow::VerticalFirst),
+            vec![
+                (0, 0),
+                (0, 50),
+                (100, 50),
+                (100, 200),
+                (100, 250),
+                (300, 250)
+            ]
+        );
+        assert!(apply_elbow(&[], ConnectorElbow::VerticalFirst).is_empty());
+    }
+
+    #[test]
+    fn test_get_marker_shape() {
+        // the box is on the right of the button: the arrow points left
+        let connector = new_connector(vec![(100, 50), (300, 50)], ConnectorMarker::Arrow);
+        assert_eq!(
+            get_marker_shape(&connector),
+            Some(MarkerShape::Arrow([
+                (100.0, 50.0),
+                (116.0, 44.0),
+                (116.0, 56.0)
+            ]))
+        );
+        assert_eq!(
+            get_line_points(&connector),
+            vec![(116.0, 50.0), (300.0, 50.0)]
+        );
+
+        let connector = new_connector(vec![(100, 50), (300, 50)], ConnectorMarker::Dot);
+        assert_eq!(
+            get_marker_shape(&connector),
+            Some(MarkerShape::Dot {
+                center: (100.0, 50.0),
+                radius: 6.0
+            })
+        );
+        assert_eq!(
+            get_line_points(&connector),
+            vec![(100.0, 50.0), (300.0, 50.0)]
+        );
+
+        // NO direction
+        let connector = new_connector(vec![(100, 50), (100, 50)], ConnectorMarker::Arrow);
+        assert_eq!(get_marker_shape(&connector), None);
+    }
+}
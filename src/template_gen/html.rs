@@ -0,0 +1,225 @@
+//! `OutputFormat::Html`: a single self-contained page to open in a browser, eg offline on a second monitor
+//! - the sheet itself is the inline svg of `SvgCanvas` (font and stick photos embedded)
+//! - a hotspot on each button group anchor cf `Hotspot`: hover/click shows ALL its layers in the side panel
+//! - a searchable list of ALL the game actions of the sheet; clicking one selects its hotspot
+//!
+//! NOTE: NO external resource at all: the css, js and data are inlined
+//!
+
+use std::fmt::Write;
+use std::path::Path;
+
+use super::scene::{Hotspot, SceneBox, SceneConnector};
+use super::svg::{escape_xml, SvgCanvas};
+use super::SheetCanvas;
+use crate::Error;
+
+/// In sheet pixels
+const HOTSPOT_RADIUS: u32 = 40;
+
+const STYLE: &str = r"
+body { margin: 0; display: flex; background: #1e1e1e; color: #ddd; font-family: sans-serif; }
+main { flex: 1; min-width: 0; }
+main svg { display: block; width: 100%; height: auto; }
+aside { width: 28em; height: 100vh; overflow: auto; position: sticky; top: 0; padding: 1em; box-sizing: border-box; background: #2a2a2a; }
+h2 { font-size: 1.1em; }
+table { border-collapse: collapse; width: 100%; font-size: 0.9em; }
+td { border-bottom: 1px solid #444; padding: 0.2em 0.4em; vertical-align: top; }
+input { width: 100%; box-sizing: border-box; padding: 0.4em; margin: 1em 0 0.5em; }
+ul { list-style: none; padding: 0; margin: 0; font-size: 0.9em; }
+li { padding: 0.2em 0; cursor: pointer; }
+li:hover { color: #ffc800; }
+.layer { color: #999; }
+.hotspot { cursor: pointer; }
+.hotspot circle { fill: rgba(255, 200, 0, 0.25); stroke: #ffc800; stroke-width: 6; }
+.hotspot:hover circle, .hotspot.selected circle { fill: rgba(255, 200, 0, 0.7); }
+";
+
+const SCRIPT: &str = r#"
+const hotspotElements = document.querySelectorAll(".hotspot");
+
+function showHotspot(index) {
+  hotspotElements.forEach((element) => element.classList.toggle("selected", element.dataset.hotspot == index));
+  const hotspot = HOTSPOTS[index];
+  document.getElementById("details-title").textContent = hotspot.user_desc;
+  const table = document.getElementById("details-table");
+  table.replaceChildren();
+  for (const binding of hotspot.bindings) {
+    const row = table.insertRow();
+    row.insertCell().textContent = binding.physical_name;
+    row.insertCell().textContent = binding.layer;
+    row.insertCell().textContent = binding.game_actions.length ? binding.game_actions.join(", ") : "NO BINDING";
+  }
+}
+
+hotspotElements.forEach((element) => {
+  element.addEventListener("mouseenter", () => showHotspot(element.dataset.hotspot));
+  element.addEventListener("click", () => showHotspot(element.dataset.hotspot));
+});
+
+const entries = [];
+HOTSPOTS.forEach((hotspot, index) => {
+  for (const binding of hotspot.bindings) {
+    for (const action of binding.game_actions) {
+      const item = document.createElement("li");
+      item.append(action + " ");
+      const layer = document.createElement("span");
+      layer.className = "layer";
+      layer.textContent = binding.layer + " " + binding.physical_name;
+      item.append(layer);
+      item.addEventListener("click", () => showHotspot(index));
+      entries.push({ action, item, text: [action, binding.layer, binding.physical_name, hotspot.user_desc].join(" ").toLowerCase() });
+    }
+  }
+});
+entries.sort((a, b) => a.action.localeCompare(b.action));
+document.getElementById("actions").append(...entries.map((entry) => entry.item));
+
+document.getElementById("search").addEventListener("input", (event) => {
+  const query = event.target.value.toLowerCase();
+  for (const entry of entries) {
+    entry.item.hidden = !entry.text.includes(query);
+  }
+});
+"#;
+
+pub(super) struct HtmlCanvas {
+    svg: SvgCanvas,
+    hotspots: Vec<Hotspot>,
+}
+
+impl HtmlCanvas {
+    pub(super) fn new(size: (u32, u32), font_data: &'static [u8]) -> Result<Self, Error> {
+        Ok(Self {
+            svg: SvgCanvas::new(size, font_data)?,
+            hotspots: vec![],
+        })
+    }
+
+    fn to_html_string(&self, title: &str) -> Result<String, Error> {
+        // NOTE: "</script>" in eg an action name would close the <script>
+        let hotspots_json = serde_json::to_string(&self.hotspots)
+            .map_err(|err| Error::Other(format!("html: could not serialize hotspots : {err}")))?
+            .replace("</", "<\\/");
+
+        Ok(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>{STYLE}</style>
+</head>
+<body>
+<main>
+{svg}</main>
+<aside>
+<h2 id="details-title">Hover or click a button</h2>
+<table id="details-table"></table>
+<input id="search" type="search" placeholder="Search eg v_attack, SHIFT1, (A2)">
+<ul id="actions"></ul>
+</aside>
+<script>const HOTSPOTS = {hotspots_json};</script>
+<script>{SCRIPT}</script>
+</body>
+</html>
+"#,
+            title = escape_xml(title),
+            svg = self.svg.to_svg_string(),
+        ))
+    }
+}
+
+impl SheetCanvas for HtmlCanvas {
+    fn draw_image(
+        &mut self,
+        image_bytes: &[u8],
+        top_left_position: (i32, i32),
+        size: (u32, u32),
+    ) -> Result<(), Error> {
+        self.svg.draw_image(image_bytes, top_left_position, size)
+    }
+
+    fn draw_box(&mut self, parameters: &SceneBox) {
+        self.svg.draw_box(parameters);
+    }
+
+    fn draw_connector(&mut self, connector: &SceneConnector) {
+        self.svg.draw_connector(connector);
+    }
+
+    fn add_hotspot(&mut self, hotspot: Hotspot) {
+        let mut element = String::new();
+        // NOTE: "write!" to a String can NOT fail
+        let _ = write!(
+            element,
+            r#"<g class="hotspot" data-hotspot="{}"><title>{}</title><circle cx="{}" cy="{}" r="{HOTSPOT_RADIUS}"/></g>"#,
+            self.hotspots.len(),
+            escape_xml(&hotspot.user_desc),
+            hotspot.anchor_position.0,
+            hotspot.anchor_position.1,
+        );
+        self.svg.push_element(&element);
+
+        self.hotspots.push(hotspot);
+    }
+
+    fn save(&self, path: &Path) -> Result<(), Error> {
+        let title = path
+            .file_stem()
+            .map(|file_stem| file_stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        std::fs::write(path, self.to_html_string(&title)?)
+            .map_err(|_err| Error::Other(format!("could not write html to {}", path.display())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::params::ConnectorMarker;
+    use super::super::scene::HotspotBinding;
+    use super::*;
+
+    #[test]
+    fn test_html_canvas_self_contained() {
+        let mut canvas = HtmlCanvas::new(
+            (400, 200),
+            include_bytes!("../../data/BF_Modernista-Regular.ttf"),
+        )
+        .unwrap();
+        canvas.draw_connector(&SceneConnector {
+            points: vec![(100, 50), (200, 50)],
+            thickness: 4,
+            color: image::Rgba([0, 255, 0, 255]),
+            marker: ConnectorMarker::Dot,
+            label: None,
+        });
+        canvas.add_hotspot(Hotspot {
+            user_desc: "Red thumb <button>".to_string(),
+            anchor_position: (100, 50),
+            bindings: vec![
+                HotspotBinding {
+                    physical_name: "(A2)".to_string(),
+                    layer: "press".to_string(),
+                    game_actions: vec!["v_attack_group2".to_string()],
+                },
+                HotspotBinding {
+                    physical_name: "(A2)".to_string(),
+                    layer: "[SHIFT1]".to_string(),
+                    game_actions: vec!["</script><b>".to_string()],
+                },
+            ],
+        });
+
+        let html = canvas.to_html_string("output_L").unwrap();
+
+        assert!(html.contains(r#"<g class="hotspot" data-hotspot="0"><title>Red thumb &lt;button&gt;</title><circle cx="100" cy="50" r="40"/></g>"#));
+        assert!(html.contains(r#""layer":"[SHIFT1]","game_actions":["<\/script><b>"]"#));
+        // ONLY the 2 real ones
+        assert_eq!(html.matches("</script>").count(), 2);
+        // self-contained
+        assert!(!html.contains("src=\"http"));
+        assert!(!html.contains("href=\"http"));
+    }
+}
@@ -0,0 +1,1256 @@
+//! Second step of a sheet: the positions and sizes of everything, from the template params
+//! ie `ResolvedButtonGroup` + `TemplateJsonParamaters` -> `Scene`
+//!
+//! NOTE: pure; no file is read and nothing is drawn here, so it can be tested without pixels
+//! (the font is only used to measure the text cf `fit_boxes_texts`)
+//!
+
+use super::connector;
+use super::params::{
+    BoxesCenter, BoxesPattern, LayersLayout, TemplateJsonButtonOrStickParameters,
+    TemplateJsonParamaters, TemplateJsonSheetParameters, TemplateJsonStyleParameters,
+};
+use super::resolve::{Layer, ResolvedButtonGroup};
+use super::scene::{Hotspot, Scene, SceneBox, SceneConnector, SceneElement, SceneLabel, SceneText};
+use super::text;
+use super::theme::{LineCategory, Theme};
+use crate::Error;
+
+/// The background (if any), the stick images, the title block (if any), then for each button group: its boxes,
+/// its connector and its hotspot
+/// With `LayersLayout::Columns`/`Rows`: each box split in one cell per layer cf `split_box_by_layers`
+/// `title`: eg the mode name cf `TemplateJsonModeParameters`
+/// The colors NOT set in the template params come from `theme`
+/// The texts too big for their box are fitted cf `fit_boxes_texts`
+///
+/// # Errors
+/// - `resolved_groups` does NOT match `json_params.buttons_params`
+/// - the sizes in the template params do NOT fit in an i32
+#[allow(clippy::too_many_lines)]
+pub(super) fn layout_sheet(
+    json_params: &TemplateJsonParamaters,
+    theme: &Theme,
+    resolved_groups: Vec<ResolvedButtonGroup>,
+    title: Option<&str>,
+    font: &rusttype::Font<'static>,
+) -> Result<Scene, Error> {
+    if resolved_groups.len() != json_params.buttons_params.len() {
+        return Err(Error::Other(format!(
+            "layout: {} resolved groups for {} buttons_params",
+            resolved_groups.len(),
+            json_params.buttons_params.len()
+        )));
+    }
+
+    let sheet = &json_params.sheet;
+    let mut elements: Vec<SceneElement> =
+        layout_background(sheet.size, theme).into_iter().collect();
+    // the boxes to fit: index in `elements`, `min_text_size`
+    let mut boxes_to_fit: Vec<(usize, u32)> = vec![];
+
+    // The main image; usually this is the "front" or "3/4 front" view
+    elements.push(SceneElement::Image {
+        path: json_params.path_to_full_png.clone(),
+        top_left_position: get_image_top_left(sheet.size, sheet.full_png_position_fraction),
+        size: sheet.full_png_size,
+    });
+    // The side/back
+    elements.push(SceneElement::Image {
+        path: json_params.path_to_side_png.clone(),
+        top_left_position: get_image_top_left(sheet.size, sheet.side_png_position_fraction),
+        size: sheet.side_png_size,
+    });
+
+    if let Some(title) = title {
+        let style = &json_params.style;
+        boxes_to_fit.push((elements.len(), style.min_text_size));
+        elements.push(SceneElement::Box(SceneBox {
+            position: sheet.title_position,
+            size: style.box_size,
+            color: image::Rgba(style.box_color.unwrap_or(theme.box_color)),
+            border_color: image::Rgba(style.box_border_color.unwrap_or(theme.box_border_color)),
+            stroke_thickness: style.box_stroke_thickness,
+            text: Some(SceneText {
+                text_size: style.text_size * 2,
+                ..new_scene_text(title.to_string(), &[LineCategory::Base], style, theme)
+            }),
+            label: None,
+        }));
+    }
+
+    for (group_no, (button_param, resolved_group)) in json_params
+        .buttons_params
+        .iter()
+        .zip(resolved_groups)
+        .enumerate()
+    {
+        let reference_point =
+            get_reference_point(sheet, button_param.is_using_full_png_center_as_reference)?;
+        let style = button_param.get_style(&json_params.style);
+
+        let texts: Vec<SceneText> = resolved_group
+            .keybind_lines
+            .iter()
+            .zip(&resolved_group.keybind_lines_categories)
+            .map(|(text, categories)| new_scene_text(text.clone(), categories, &style, theme))
+            .collect();
+        let group_label = SceneLabel {
+            group_no,
+            user_desc: button_param.user_desc.clone(),
+            physical_name: None,
+        };
+        let labels: Vec<SceneLabel> = button_param
+            .physical_names
+            .iter()
+            .map(|physical_name| SceneLabel {
+                physical_name: Some(physical_name.clone()),
+                ..group_label.clone()
+            })
+            .collect();
+        let boxes = layout_boxes(
+            button_param.get_pattern()?,
+            &style,
+            theme,
+            transform_relative_coords_to_absolute(
+                reference_point,
+                button_param
+                    .desired_box_position_relative_to_center_full_png
+                    .ok_or_else(|| get_not_placed_error(button_param))?,
+            ),
+            &texts,
+            &labels,
+        )?;
+        for (box_no, scene_box) in boxes.into_iter().enumerate() {
+            // NOT the empty center, which is after the names cf `layout_boxes`
+            let cells = if box_no < texts.len() {
+                split_box_by_layers(
+                    scene_box,
+                    json_params.layers_layout,
+                    &resolved_group,
+                    box_no,
+                    &style,
+                    theme,
+                )
+            } else {
+                vec![scene_box]
+            };
+            for cell in cells {
+                boxes_to_fit.push((elements.len(), style.min_text_size));
+                elements.push(SceneElement::Box(cell));
+            }
+        }
+
+        let anchor_position = transform_relative_coords_to_absolute(
+            reference_point,
+            button_param.connector_start_line_position_relative_to_center_full_png,
+        );
+        let connector_end_position = transform_relative_coords_to_absolute(
+            reference_point,
+            button_param
+                .connector_end_line_position_relative_to_center_full_png
+                .ok_or_else(|| get_not_placed_error(button_param))?,
+        );
+        // button -> waypoints, if any -> box
+        let points: Vec<(i32, i32)> = std::iter::once(anchor_position)
+            .chain(
+                button_param
+                    .connector_waypoints_relative_to_center_full_png
+                    .iter()
+                    .map(|waypoint| {
+                        transform_relative_coords_to_absolute(reference_point, *waypoint)
+                    }),
+            )
+            .chain(std::iter::once(connector_end_position))
+            .collect();
+        elements.push(SceneElement::Connector(SceneConnector {
+            points: connector::apply_elbow(&points, style.connector_elbow),
+            thickness: style.connector_thickness,
+            color: image::Rgba(style.connector_color.unwrap_or(theme.connector_color)),
+            marker: style.connector_marker,
+            label: Some(group_label),
+        }));
+
+        elements.push(SceneElement::Hotspot(Hotspot {
+            user_desc: button_param.user_desc.clone(),
+            anchor_position,
+            bindings: resolved_group.bindings,
+        }));
+    }
+
+    let truncated_labels = fit_boxes_texts(&mut elements, &boxes_to_fit, sheet.size, font);
+    if !truncated_labels.is_empty() {
+        log::warn!(
+            "the text does NOT fit even at min_text_size ie truncated: {}",
+            truncated_labels
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+
+    Ok(Scene {
+        size: sheet.size,
+        elements,
+    })
+}
+
+/// For each box of `boxes_to_fit` (index in `elements`, `min_text_size`), in order:
+/// - grow it down, if its text needs it and there is room ie NOT over another box, NOT out of the sheet
+/// - then `text::fit_text` ie wrap, shrink, truncate
+///
+/// Returns the labels of the truncated ones
+fn fit_boxes_texts(
+    elements: &mut [SceneElement],
+    boxes_to_fit: &[(usize, u32)],
+    sheet_size: (u32, u32),
+    font: &rusttype::Font<'static>,
+) -> Vec<SceneLabel> {
+    /// Kept between a grown box and the one below
+    const MIN_GAP: i32 = 10;
+
+    let get_box = |elements: &[SceneElement], index: usize| match &elements[index] {
+        SceneElement::Box(scene_box) => Some(scene_box.clone()),
+        _ => None,
+    };
+
+    let mut truncated_labels = vec![];
+    for (index, min_text_size) in boxes_to_fit {
+        let Some(mut scene_box) = get_box(elements, *index) else {
+            continue;
+        };
+        let Some(scene_text) = scene_box.text.clone() else {
+            continue;
+        };
+
+        let needed_height = text::get_needed_box_height(&scene_text, scene_box.size, font);
+        if needed_height > scene_box.size.1 {
+            let (left, top) = scene_box.position;
+            let right = left.saturating_add_unsigned(scene_box.size.0);
+            let bottom = top.saturating_add_unsigned(scene_box.size.1);
+            let max_bottom = boxes_to_fit
+                .iter()
+                .filter(|(other_index, _)| other_index != index)
+                .filter_map(|(other_index, _)| get_box(elements, *other_index))
+                .filter(|other| {
+                    other.position.0 < right
+                        && other.position.0.saturating_add_unsigned(other.size.0) > left
+                        && other.position.1 >= bottom
+                })
+                .map(|other| other.position.1 - MIN_GAP)
+                .fold(i32::try_from(sheet_size.1).unwrap_or(i32::MAX), i32::min);
+            let room = u32::try_from(max_bottom - top).unwrap_or_default();
+            scene_box.size.1 = needed_height.min(room).max(scene_box.size.1);
+        }
+
+        let fitted = text::fit_text(&scene_text, scene_box.size, *min_text_size, font);
+        if let (true, Some(label)) = (fitted.is_truncated, &scene_box.label) {
+            truncated_labels.push(label.clone());
+        }
+        scene_box.text = Some(fitted.text);
+        elements[*index] = SceneElement::Box(scene_box);
+    }
+
+    truncated_labels
+}
+
+/// The legend page: one box per line, top -> bottom; each line in the color of its category
+/// NOTE: the default sheet size; the colors of `style`, else of `theme`
+///
+/// # Errors
+/// - too many lines
+pub(super) fn layout_legend(
+    lines: Vec<(String, LineCategory)>,
+    style: &TemplateJsonStyleParameters,
+    theme: &Theme,
+) -> Result<Scene, Error> {
+    const LEGEND_BOX_LENGTH: u32 = 2400;
+    const LEGEND_BOX_HEIGHT: u32 = 110;
+    const LEGEND_PADDING_V: i32 = 20;
+
+    let box_height = i32::try_from(LEGEND_BOX_HEIGHT).map_err(Error::TryFromIntError)?;
+    let size = TemplateJsonSheetParameters::default().size;
+    let mut elements: Vec<SceneElement> = layout_background(size, theme).into_iter().collect();
+    for (line_no, (line, category)) in lines.into_iter().enumerate() {
+        let line_no = i32::try_from(line_no).map_err(Error::TryFromIntError)?;
+        elements.push(SceneElement::Box(SceneBox {
+            position: (200, 200 + line_no * (box_height + LEGEND_PADDING_V)),
+            size: (LEGEND_BOX_LENGTH, LEGEND_BOX_HEIGHT),
+            color: image::Rgba(style.box_color.unwrap_or(theme.box_color)),
+            border_color: image::Rgba(style.box_border_color.unwrap_or(theme.box_border_color)),
+            stroke_thickness: style.box_stroke_thickness,
+            text: Some(SceneText {
+                text_size: 40,
+                ..new_scene_text(line, &[category], style, theme)
+            }),
+            label: None,
+        }));
+    }
+
+    Ok(Scene { size, elements })
+}
+
+/// A rectangle over the whole sheet; `None` if transparent
+pub(super) fn layout_background(size: (u32, u32), theme: &Theme) -> Option<SceneElement> {
+    (theme.background_color[3] > 0).then_some(SceneElement::Box(SceneBox {
+        position: (0, 0),
+        size,
+        color: image::Rgba(theme.background_color),
+        border_color: image::Rgba(theme.background_color),
+        stroke_thickness: 0,
+        text: None,
+        label: None,
+    }))
+}
+
+/// `scene_box` ie the box of `resolved_group.physical_names[name_no]`, split in equal cells: one per layer with
+/// at least a line, in the `Layer` order; left -> right for `LayersLayout::Columns`, top -> bottom for `Rows`
+/// Each cell: the layer name in the color of its category, then its lines without their prefix eg "[SHIFT1] "
+///
+/// NOTE: as-is with `Stacked`/`Sheets`, or if a single layer
+fn split_box_by_layers(
+    scene_box: SceneBox,
+    layers_layout: LayersLayout,
+    resolved_group: &ResolvedButtonGroup,
+    name_no: usize,
+    style: &TemplateJsonStyleParameters,
+    theme: &Theme,
+) -> Vec<SceneBox> {
+    let layers: Vec<Layer> = resolved_group.get_layers(name_no);
+    let is_columns = match layers_layout {
+        LayersLayout::Columns => true,
+        LayersLayout::Rows => false,
+        LayersLayout::Stacked | LayersLayout::Sheets => return vec![scene_box],
+    };
+    if layers.len() < 2 {
+        return vec![scene_box];
+    }
+
+    // in u32 then i32: a box is NOT bigger than the sheet, and there are at most 5 layers
+    let count = u32::try_from(layers.len()).unwrap_or(1);
+    let (length, last_length) = if is_columns {
+        (
+            scene_box.size.0 / count,
+            scene_box.size.0 - scene_box.size.0 / count * (count - 1),
+        )
+    } else {
+        (
+            scene_box.size.1 / count,
+            scene_box.size.1 - scene_box.size.1 / count * (count - 1),
+        )
+    };
+    let step = i32::try_from(length).unwrap_or_default();
+    let last_cell_no = layers.len() - 1;
+
+    layers
+        .into_iter()
+        .enumerate()
+        .map(|(cell_no, layer)| {
+            let (lines, categories) = resolved_group.get_layer_lines(name_no, layer);
+            let mut cell_categories = vec![layer.get_category()];
+            cell_categories.extend(categories);
+            let cell_length = if cell_no == last_cell_no {
+                last_length
+            } else {
+                length
+            };
+            let offset = step * i32::try_from(cell_no).unwrap_or_default();
+            SceneBox {
+                position: if is_columns {
+                    (scene_box.position.0 + offset, scene_box.position.1)
+                } else {
+                    (scene_box.position.0, scene_box.position.1 + offset)
+                },
+                size: if is_columns {
+                    (cell_length, scene_box.size.1)
+                } else {
+                    (scene_box.size.0, cell_length)
+                },
+                text: Some(new_scene_text(
+                    format!("{}\n{lines}", layer.get_name()),
+                    &cell_categories,
+                    style,
+                    theme,
+                )),
+                ..scene_box.clone()
+            }
+        })
+        .collect()
+}
+
+/// `categories`: one per line of `text` cf `ResolvedButtonGroup::keybind_lines_categories`
+fn new_scene_text(
+    text: String,
+    categories: &[LineCategory],
+    style: &TemplateJsonStyleParameters,
+    theme: &Theme,
+) -> SceneText {
+    let text_color = style.text_color.unwrap_or(theme.text_color);
+
+    SceneText {
+        text,
+        text_size: style.text_size,
+        text_color: image::Rgba(text_color),
+        line_colors: categories
+            .iter()
+            .map(|category| theme.get_line_color(*category, text_color))
+            .collect(),
+    }
+}
+
+/// The boxes of a group, arranged as `pattern` from `start_position` ie the top left of the FIRST box
+///
+/// `texts` and `labels`: one per physical name; order is important cf `BoxesPattern`
+///
+/// Returns the boxes in drawing order ie the order of the names, then the center if any
+///
+/// # Errors
+/// - the number of `texts` does NOT match `pattern` cf `BoxesPattern::is_valid_count`
+/// - the sizes do NOT fit in an i32
+#[allow(clippy::cast_possible_truncation)]
+fn layout_boxes(
+    pattern: BoxesPattern,
+    style: &TemplateJsonStyleParameters,
+    theme: &Theme,
+    start_position: (i32, i32),
+    texts: &[SceneText],
+    labels: &[SceneLabel],
+) -> Result<Vec<SceneBox>, Error> {
+    assert_eq!(texts.len(), labels.len());
+    if !pattern.is_valid_count(texts.len()) {
+        return Err(Error::InvalidBoxesPattern(format!(
+            "{pattern:?} can NOT have {} boxes",
+            texts.len()
+        )));
+    }
+
+    // from one box to the next one: its size + the padding
+    let step = (
+        f64::from(
+            i32::try_from(style.box_size.0).map_err(Error::TryFromIntError)? + style.box_padding.0,
+        ),
+        f64::from(
+            i32::try_from(style.box_size.1).map_err(Error::TryFromIntError)? + style.box_padding.1,
+        ),
+    );
+
+    let no_text = new_scene_text(String::new(), &[], style, theme);
+    Ok(get_pattern_cells(pattern, texts.len())
+        .into_iter()
+        // `None`: an empty box eg the center of the 8 boxes
+        .map(|(index, cell)| SceneBox {
+            position: (
+                start_position.0 + (cell.0 * step.0).round() as i32,
+                start_position.1 + (cell.1 * step.1).round() as i32,
+            ),
+            size: style.box_size,
+            color: image::Rgba(style.box_color.unwrap_or(theme.box_color)),
+            border_color: image::Rgba(style.box_border_color.unwrap_or(theme.box_border_color)),
+            stroke_thickness: style.box_stroke_thickness,
+            text: Some(index.map_or_else(|| no_text.clone(), |index| texts[index].clone())),
+            label: Some(index.map_or_else(
+                || SceneLabel {
+                    physical_name: None,
+                    ..labels[0].clone()
+                },
+                |index| labels[index].clone(),
+            )),
+        })
+        .collect())
+}
+
+/// Where each box of `pattern` goes: (index of the physical name, cell); `None` for an empty center
+/// A cell is in "boxes" ie a box + its padding, relative to the FIRST box
+/// NOTE: `count` MUST be valid cf `BoxesPattern::is_valid_count`
+#[allow(clippy::cast_precision_loss)]
+fn get_pattern_cells(pattern: BoxesPattern, count: usize) -> Vec<(Option<usize>, (f64, f64))> {
+    // the middle of a cross/ring: the last name, or an empty box
+    let with_center = |mut cells: Vec<(Option<usize>, (f64, f64))>, center, center_cell| {
+        match center {
+            BoxesCenter::None => {}
+            BoxesCenter::Empty => cells.push((None, center_cell)),
+            BoxesCenter::LastName => cells.push((Some(count - 1), center_cell)),
+        }
+        cells
+    };
+
+    match pattern {
+        BoxesPattern::Vertical => (0..count)
+            .map(|index| (Some(index), (0.0, index as f64)))
+            .collect(),
+        BoxesPattern::Horizontal => (0..count)
+            .map(|index| (Some(index), (index as f64, 0.0)))
+            .collect(),
+        BoxesPattern::Grid { cols, .. } => (0..count)
+            .map(|index| (Some(index), ((index % cols) as f64, (index / cols) as f64)))
+            .collect(),
+        BoxesPattern::Cross { center } => with_center(
+            [(0.0, 0.0), (1.0, 1.0), (0.0, 2.0), (-1.0, 1.0)]
+                .into_iter()
+                .enumerate()
+                .map(|(index, cell)| (Some(index), cell))
+                .collect(),
+            center,
+            (0.0, 1.0),
+        ),
+        BoxesPattern::Ring { center } => {
+            let ring_count = count - usize::from(center == BoxesCenter::LastName);
+            // in boxes; a square ring of radius R has 8*R cells, one box each
+            let radius = ring_count.div_ceil(8);
+            let perimeter = 8 * radius;
+            let cells = (0..ring_count)
+                .map(|index| {
+                    // NOT an angle: the cells of the ring ie NEVER 2 boxes in the same cell
+                    // cf `test_layout_boxes_ring_no_overlap`
+                    // ie round(index * perimeter / ring_count)
+                    let step = (index * perimeter * 2 + ring_count) / (ring_count * 2);
+                    (Some(index), get_ring_cell(radius, step))
+                })
+                .collect();
+            let radius = radius as f64;
+            with_center(cells, center, (0.0, radius))
+        }
+    }
+}
+
+/// The `step`-th cell of the square ring of `radius`, clockwise from the top middle one
+/// Relative to the top middle one, like `get_pattern_cells`
+/// eg radius 1: (0, 0), (1, 0), (1, 1), (1, 2), (0, 2), (-1, 2), (-1, 1), (-1, 0)
+#[allow(clippy::cast_precision_loss)]
+fn get_ring_cell(radius: usize, step: usize) -> (f64, f64) {
+    let (radius, step) = (radius as f64, step as f64);
+    let (x, y) = if step <= radius {
+        // top, right half
+        (step, -radius)
+    } else if step <= 3.0 * radius {
+        // right
+        (radius, step - 2.0 * radius)
+    } else if step <= 5.0 * radius {
+        // bottom
+        (4.0 * radius - step, radius)
+    } else if step <= 7.0 * radius {
+        // left
+        (-radius, 6.0 * radius - step)
+    } else {
+        // top, left half
+        (step - 8.0 * radius, -radius)
+    };
+
+    (x, y + radius)
+}
+
+#[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+fn get_image_top_left(sheet_size: (u32, u32), position_fraction: (f32, f32)) -> (i32, i32) {
+    (
+        (sheet_size.0 as f32 * position_fraction.0) as i32,
+        (sheet_size.1 as f32 * position_fraction.1) as i32,
+    )
+}
+
+/// The center of the full png, or of the side png: what the positions of the template params are relative to
+/// cf `TemplateJsonButtonOrStickParameters::is_using_full_png_center_as_reference`
+pub(super) fn get_reference_point(
+    sheet: &TemplateJsonSheetParameters,
+    is_using_full_png_center_as_reference: bool,
+) -> Result<(i32, i32), Error> {
+    let (size, position_fraction) = if is_using_full_png_center_as_reference {
+        (sheet.full_png_size, sheet.full_png_position_fraction)
+    } else {
+        (sheet.side_png_size, sheet.side_png_position_fraction)
+    };
+
+    Ok(transform_relative_coords_to_absolute(
+        (
+            i32::try_from(size.0).map_err(Error::TryFromIntError)? / 2,
+            i32::try_from(size.1).map_err(Error::TryFromIntError)? / 2,
+        ),
+        get_image_top_left(sheet.size, position_fraction),
+    ))
+}
+
+/// The bounding box of ALL the boxes of a group, relative to its `start_position` cf `layout_boxes`
+/// ie left, top, right, bottom; eg the cross starts at its top center box so `left` < 0
+/// `style`: the global one ie WITHOUT the `style_overrides` of the group
+///
+/// # Errors
+/// - cf `TemplateJsonButtonOrStickParameters::get_pattern`
+pub(super) fn get_boxes_extent(
+    button_param: &TemplateJsonButtonOrStickParameters,
+    style: &TemplateJsonStyleParameters,
+) -> Result<(i32, i32, i32, i32), Error> {
+    let style = &button_param.get_style(style);
+    let count = button_param.physical_names.len();
+    let theme = Theme::default();
+    let texts = vec![new_scene_text(String::new(), &[], style, &theme); count];
+    let labels = vec![
+        SceneLabel {
+            group_no: 0,
+            user_desc: String::new(),
+            physical_name: None,
+        };
+        count
+    ];
+
+    let mut extent: Option<(i32, i32, i32, i32)> = None;
+    for scene_box in layout_boxes(
+        button_param.get_pattern()?,
+        style,
+        &theme,
+        (0, 0),
+        &texts,
+        &labels,
+    )? {
+        let (right, bottom) = transform_relative_coords_to_absolute(
+            scene_box.position,
+            (
+                i32::try_from(scene_box.size.0).map_err(Error::TryFromIntError)?,
+                i32::try_from(scene_box.size.1).map_err(Error::TryFromIntError)?,
+            ),
+        );
+        let (left, top) = scene_box.position;
+        extent = Some(match extent {
+            Some(extent) => (
+                extent.0.min(left),
+                extent.1.min(top),
+                extent.2.max(right),
+                extent.3.max(bottom),
+            ),
+            None => (left, top, right, bottom),
+        });
+    }
+
+    extent.ok_or_else(|| Error::Other(format!("layout: NO box for \"{}\"", button_param.user_desc)))
+}
+
+fn get_not_placed_error(button_param: &TemplateJsonButtonOrStickParameters) -> Error {
+    Error::Other(format!(
+        "layout: \"{}\" is NOT placed ie no box position or connector end; cf placement",
+        button_param.user_desc
+    ))
+}
+
+fn transform_relative_coords_to_absolute(add: (i32, i32), relative_to: (i32, i32)) -> (i32, i32) {
+    (add.0 + relative_to.0, add.1 + relative_to.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::params::ConnectorMarker;
+    use super::*;
+
+    const FONT_DATA: &[u8] = include_bytes!("../../data/BF_Modernista-Regular.ttf");
+
+    fn get_font() -> rusttype::Font<'static> {
+        rusttype::Font::try_from_bytes(FONT_DATA).unwrap()
+    }
+
+    fn get_positions_and_texts(boxes: &[SceneBox]) -> Vec<((i32, i32), &str)> {
+        boxes
+            .iter()
+            .map(|scene_box| {
+                (
+                    scene_box.position,
+                    scene_box.text.as_ref().unwrap().text.as_str(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_layout_boxes_5_cross() {
+        let style = TemplateJsonStyleParameters::default();
+        let theme = Theme::default();
+        let texts: Vec<SceneText> = ["N", "E", "S", "W", "push"]
+            .iter()
+            .map(|text| new_scene_text(text.to_string(), &[], &style, &theme))
+            .collect();
+        let labels: Vec<SceneLabel> = (1..=5)
+            .map(|no| SceneLabel {
+                group_no: 0,
+                user_desc: "Ministick".to_string(),
+                physical_name: Some(format!("(C{no})")),
+            })
+            .collect();
+        let boxes = layout_boxes(
+            BoxesPattern::Cross {
+                center: BoxesCenter::LastName,
+            },
+            &style,
+            &theme,
+            (1000, 100),
+            &texts,
+            &labels,
+        )
+        .unwrap();
+
+        assert_eq!(
+            get_positions_and_texts(&boxes),
+            vec![
+                ((1000, 100), "N"),
+                ((1510, 220), "E"),
+                ((1000, 340), "S"),
+                ((490, 220), "W"),
+                ((1000, 220), "push"),
+            ]
+        );
+        assert!(boxes.iter().all(|scene_box| scene_box.size == (500, 110)));
+        assert_eq!(
+            boxes[4].label.as_ref().unwrap().to_string(),
+            r#"#0 "Ministick" (C5)"#
+        );
+    }
+
+    #[test]
+    fn test_layout_boxes_patterns() {
+        let style = TemplateJsonStyleParameters::default();
+        let theme = Theme::default();
+        let layout = |pattern, names: &[&str]| {
+            let texts: Vec<SceneText> = names
+                .iter()
+                .map(|name| new_scene_text(name.to_string(), &[], &style, &theme))
+                .collect();
+            let labels = vec![
+                SceneLabel {
+                    group_no: 0,
+                    user_desc: "group".to_string(),
+                    physical_name: None,
+                };
+                names.len()
+            ];
+            layout_boxes(pattern, &style, &theme, (1000, 100), &texts, &labels)
+        };
+
+        // the 8-way ministick: a 3x3 grid without its middle, clockwise from the top
+        let boxes = layout(
+            BoxesPattern::Ring {
+                center: BoxesCenter::Empty,
+            },
+            &["N", "NE", "E", "SE", "S", "SW", "W", "NW"],
+        )
+        .unwrap();
+        assert_eq!(
+            get_positions_and_texts(&boxes),
+            vec![
+                ((1000, 100), "N"),
+                ((1510, 100), "NE"),
+                ((1510, 220), "E"),
+                ((1510, 340), "SE"),
+                ((1000, 340), "S"),
+                ((490, 340), "SW"),
+                ((490, 220), "W"),
+                ((490, 100), "NW"),
+                ((1000, 220), ""),
+            ]
+        );
+
+        // a 6-position rotary: the closest cells of the 3x3 ring ie N NE SE S SW NW
+        let boxes = layout(
+            BoxesPattern::Ring {
+                center: BoxesCenter::None,
+            },
+            &["1", "2", "3", "4", "5", "6"],
+        )
+        .unwrap();
+        assert_eq!(boxes.len(), 6);
+        assert_eq!(boxes[0].position, (1000, 100));
+        assert_eq!(boxes[1].position.0, 1510);
+        assert_eq!(boxes[3].position, (1000, 340));
+
+        // row by row, the last one incomplete
+        let boxes = layout(
+            BoxesPattern::Grid { rows: 2, cols: 3 },
+            &["1", "2", "3", "4", "5"],
+        )
+        .unwrap();
+        assert_eq!(
+            get_positions_and_texts(&boxes),
+            vec![
+                ((1000, 100), "1"),
+                ((1510, 100), "2"),
+                ((2020, 100), "3"),
+                ((1000, 220), "4"),
+                ((1510, 220), "5"),
+            ]
+        );
+
+        // a 4-way hat without push
+        let boxes = layout(
+            BoxesPattern::Cross {
+                center: BoxesCenter::None,
+            },
+            &["N", "E", "S", "W"],
+        )
+        .unwrap();
+        assert_eq!(boxes.len(), 4);
+
+        assert!(matches!(
+            layout(
+                BoxesPattern::Grid { rows: 2, cols: 2 },
+                &["1", "2", "3", "4", "5"]
+            ),
+            Err(Error::InvalidBoxesPattern(_))
+        ));
+        assert!(matches!(
+            layout(
+                BoxesPattern::Cross {
+                    center: BoxesCenter::LastName
+                },
+                &["N", "E", "S", "W"]
+            ),
+            Err(Error::InvalidBoxesPattern(_))
+        ));
+    }
+
+    #[test]
+    fn test_layout_boxes_ring_no_overlap() {
+        let style = TemplateJsonStyleParameters::default();
+        let theme = Theme::default();
+
+        for count in 2..=24 {
+            for center in [BoxesCenter::None, BoxesCenter::Empty, BoxesCenter::LastName] {
+                if !(BoxesPattern::Ring { center }).is_valid_count(count) {
+                    continue;
+                }
+                let texts = vec![new_scene_text("name".to_string(), &[], &style, &theme); count];
+                let labels = vec![
+                    SceneLabel {
+                        group_no: 0,
+                        user_desc: "group".to_string(),
+                        physical_name: None,
+                    };
+                    count
+                ];
+                let boxes = layout_boxes(
+                    BoxesPattern::Ring { center },
+                    &style,
+                    &theme,
+                    (1000, 1000),
+                    &texts,
+                    &labels,
+                )
+                .unwrap();
+
+                for (index, scene_box) in boxes.iter().enumerate() {
+                    for other in &boxes[index + 1..] {
+                        let is_apart = (scene_box.position.0 - other.position.0).unsigned_abs()
+                            >= scene_box.size.0
+                            || (scene_box.position.1 - other.position.1).unsigned_abs()
+                                >= scene_box.size.1;
+                        assert!(
+                            is_apart,
+                            "{count} {center:?}: {:?} and {:?} overlap",
+                            scene_box.position, other.position
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_layout_sheet_relative_to_images() {
+        let json_params: TemplateJsonParamaters = serde_json::from_str(
+            r#"{
+                "path_to_full_png": "full.png",
+                "path_to_side_png": "side.png",
+                "path_to_output_png": "output.png",
+                "buttons_params": [
+                    {
+                        "physical_names": ["(A2)"],
+                        "user_desc": "Red thumb button",
+                        "is_using_full_png_center_as_reference": true,
+                        "desired_box_position_relative_to_center_full_png": [100, -200],
+                        "connector_start_line_position_relative_to_center_full_png": [10, 20],
+                        "connector_end_line_position_relative_to_center_full_png": [100, -150]
+                    },
+                    {
+                        "physical_names": ["(D1)", "(F1)"],
+                        "user_desc": "Side",
+                        "is_using_full_png_center_as_reference": false,
+                        "desired_box_position_relative_to_center_full_png": [0, 0],
+                        "connector_start_line_position_relative_to_center_full_png": [0, 0],
+                        "connector_end_line_position_relative_to_center_full_png": [0, 0]
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let resolved_groups = vec![
+            ResolvedButtonGroup {
+                keybind_lines: vec!["v_attack_group2\n".to_string()],
+                keybind_lines_categories: vec![vec![LineCategory::Base]],
+                keybind_lines_layers: vec![vec![Layer::Base]],
+                bindings: vec![],
+            },
+            ResolvedButtonGroup {
+                keybind_lines: vec!["a\n".to_string(), "b\n".to_string()],
+                keybind_lines_categories: vec![vec![], vec![]],
+                keybind_lines_layers: vec![vec![], vec![]],
+                bindings: vec![],
+            },
+        ];
+
+        let scene = layout_sheet(
+            &json_params,
+            &Theme::default(),
+            resolved_groups,
+            None,
+            &get_font(),
+        )
+        .unwrap();
+        assert_eq!(scene.size, (4000, 2000));
+
+        // 2 images, then 1 box + line + hotspot, then 2 boxes + line + hotspot
+        assert_eq!(scene.elements.len(), 2 + 3 + 4);
+        // full png: top left (1200, 0) -> center (2100, 900)
+        let SceneElement::Box(scene_box) = &scene.elements[2] else {
+            panic!("expected a box");
+        };
+        assert_eq!(scene_box.position, (2200, 700));
+        assert_eq!(
+            scene.elements[3],
+            SceneElement::Connector(SceneConnector {
+                points: vec![(2110, 920), (2200, 750)],
+                thickness: 4,
+                color: image::Rgba([0, 255, 0, 255]),
+                marker: ConnectorMarker::None,
+                label: Some(SceneLabel {
+                    group_no: 0,
+                    user_desc: "Red thumb button".to_string(),
+                    physical_name: None,
+                }),
+            })
+        );
+        // side png: top left (200, 600) -> center (800, 1200)
+        let SceneElement::Hotspot(hotspot) = &scene.elements[8] else {
+            panic!("expected a hotspot");
+        };
+        assert_eq!(hotspot.anchor_position, (800, 1200));
+        assert_eq!(hotspot.user_desc, "Side");
+
+        // NOT matching
+        assert!(layout_sheet(&json_params, &Theme::default(), vec![], None, &get_font()).is_err());
+    }
+
+    #[test]
+    fn test_layout_sheet_theme() {
+        let json_params: TemplateJsonParamaters = serde_json::from_str(
+            r#"{
+                "path_to_full_png": "full.png",
+                "path_to_side_png": "side.png",
+                "path_to_output_png": "output.png",
+                "style": { "text_color": [1, 2, 3, 255] },
+                "buttons_params": [
+                    {
+                        "physical_names": ["(A2)"],
+                        "user_desc": "Red thumb button",
+                        "is_using_full_png_center_as_reference": true,
+                        "desired_box_position_relative_to_center_full_png": [0, 0],
+                        "connector_start_line_position_relative_to_center_full_png": [0, 0],
+                        "connector_end_line_position_relative_to_center_full_png": [0, 0]
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let theme = Theme::from_name_or_path("colour-blind").unwrap();
+        let resolved_groups = vec![ResolvedButtonGroup {
+            keybind_lines: vec!["v_attack_group2\n[SHIFT1] NO BINDING\n".to_string()],
+            keybind_lines_categories: vec![vec![LineCategory::Base, LineCategory::NoBinding]],
+            keybind_lines_layers: vec![vec![Layer::Base, Layer::Shift1]],
+            bindings: vec![],
+        }];
+
+        let scene = layout_sheet(&json_params, &theme, resolved_groups, None, &get_font()).unwrap();
+
+        // the background first ie below everything
+        let SceneElement::Box(background) = &scene.elements[0] else {
+            panic!("expected the background");
+        };
+        assert_eq!(
+            (background.position, background.size, background.color),
+            ((0, 0), (4000, 2000), image::Rgba(theme.background_color))
+        );
+
+        let SceneElement::Box(scene_box) = &scene.elements[3] else {
+            panic!("expected a box");
+        };
+        assert_eq!(scene_box.color, image::Rgba(theme.box_color));
+        assert_eq!(scene_box.border_color, image::Rgba(theme.box_border_color));
+        // the template params have priority; but NOT for the categories
+        let text = scene_box.text.as_ref().unwrap();
+        assert_eq!(text.get_line_color(0), image::Rgba([1, 2, 3, 255]));
+        assert_eq!(
+            text.get_line_color(1),
+            image::Rgba(theme.no_binding_color.unwrap())
+        );
+        assert_eq!(text.get_line_color(2), image::Rgba([1, 2, 3, 255]));
+
+        // "dark": NO background; with a title block
+        let scene = layout_sheet(
+            &json_params,
+            &Theme::default(),
+            vec![ResolvedButtonGroup {
+                keybind_lines: vec![String::new()],
+                keybind_lines_categories: vec![vec![]],
+                keybind_lines_layers: vec![vec![]],
+                bindings: vec![],
+            }],
+            Some("Flight"),
+            &get_font(),
+        )
+        .unwrap();
+        assert!(matches!(scene.elements[0], SceneElement::Image { .. }));
+        let SceneElement::Box(title) = &scene.elements[2] else {
+            panic!("expected the title block");
+        };
+        assert_eq!((title.position, title.label.as_ref()), ((40, 40), None));
+        assert_eq!(title.text.as_ref().unwrap().text, "Flight");
+    }
+
+    #[test]
+    fn test_layout_sheet_layers_columns() {
+        let mut json_params: TemplateJsonParamaters = serde_json::from_str(
+            r#"{
+                "path_to_full_png": "full.png",
+                "path_to_side_png": "side.png",
+                "path_to_output_png": "output.png",
+                "layers_layout": "columns",
+                "style": { "box_size": [600, 150] },
+                "buttons_params": [
+                    {
+                        "physical_names": ["(A2)"],
+                        "user_desc": "Red thumb button",
+                        "is_using_full_png_center_as_reference": true,
+                        "desired_box_position_relative_to_center_full_png": [0, 0],
+                        "connector_start_line_position_relative_to_center_full_png": [0, 0],
+                        "connector_end_line_position_relative_to_center_full_png": [0, 0]
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let resolved_groups = vec![ResolvedButtonGroup {
+            keybind_lines: vec![
+                "v_attack_group2\n[SHIFT1] NO BINDING\n[LONG] v_flare\n".to_string()
+            ],
+            keybind_lines_categories: vec![vec![
+                LineCategory::Base,
+                LineCategory::NoBinding,
+                LineCategory::Tempo,
+            ]],
+            keybind_lines_layers: vec![vec![Layer::Base, Layer::Shift1, Layer::Long]],
+            bindings: vec![],
+        }];
+        let get_cells = |scene: &Scene| -> Vec<SceneBox> {
+            scene
+                .elements
+                .iter()
+                .filter_map(|element| match element {
+                    SceneElement::Box(scene_box) if scene_box.label.is_some() => {
+                        Some(scene_box.clone())
+                    }
+                    _ => None,
+                })
+                .collect()
+        };
+        let (x, y) = get_reference_point(&json_params.sheet, true).unwrap();
+
+        let theme = Theme::from_name_or_path("light").unwrap();
+        let scene = layout_sheet(
+            &json_params,
+            &theme,
+            resolved_groups.clone(),
+            None,
+            &get_font(),
+        )
+        .unwrap();
+        let cells = get_cells(&scene);
+        assert_eq!(
+            get_positions_and_texts(&cells),
+            vec![
+                ((x, y), "BASE\nv_attack_group2\n"),
+                ((x + 200, y), "SHIFT1\nNO BINDING\n"),
+                ((x + 400, y), "LONG\nv_flare\n"),
+            ]
+        );
+        assert!(cells.iter().all(|cell| cell.size == (200, 150)));
+        // the header in the color of the layer; the lines keep theirs
+        let SceneElement::Box(shift_cell) = &scene.elements[4] else {
+            panic!("expected a box");
+        };
+        let shift_text = shift_cell.text.as_ref().unwrap();
+        assert_eq!(
+            shift_text.get_line_color(0),
+            image::Rgba(theme.shift_color.unwrap())
+        );
+        assert_eq!(
+            shift_text.get_line_color(1),
+            image::Rgba(theme.no_binding_color.unwrap())
+        );
+
+        // the rows have room for their 2 lines ie NOT grown cf `fit_boxes_texts`
+        json_params.layers_layout = LayersLayout::Rows;
+        json_params.style.box_size = (600, 450);
+        let scene = layout_sheet(
+            &json_params,
+            &Theme::default(),
+            resolved_groups,
+            None,
+            &get_font(),
+        )
+        .unwrap();
+        assert_eq!(
+            get_cells(&scene)
+                .iter()
+                .map(|cell| (cell.position, cell.size))
+                .collect::<Vec<_>>(),
+            vec![
+                ((x, y), (600, 150)),
+                ((x, y + 150), (600, 150)),
+                ((x, y + 300), (600, 150)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_layout_sheet_style_overrides() {
+        let json_params: TemplateJsonParamaters = serde_json::from_str(
+            r#"{
+                "path_to_full_png": "full.png",
+                "path_to_side_png": "side.png",
+                "path_to_output_png": "output.png",
+                "sheet": { "size": [3000, 1500], "full_png_position_fraction": [0.5, 0.0] },
+                "style": { "connector_thickness": 8 },
+                "buttons_params": [
+                    {
+                        "physical_names": ["(A2)", "(A3)"],
+                        "user_desc": "Red thumb button",
+                        "is_using_full_png_center_as_reference": true,
+                        "desired_box_position_relative_to_center_full_png": [0, 0],
+                        "connector_start_line_position_relative_to_center_full_png": [0, 0],
+                        "connector_end_line_position_relative_to_center_full_png": [0, 0],
+                        "style_overrides": { "box_size": [300, 50], "box_padding": [0, 5], "connector_color": [255, 0, 0, 255] }
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let resolved_groups = vec![ResolvedButtonGroup {
+            keybind_lines: vec!["a\n".to_string(), "b\n".to_string()],
+            keybind_lines_categories: vec![vec![], vec![]],
+            keybind_lines_layers: vec![vec![], vec![]],
+            bindings: vec![],
+        }];
+
+        let scene = layout_sheet(
+            &json_params,
+            &Theme::default(),
+            resolved_groups,
+            None,
+            &get_font(),
+        )
+        .unwrap();
+        assert_eq!(scene.size, (3000, 1500));
+        // full png: top left (1500, 0), default size 1800x1800 -> center (2400, 900)
+        let boxes: Vec<_> = scene
+            .elements
+            .iter()
+            .filter_map(|element| match element {
+                SceneElement::Box(scene_box) => Some((scene_box.position, scene_box.size)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            boxes,
+            vec![((2400, 900), (300, 50)), ((2400, 955), (300, 50))]
+        );
+        assert!(scene
+            .elements
+            .contains(&SceneElement::Connector(SceneConnector {
+                points: vec![(2400, 900), (2400, 900)],
+                thickness: 8,
+                color: image::Rgba([255, 0, 0, 255]),
+                marker: ConnectorMarker::None,
+                label: Some(SceneLabel {
+                    group_no: 0,
+                    user_desc: "Red thumb button".to_string(),
+                    physical_name: None,
+                }),
+            })));
+    }
+
+    #[test]
+    fn test_layout_sheet_fit_texts() {
+        let json_params: TemplateJsonParamaters = serde_json::from_str(
+            r#"{
+                "path_to_full_png": "full.png",
+                "path_to_side_png": "side.png",
+                "path_to_output_png": "output.png",
+                "buttons_params": [
+                    {
+                        "physical_names": ["(A2)", "(A3)"],
+                        "user_desc": "Red thumb button",
+                        "is_using_full_png_center_as_reference": true,
+                        "desired_box_position_relative_to_center_full_png": [0, 0],
+                        "connector_start_line_position_relative_to_center_full_png": [0, 0],
+                        "connector_end_line_position_relative_to_center_full_png": [0, 0]
+                    },
+                    {
+                        "physical_names": ["(B1)"],
+                        "user_desc": "Alone",
+                        "is_using_full_png_center_as_reference": true,
+                        "desired_box_position_relative_to_center_full_png": [1000, 0],
+                        "connector_start_line_position_relative_to_center_full_png": [0, 0],
+                        "connector_end_line_position_relative_to_center_full_png": [0, 0],
+                        "style_overrides": { "min_text_size": 24 }
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let too_long = "v_weapon_gimbal_mode_cycle_fixed_auto\nv_weapon_bombing_toggle_desired_impact_point\n[SHIFT1] v_weapon_pip_toggle_lead_lag\n[SHIFT2] NO BINDING\n".to_string();
+        let resolved_groups = vec![
+            ResolvedButtonGroup {
+                keybind_lines: vec![too_long.clone(), "b\n".to_string()],
+                keybind_lines_categories: vec![vec![], vec![]],
+                keybind_lines_layers: vec![vec![], vec![]],
+                bindings: vec![],
+            },
+            ResolvedButtonGroup {
+                keybind_lines: vec![too_long.repeat(20)],
+                keybind_lines_categories: vec![vec![]],
+                keybind_lines_layers: vec![vec![]],
+                bindings: vec![],
+            },
+        ];
+
+        let scene = layout_sheet(
+            &json_params,
+            &Theme::default(),
+            resolved_groups,
+            None,
+            &get_font(),
+        )
+        .unwrap();
+        let boxes: Vec<&SceneBox> = scene
+            .elements
+            .iter()
+            .filter_map(|element| match element {
+                SceneElement::Box(scene_box) => Some(scene_box),
+                _ => None,
+            })
+            .collect();
+
+        // (A2): NO room below b/c of (A3) -> same size, smaller text
+        assert_eq!(boxes[0].size, (500, 110));
+        let text = boxes[0].text.as_ref().unwrap();
+        assert!(text.text_size < 24);
+        assert!(!text.text.ends_with("..."));
+        // (B1): grown down to the bottom of the sheet, but still too much text at its min size
+        assert_eq!(boxes[2].size.1, 2000 - 900);
+        let text = boxes[2].text.as_ref().unwrap();
+        assert_eq!(text.text_size, 24);
+        assert!(text.text.ends_with("..."));
+    }
+}
@@ -0,0 +1,535 @@
+//! Generate the binding sheet of a stick, in 3 steps:
+//! - `resolve`: the game actions of each button group of the template, on ALL the layers
+//!   (the groups given only by their anchor are placed first cf `placement`)
+//! - `layout`: where everything goes ie a backend-neutral `Scene`
+//! - render: `Scene::render` on the `SheetCanvas` of the `OutputFormat` (png, svg, html; pdf via svg)
+//!
+//! The colors come from the template params, else from the `Theme`
+//!
+
+use std::path::{Path, PathBuf};
+
+use crate::sc::parse_keybind_xml;
+use crate::vkb::vkb_button::JoystickButtonsMapping;
+use crate::Error;
+
+mod composite;
+mod connector;
+mod html;
+mod layout;
+mod params;
+mod pdf;
+mod placement;
+mod png;
+mod resolve;
+mod scene;
+mod svg;
+mod text;
+mod theme;
+mod validate;
+
+pub use pdf::{Orientation, PaperSize, PdfOptions};
+
+use params::{
+    read_template_json_params, LayersLayout, TemplateJsonModeParameters, TemplateJsonParamaters,
+};
+use resolve::Layer;
+use scene::{Hotspot, Scene, SceneBox, SceneConnector};
+use theme::{LineCategory, Theme};
+
+// Load a system font (replace with the path to your TTF or OTF font file)
+const FONT_DATA: &[u8] = include_bytes!("../../data/BF_Modernista-Regular.ttf");
+
+/// The format of the final sheet; cf `SheetCanvas`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Raster; the text is pixelated when printed
+    #[default]
+    Png,
+    /// Vector: boxes, connectors and text are real svg elements, the stick photos are embedded
+    /// Can be edited afterwards eg with Inkscape
+    Svg,
+    /// A single self-contained .html: the svg sheet + hotspots on the buttons (ALL the layers on hover/click)
+    /// + a searchable list of the actions
+    Html,
+}
+
+/// The drawing primitives needed to render a `Scene`; one impl per `OutputFormat`
+trait SheetCanvas {
+    /// Draw an image (png/jpg file content) resized to `size`
+    /// NOTE: like `imageops::resize` the aspect ratio is NOT kept
+    fn draw_image(
+        &mut self,
+        image_bytes: &[u8],
+        top_left_position: (i32, i32),
+        size: (u32, u32),
+    ) -> Result<(), Error>;
+
+    fn draw_box(&mut self, scene_box: &SceneBox);
+
+    /// NOTE: anti-aliased; the shapes of the line and of its marker come from `connector`
+    fn draw_connector(&mut self, connector: &SceneConnector);
+
+    /// Interactive outputs only: the physical button group at `anchor_position` (ie the connector start)
+    /// NOTE: called AFTER its boxes and connector are drawn
+    fn add_hotspot(&mut self, _hotspot: Hotspot) {}
+
+    fn save(&self, path: &Path) -> Result<(), Error>;
+}
+
+/// Combine a game keybinds mapping and a physical joystick configuration and generates a .png
+///
+/// params:
+/// - `game_device_id`: usually "1" or "2"; For Star Citizen, it is e.g. "options type="joystick" instance=" in the exported xml
+/// - `output_format`: the output is written to "`path_to_output_png`" with the extension replaced eg "`output_L.svg`"
+///   NOTE: with "modes" in the template params: one file per mode eg "`output_L_flight.svg`"; and with
+///   `"layers_layout": "sheets"` one per layer eg "`output_L_flight_shift1.svg`"
+/// - `theme`: a built-in theme name eg "light", or the path to a theme json; if `None` the "theme" of the
+///   template params, else "dark"
+/// - `is_strict`: refuse to render when the layout has issues eg overlapping boxes cf `validate`;
+///   else they are only logged
+///
+/// # Errors
+/// - the various files could not be read
+/// - the positions/sizes/etc in `vkb_template_params.json` are not correct
+/// - `is_strict` and the layout has issues
+/// - etc
+///
+pub fn generate_template(
+    game_buttons_mapping: &parse_keybind_xml::GameButtonsMapping,
+    joysticks_mappings: &JoystickButtonsMapping,
+    json_template_params_path: &Path,
+    game_device_id: u8,
+    output_format: OutputFormat,
+    theme: Option<&str>,
+    is_strict: bool,
+) -> Result<(), Error> {
+    let json_params = read_template_json_params(json_template_params_path)?;
+    let theme = get_theme(theme, &json_params)?;
+
+    for sheet_kind in get_sheet_kinds(&json_params.modes, json_params.layers_layout) {
+        let Some(scene) = build_scene(
+            game_buttons_mapping,
+            joysticks_mappings,
+            &json_params,
+            &theme,
+            game_device_id,
+            &sheet_kind,
+        )?
+        else {
+            continue;
+        };
+        check_layout(&scene, is_strict)?;
+
+        render_to_file(
+            &scene,
+            output_format,
+            &sheet_kind.get_output_path(&json_params.path_to_output_png),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Like `generate_template` but for several devices eg both sticks, on ONE sheet cf `composite`
+/// Each device has its own report, template params, game device id and region, in `composite_params_path`
+///
+/// params:
+/// - `vkb_user_provided_data_path`: cf `vkb_parse_and_check_both_sticks`; for ALL the devices, with their `vkb_side`
+/// - `output_format`, `theme`, `is_strict`: cf `generate_template`; `theme` has priority over the "theme"
+///   of the composite params
+///
+/// # Errors
+/// - same as `generate_template`, for each device
+/// - the regions of the devices are out of the sheet, or overlapping
+///
+pub fn generate_composite_template(
+    game_buttons_mapping: &parse_keybind_xml::GameButtonsMapping,
+    composite_params_path: &Path,
+    vkb_user_provided_data_path: &Option<PathBuf>,
+    output_format: OutputFormat,
+    theme: Option<&str>,
+    is_strict: bool,
+) -> Result<(), Error> {
+    let composite_params = composite::read_composite_json_params(composite_params_path)?;
+    let theme = match theme.or(composite_params.theme.as_deref()) {
+        Some(name_or_path) => Theme::from_name_or_path(name_or_path)?,
+        None => Theme::default(),
+    };
+
+    let mut devices = vec![];
+    for device in &composite_params.devices {
+        devices.push((
+            device,
+            crate::vkb::parse_and_check_vkb_both_sticks(
+                &device.vkb_report_path,
+                vkb_user_provided_data_path,
+                device.vkb_side.as_deref(),
+            )?,
+            TemplateJsonParamaters {
+                layers_layout: composite_params.layers_layout,
+                ..read_template_json_params(&device.vkb_template_params_path)?
+            },
+        ));
+    }
+
+    for sheet_kind in get_sheet_kinds(&composite_params.modes, composite_params.layers_layout) {
+        let mut device_scenes = vec![];
+        for (device, joysticks_mappings, json_params) in &devices {
+            if let Some(scene) = build_scene(
+                game_buttons_mapping,
+                joysticks_mappings,
+                json_params,
+                &theme,
+                device.game_device_id,
+                &sheet_kind,
+            )? {
+                device_scenes.push(composite::DeviceScene {
+                    position: device.position,
+                    scene,
+                    groups_count: json_params.buttons_params.len(),
+                });
+            }
+        }
+        // ie a layer on NONE of the devices; NOT an error unlike NO device at all
+        if device_scenes.is_empty() && !devices.is_empty() {
+            continue;
+        }
+
+        let scene = composite::compose_scenes(composite_params.size, &theme, device_scenes)?;
+        check_layout(&scene, is_strict)?;
+
+        render_to_file(
+            &scene,
+            output_format,
+            &sheet_kind.get_output_path(&composite_params.path_to_output_png),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Render `scene` with the `SheetCanvas` of `output_format`, to `path_to_output_png` with the extension replaced
+fn render_to_file(
+    scene: &Scene,
+    output_format: OutputFormat,
+    path_to_output_png: &Path,
+) -> Result<(), Error> {
+    let (mut canvas, path_to_output): (Box<dyn SheetCanvas>, PathBuf) = match output_format {
+        OutputFormat::Png => (
+            Box::new(png::PngCanvas::new(scene.size, FONT_DATA)?),
+            path_to_output_png.to_path_buf(),
+        ),
+        OutputFormat::Svg => (
+            Box::new(svg::SvgCanvas::new(scene.size, FONT_DATA)?),
+            path_to_output_png.with_extension("svg"),
+        ),
+        OutputFormat::Html => (
+            Box::new(html::HtmlCanvas::new(scene.size, FONT_DATA)?),
+            path_to_output_png.with_extension("html"),
+        ),
+    };
+
+    scene.render(canvas.as_mut())?;
+
+    // Save the final image
+    canvas.save(&path_to_output)
+}
+
+/// One stick page of `generate_pdf`; cf the params of `generate_template`
+pub struct PdfSheet<'a> {
+    pub game_buttons_mapping: &'a parse_keybind_xml::GameButtonsMapping,
+    pub joysticks_mappings: &'a JoystickButtonsMapping,
+    pub json_template_params_path: &'a Path,
+    pub game_device_id: u8,
+}
+
+/// Like `generate_template` but for several sticks eg left + right, in a single printable pdf
+/// One page per `sheets` (in order; one per mode and per layer if any), then an optional legend page
+///
+/// # Errors
+/// - same as `generate_template`
+/// - the margins are too big for the paper size
+///
+/// NOTE: `theme` and `is_strict` as in `generate_template`, per sheet; the legend uses the theme of the first sheet
+///
+pub fn generate_pdf(
+    sheets: &[PdfSheet<'_>],
+    options: &PdfOptions,
+    with_legend: bool,
+    path_to_output_pdf: &Path,
+    theme: Option<&str>,
+    is_strict: bool,
+) -> Result<(), Error> {
+    let mut scenes = vec![];
+    let mut legend_theme = None;
+    for sheet in sheets {
+        let json_params = read_template_json_params(sheet.json_template_params_path)?;
+        let sheet_theme = get_theme(theme, &json_params)?;
+        for sheet_kind in get_sheet_kinds(&json_params.modes, json_params.layers_layout) {
+            let Some(scene) = build_scene(
+                sheet.game_buttons_mapping,
+                sheet.joysticks_mappings,
+                &json_params,
+                &sheet_theme,
+                sheet.game_device_id,
+                &sheet_kind,
+            )?
+            else {
+                continue;
+            };
+            check_layout(&scene, is_strict)?;
+            scenes.push(scene);
+        }
+        legend_theme.get_or_insert(sheet_theme);
+    }
+
+    if with_legend {
+        scenes.push(layout::layout_legend(
+            get_legend_lines(sheets),
+            &params::TemplateJsonStyleParameters::default(),
+            &legend_theme.unwrap_or_default(),
+        )?);
+    }
+
+    let mut svg_pages = vec![];
+    for scene in scenes {
+        let mut canvas = svg::SvgCanvas::new(scene.size, FONT_DATA)?;
+        scene.render(&mut canvas)?;
+        svg_pages.push(canvas.to_svg_string());
+    }
+
+    pdf::write_pdf(&svg_pages, FONT_DATA, options, path_to_output_pdf)
+}
+
+/// Place the groups without a box position cf `placement`, and write the resulting template params
+/// to `output_json_path` eg to fine-tune it by hand
+///
+/// # Errors
+/// - `json_template_params_path` could not be read, or `output_json_path` written
+/// - the sizes in the template params do NOT fit in an i32
+///
+pub fn generate_auto_layout(
+    json_template_params_path: &Path,
+    output_json_path: &Path,
+) -> Result<(), Error> {
+    let json_params =
+        placement::place_boxes(&read_template_json_params(json_template_params_path)?)?;
+
+    let json = serde_json::to_string_pretty(&json_params)
+        .map_err(|err| Error::Other(format!("serde_json error : {err}")))?;
+    std::fs::write(output_json_path, json).map_err(|_err| {
+        Error::Other(format!(
+            "could not write the template params to {}",
+            output_json_path.display()
+        ))
+    })
+}
+
+/// `placement`, `resolve` then `layout` ONE sheet
+/// With a mode: ONLY the actions of its actionmaps; with a layer: ONLY the lines of that layer
+/// Their names are in the title block
+///
+/// Returns `None` for a layer with NO line on the whole sheet ie nothing to print
+fn build_scene(
+    game_buttons_mapping: &parse_keybind_xml::GameButtonsMapping,
+    joysticks_mappings: &JoystickButtonsMapping,
+    json_params: &TemplateJsonParamaters,
+    theme: &Theme,
+    game_device_id: u8,
+    sheet_kind: &SheetKind<'_>,
+) -> Result<Option<Scene>, Error> {
+    let json_params = &placement::place_boxes(json_params)?;
+    let mode_game_buttons_mapping = match sheet_kind.mode {
+        Some(mode) => {
+            let mode_game_buttons_mapping =
+                game_buttons_mapping.filter_actionmaps(&mode.actionmaps)?;
+            if mode_game_buttons_mapping.get_actionmaps().is_empty() {
+                log::warn!(
+                    "mode \"{}\": NO actionmap matches {:?}",
+                    mode.name,
+                    mode.actionmaps
+                );
+            }
+            Some(mode_game_buttons_mapping)
+        }
+        None => None,
+    };
+    let mut resolved_groups = resolve::resolve_bindings(
+        mode_game_buttons_mapping
+            .as_ref()
+            .unwrap_or(game_buttons_mapping),
+        joysticks_mappings,
+        json_params,
+        game_device_id,
+    )?;
+    if let Some(layer) = sheet_kind.layer {
+        resolved_groups = resolved_groups
+            .iter()
+            .map(|resolved_group| resolved_group.filter_layer(layer))
+            .collect();
+        if resolved_groups
+            .iter()
+            .all(|resolved_group| resolved_group.keybind_lines.iter().all(String::is_empty))
+        {
+            log::info!("layer {}: NO line, skipped", layer.get_name());
+            return Ok(None);
+        }
+    }
+
+    let font = rusttype::Font::try_from_bytes(FONT_DATA)
+        .ok_or_else(|| Error::Other("Failed to load font".to_string()))?;
+
+    layout::layout_sheet(
+        json_params,
+        theme,
+        resolved_groups,
+        sheet_kind.get_title().as_deref(),
+        &font,
+    )
+    .map(Some)
+}
+
+/// What goes on ONE sheet: ALL the actions, or those of a mode; ALL the layers, or one of them
+struct SheetKind<'a> {
+    mode: Option<&'a TemplateJsonModeParameters>,
+    /// cf `LayersLayout::Sheets`
+    layer: Option<Layer>,
+}
+
+impl SheetKind<'_> {
+    /// eg "Flight - SHIFT1"; `None` if ALL the actions and ALL the layers
+    fn get_title(&self) -> Option<String> {
+        let names: Vec<&str> = self
+            .mode
+            .map(|mode| mode.name.as_str())
+            .into_iter()
+            .chain(self.layer.map(Layer::get_name))
+            .collect();
+
+        (!names.is_empty()).then(|| names.join(" - "))
+    }
+
+    /// `path_to_output_png`, with the mode name then the layer name if any
+    /// cf `TemplateJsonModeParameters::get_output_path`
+    fn get_output_path(&self, path_to_output_png: &Path) -> PathBuf {
+        let path_to_output = self.mode.map_or_else(
+            || path_to_output_png.to_path_buf(),
+            |mode| mode.get_output_path(path_to_output_png),
+        );
+
+        match self.layer {
+            Some(layer) => params::append_to_file_name(&path_to_output, layer.get_name()),
+            None => path_to_output,
+        }
+    }
+}
+
+/// One sheet per mode (or ONE sheet without modes), times one per layer with `LayersLayout::Sheets`
+fn get_sheet_kinds(
+    modes: &[TemplateJsonModeParameters],
+    layers_layout: LayersLayout,
+) -> Vec<SheetKind<'_>> {
+    let modes: Vec<Option<&TemplateJsonModeParameters>> = if modes.is_empty() {
+        vec![None]
+    } else {
+        modes.iter().map(Some).collect()
+    };
+    let layers: Vec<Option<Layer>> = match layers_layout {
+        LayersLayout::Sheets => Layer::ALL.into_iter().map(Some).collect(),
+        LayersLayout::Stacked | LayersLayout::Columns | LayersLayout::Rows => vec![None],
+    };
+
+    modes
+        .into_iter()
+        .flat_map(|mode| {
+            layers.iter().map(move |layer| SheetKind {
+                mode,
+                layer: *layer,
+            })
+        })
+        .collect()
+}
+
+/// Log the issues of `validate::validate_scene`; and with `is_strict`: an error if any
+fn check_layout(scene: &Scene, is_strict: bool) -> Result<(), Error> {
+    let issues: Vec<String> = validate::validate_scene(scene)
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    for issue in &issues {
+        log::warn!("layout: {issue}");
+    }
+
+    if is_strict && !issues.is_empty() {
+        return Err(Error::InvalidLayout(issues));
+    }
+
+    Ok(())
+}
+
+/// `theme` (ie `--theme`) has priority over the "theme" of the template params; default: "dark"
+fn get_theme(theme: Option<&str>, json_params: &TemplateJsonParamaters) -> Result<Theme, Error> {
+    match theme.or(json_params.theme.as_deref()) {
+        Some(name_or_path) => Theme::from_name_or_path(name_or_path),
+        None => Ok(Theme::default()),
+    }
+}
+
+/// The legend page: what the "[SHIFT1]", "tap:" etc prefixes in the boxes mean
+/// For the SHIFT: which physical button(s) of each stick cf `get_shift_buttons_infos`
+fn get_legend_lines(sheets: &[PdfSheet<'_>]) -> Vec<(String, LineCategory)> {
+    let get_shift_buttons = |shift_kind| {
+        sheets
+            .iter()
+            .map(|sheet| {
+                let infos = sheet
+                    .joysticks_mappings
+                    .get_shift_buttons_infos(&shift_kind);
+                let infos = if infos.is_empty() {
+                    "NOT DEFINED".to_string()
+                } else {
+                    infos
+                        .iter()
+                        .map(|info| info.as_str())
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                };
+                format!("js{}: {infos}", sheet.game_device_id)
+            })
+            .collect::<Vec<_>>()
+            .join(" / ")
+    };
+
+    vec![
+        ("LEGEND".to_string(), LineCategory::Base),
+        (
+            format!(
+                "[SHIFT1] action : hold SHIFT1 then press ; {}",
+                get_shift_buttons(crate::button::VirtualShiftKind::Shift1)
+            ),
+            LineCategory::Shift,
+        ),
+        (
+            format!(
+                "[SHIFT2] action : hold SHIFT2 then press ; {}",
+                get_shift_buttons(crate::button::VirtualShiftKind::Shift2)
+            ),
+            LineCategory::Shift,
+        ),
+        (
+            "[SHORT] / [LONG] / [DOUBLE] action : VKB Tempo ie short press, long press, double press"
+                .to_string(),
+            LineCategory::Tempo,
+        ),
+        (
+            "SHIFT1 / tap: action : the button is a SHIFT when held, a normal button when tapped"
+                .to_string(),
+            LineCategory::Modifier,
+        ),
+        (
+            "NO BINDING : the stick sends a button, the game does nothing with it".to_string(),
+            LineCategory::NoBinding,
+        ),
+    ]
+}
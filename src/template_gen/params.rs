@@ -0,0 +1,659 @@
+//! "`vkb_template_params.json`": where and how each button group is drawn on the sheet
+//!
+//! Everything about the geometry and the styling is optional, with defaults matching the original sheet:
+//! - `sheet`: the canvas size and the placement of the stick photos cf `TemplateJsonSheetParameters`
+//! - `style`: the boxes, text and connectors cf `TemplateJsonStyleParameters`
+//! - per button group: `pattern` ie how its boxes are arranged cf `BoxesPattern`, and `style_overrides`
+//!   on top of `style` cf `TemplateJsonStyleOverrides`
+//! - `theme`: the colors NOT set in `style`/`style_overrides` cf `Theme`
+//! - `auto_layout`: for the button groups with ONLY their anchor ie the connector start cf `placement`
+//! - `modes`: one sheet per set of actionmaps eg "Flight" cf `TemplateJsonModeParameters`
+//! - `layers_layout`: where the SHIFT/Tempo layers go ie in the same box, on their own sheet, etc cf `LayersLayout`
+//!
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::Error;
+
+/// Parse the "`vkb_template_params.json`"
+pub(super) fn read_template_json_params(
+    json_template_params_path: &Path,
+) -> Result<TemplateJsonParamaters, Error> {
+    let json_params: TemplateJsonParamaters = serde_json::from_reader(std::io::BufReader::new(
+        std::fs::File::open(json_template_params_path).map_err(|_err| {
+            Error::Other(format!(
+                "failed to open json_template_params_path {}",
+                json_template_params_path.display()
+            ))
+        })?,
+    ))
+    .map_err(|_err| {
+        Error::Other(format!(
+            "serde_json error for {}",
+            json_template_params_path.display()
+        ))
+    })?;
+    log::debug!("json_params : {json_params:?}");
+    json_params.validate()?;
+
+    Ok(json_params)
+}
+
+/// NOTE: this is for one joystick, either L or right
+/// (at least for now)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(super) struct TemplateJsonParamaters {
+    pub(super) path_to_full_png: PathBuf,
+    pub(super) path_to_side_png: PathBuf,
+    pub(super) path_to_output_png: PathBuf,
+    #[serde(default)]
+    pub(super) sheet: TemplateJsonSheetParameters,
+    /// The default style of ALL the button groups
+    #[serde(default)]
+    pub(super) style: TemplateJsonStyleParameters,
+    /// A built-in theme name eg "light", or the path to a theme json; cf `Theme::from_name_or_path`
+    /// NOTE: `--theme` has priority
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) theme: Option<String>,
+    #[serde(default)]
+    pub(super) auto_layout: TemplateJsonAutoLayoutParameters,
+    /// One sheet per mode, with ONLY its actions; if empty: ONE sheet with ALL the actions
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(super) modes: Vec<TemplateJsonModeParameters>,
+    #[serde(default)]
+    pub(super) layers_layout: LayersLayout,
+    pub(super) buttons_params: Vec<TemplateJsonButtonOrStickParameters>,
+}
+
+impl TemplateJsonParamaters {
+    /// CHECK what serde can NOT: a text size of 0, in `style` or in ANY `style_overrides`
+    ///
+    /// # Errors
+    /// - `Error::InvalidLayout` with ALL the invalid values
+    pub(super) fn validate(&self) -> Result<(), Error> {
+        let mut issues = vec![];
+        let mut check_text_sizes =
+            |location: &str, text_size: Option<u32>, min_text_size: Option<u32>| {
+                for (name, value) in [("text_size", text_size), ("min_text_size", min_text_size)] {
+                    if value == Some(0) {
+                        issues.push(format!("{location} : {name} MUST be > 0"));
+                    }
+                }
+            };
+
+        check_text_sizes(
+            "style",
+            Some(self.style.text_size),
+            Some(self.style.min_text_size),
+        );
+        for button_param in &self.buttons_params {
+            check_text_sizes(
+                &format!("\"{}\" style_overrides", button_param.user_desc),
+                button_param.style_overrides.text_size,
+                button_param.style_overrides.min_text_size,
+            );
+        }
+
+        if !issues.is_empty() {
+            return Err(Error::InvalidLayout(issues));
+        }
+
+        Ok(())
+    }
+}
+
+/// This is how/where a button/stick will be drawn in the final composite image
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(super) struct TemplateJsonButtonOrStickParameters {
+    /// Based on whate is written on the stick itself: eg "A1", "F1", etc
+    /// It MUST either match:
+    /// - the "info" field in xml; that would be "(A1)","(F1)" etc for simple buttons
+    /// - OR the "desciption" found in `bindings/vkb_user_provided_data.csv`
+    ///   Typically that would be for the 4-ways/8-ways sticks
+    ///
+    /// List b/c for 4-ways/8-ways/encoders etc we group them and draw all-at-once in a box.
+    /// The order matters cf `BoxesPattern`
+    pub(super) physical_names: Vec<String>,
+    /// User-friendly description: eg "Red thumb button top of stick"
+    pub(super) user_desc: String,
+    /// Should the 3 points below be relative to the "face"(full) png, or the side one?
+    pub(super) is_using_full_png_center_as_reference: bool,
+    /// The top left of the FIRST box cf `layout_boxes`
+    /// `None` (or no connector end): placed automatically cf `placement`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) desired_box_position_relative_to_center_full_png: Option<(i32, i32)>,
+    /// By convention: `start` is the joystick button ie the anchor
+    pub(super) connector_start_line_position_relative_to_center_full_png: (i32, i32),
+    /// By convention: `end` is the box ie near `desired_box_position`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) connector_end_line_position_relative_to_center_full_png: Option<(i32, i32)>,
+    /// Optional points between the connector `start` and `end`, in order ie a polyline
+    /// NOTE: each leg can also be an elbow cf `TemplateJsonStyleParameters::connector_elbow`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(super) connector_waypoints_relative_to_center_full_png: Vec<(i32, i32)>,
+    /// `None`: from the number of `physical_names` cf `get_pattern`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) pattern: Option<BoxesPattern>,
+    /// Only for this group, on top of `TemplateJsonParamaters::style`
+    #[serde(default)]
+    pub(super) style_overrides: TemplateJsonStyleOverrides,
+}
+
+/// How the boxes of a group are arranged, ie where each of `physical_names` goes, in order
+/// NOTE: `desired_box_position_relative_to_center_full_png` is ALWAYS the top left of the box of the FIRST name
+///
+/// eg `{ "kind": "grid", "rows": 3, "cols": 3 }` or `{ "kind": "cross", "center": "last_name" }`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(super) enum BoxesPattern {
+    /// Top to bottom
+    Vertical,
+    /// Left to right
+    Horizontal,
+    /// Row by row, each left to right; only the last row can be incomplete
+    Grid { rows: usize, cols: usize },
+    /// up, right, down, left ie N E S W; then the center cf `BoxesCenter`
+    Cross {
+        #[serde(default)]
+        center: BoxesCenter,
+    },
+    /// Clockwise from the top, as evenly spaced as possible on the cells of a square ring eg N NE E SE S SW W NW;
+    /// then the center cf `BoxesCenter`
+    /// NOTE: 8 slots is a 3x3 grid without its middle; more than 8 and the ring grows ie 16 cells, then 24, etc
+    Ring {
+        #[serde(default)]
+        center: BoxesCenter,
+    },
+}
+
+/// The middle of a `BoxesPattern::Cross`/`BoxesPattern::Ring`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum BoxesCenter {
+    /// Nothing
+    #[default]
+    None,
+    /// An empty box eg a 8-way ministick without a push
+    Empty,
+    /// The box of the LAST physical name eg the "push" of a 4-way hat
+    LastName,
+}
+
+impl BoxesPattern {
+    /// Can `count` boxes be arranged in `self`; NOT eg a grid 2x2 for 5 names, a cross for 3
+    pub(super) fn is_valid_count(self, count: usize) -> bool {
+        match self {
+            _ if count == 0 => false,
+            BoxesPattern::Vertical | BoxesPattern::Horizontal => true,
+            BoxesPattern::Grid { rows, cols } => {
+                rows > 0 && cols > 0 && count <= rows * cols && count > (rows - 1) * cols
+            }
+            BoxesPattern::Cross { center } => {
+                count == 4 + usize::from(center == BoxesCenter::LastName)
+            }
+            BoxesPattern::Ring { center } => {
+                count >= 2 + usize::from(center == BoxesCenter::LastName)
+            }
+        }
+    }
+}
+
+impl TemplateJsonButtonOrStickParameters {
+    /// `pattern`, or by default from the number of physical names:
+    /// - 3: horizontal
+    /// - 5: cross, the last one in the middle eg a 4-way hat + push
+    /// - 8: ring with an empty middle eg a 8-way ministick
+    /// - else: vertical
+    ///
+    /// # Errors
+    /// - the number of physical names does NOT match the pattern cf `BoxesPattern::is_valid_count`
+    pub(super) fn get_pattern(&self) -> Result<BoxesPattern, Error> {
+        let count = self.physical_names.len();
+        let pattern = self.pattern.unwrap_or(match count {
+            3 => BoxesPattern::Horizontal,
+            5 => BoxesPattern::Cross {
+                center: BoxesCenter::LastName,
+            },
+            8 => BoxesPattern::Ring {
+                center: BoxesCenter::Empty,
+            },
+            _ => BoxesPattern::Vertical,
+        });
+
+        if !pattern.is_valid_count(count) {
+            return Err(Error::InvalidBoxesPattern(format!(
+                "\"{}\" : {pattern:?} can NOT have {count} physical names",
+                self.user_desc
+            )));
+        }
+
+        Ok(pattern)
+    }
+
+    /// The global `style` with the `style_overrides` of this group applied
+    pub(super) fn get_style(
+        &self,
+        style: &TemplateJsonStyleParameters,
+    ) -> TemplateJsonStyleParameters {
+        let overrides = &self.style_overrides;
+        TemplateJsonStyleParameters {
+            box_size: overrides.box_size.unwrap_or(style.box_size),
+            box_padding: overrides.box_padding.unwrap_or(style.box_padding),
+            box_color: overrides.box_color.or(style.box_color),
+            box_border_color: overrides.box_border_color.or(style.box_border_color),
+            box_stroke_thickness: overrides
+                .box_stroke_thickness
+                .unwrap_or(style.box_stroke_thickness),
+            text_color: overrides.text_color.or(style.text_color),
+            text_size: overrides.text_size.unwrap_or(style.text_size),
+            min_text_size: overrides.min_text_size.unwrap_or(style.min_text_size),
+            connector_color: overrides.connector_color.or(style.connector_color),
+            connector_thickness: overrides
+                .connector_thickness
+                .unwrap_or(style.connector_thickness),
+            connector_elbow: overrides.connector_elbow.unwrap_or(style.connector_elbow),
+            connector_marker: overrides.connector_marker.unwrap_or(style.connector_marker),
+        }
+    }
+}
+
+/// The canvas, and where the 2 photos go
+/// NOTE: the photos are resized to EXACTLY `*_png_size` ie the aspect ratio is NOT kept
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub(super) struct TemplateJsonSheetParameters {
+    /// In pixels; width x height
+    pub(super) size: (u32, u32),
+    pub(super) full_png_size: (u32, u32),
+    /// The top left corner of the full png, as a fraction of `size` eg (0.3, 0.0)
+    pub(super) full_png_position_fraction: (f32, f32),
+    pub(super) side_png_size: (u32, u32),
+    /// cf `full_png_position_fraction`
+    pub(super) side_png_position_fraction: (f32, f32),
+    /// The top left corner of the title block ie the mode name cf `TemplateJsonModeParameters`, in pixels
+    pub(super) title_position: (i32, i32),
+}
+
+impl Default for TemplateJsonSheetParameters {
+    fn default() -> Self {
+        Self {
+            size: (4000, 2000),
+            full_png_size: (1800, 1800),
+            full_png_position_fraction: (0.3, 0.0),
+            side_png_size: (1200, 1200),
+            side_png_position_fraction: (0.05, 0.3),
+            title_position: (40, 40),
+        }
+    }
+}
+
+/// A set of actionmaps eg "Flight" = `["spaceship_*", "seat_general"]`; cf `GameButtonsMapping::filter_actionmaps`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(super) struct TemplateJsonModeParameters {
+    /// In the title block of its sheet, and in the name of its output file
+    pub(super) name: String,
+    /// Globs eg "`spaceship_*`"; an action is on the sheet if ANY of its actionmaps matches
+    pub(super) actionmaps: Vec<String>,
+}
+
+impl TemplateJsonModeParameters {
+    /// `path_to_output` with the mode name appended eg "`output_L.png`" -> "`output_L_flight.png`"
+    pub(super) fn get_output_path(&self, path_to_output: &Path) -> PathBuf {
+        append_to_file_name(path_to_output, &self.name)
+    }
+}
+
+/// `path_to_output` with `name` appended eg "`output_L.png`" + "On foot (FPS)" -> "`output_L_on_foot_fps.png`"
+pub(super) fn append_to_file_name(path_to_output: &Path, name: &str) -> PathBuf {
+    let suffix = name
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("_");
+    let mut file_name = path_to_output
+        .file_stem()
+        .unwrap_or_default()
+        .to_os_string();
+    file_name.push(format!("_{suffix}"));
+    if let Some(extension) = path_to_output.extension() {
+        file_name.push(".");
+        file_name.push(extension);
+    }
+
+    path_to_output.with_file_name(file_name)
+}
+
+/// Where the modifier layers go ie SHIFT1, SHIFT2 and the Tempo LONG/DOUBLE; cf `resolve::Layer`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum LayersLayout {
+    /// ALL in the same box, one after the other eg "`[SHIFT1] v_attack_all`"
+    #[default]
+    Stacked,
+    /// One sheet per layer eg "`output_L_shift1.png`"; the layers with NO line on the whole sheet are skipped
+    Sheets,
+    /// Each box split in one column per layer, base first; so that the base actions line up across the stick
+    Columns,
+    /// Same as `Columns` but top -> bottom
+    Rows,
+}
+
+/// How each leg of a connector goes from one point to the next
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum ConnectorElbow {
+    /// A straight line, whatever the angle
+    #[default]
+    None,
+    /// Horizontal then vertical ie a right angle
+    HorizontalFirst,
+    /// Vertical then horizontal
+    VerticalFirst,
+}
+
+/// What is drawn at the button end of a connector ie its `start`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum ConnectorMarker {
+    #[default]
+    None,
+    Dot,
+    /// Pointing to the button
+    Arrow,
+}
+
+/// Where `placement` puts the boxes: in a column on the left of the sheet, and one on the right
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub(super) struct TemplateJsonAutoLayoutParameters {
+    /// Between the columns and the edges of the sheet; horizontal, vertical
+    pub(super) margin: (i32, i32),
+    /// Between 2 button groups of the same column
+    pub(super) gap: i32,
+}
+
+impl Default for TemplateJsonAutoLayoutParameters {
+    fn default() -> Self {
+        Self {
+            margin: (40, 40),
+            gap: 30,
+        }
+    }
+}
+
+/// Colors are RGBA eg `[50, 50, 50, 220]`; `None` means "from the `Theme`"
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub(super) struct TemplateJsonStyleParameters {
+    /// ONE box ie for ONE physical name; width x height
+    pub(super) box_size: (u32, u32),
+    /// Between the boxes of a group; horizontal, vertical
+    pub(super) box_padding: (i32, i32),
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) box_color: Option<[u8; 4]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) box_border_color: Option<[u8; 4]>,
+    pub(super) box_stroke_thickness: i32,
+    /// NOTE: the lines with a category eg "[SHIFT1] ..." use the color of the `Theme`, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) text_color: Option<[u8; 4]>,
+    pub(super) text_size: u32,
+    /// When the text does NOT fit in the box, it is wrapped then shrunk down to this, then truncated
+    pub(super) min_text_size: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) connector_color: Option<[u8; 4]>,
+    /// The SAME at any angle
+    pub(super) connector_thickness: i32,
+    pub(super) connector_elbow: ConnectorElbow,
+    pub(super) connector_marker: ConnectorMarker,
+}
+
+impl Default for TemplateJsonStyleParameters {
+    fn default() -> Self {
+        Self {
+            box_size: (500, 110),
+            box_padding: (10, 10),
+            box_color: None,
+            box_border_color: None,
+            box_stroke_thickness: 2,
+            text_color: None,
+            text_size: 24,
+            min_text_size: 14,
+            connector_color: None,
+            connector_thickness: 4,
+            connector_elbow: ConnectorElbow::None,
+            connector_marker: ConnectorMarker::None,
+        }
+    }
+}
+
+/// cf `TemplateJsonStyleParameters`; `None` means "use the global one"
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub(super) struct TemplateJsonStyleOverrides {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) box_size: Option<(u32, u32)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) box_padding: Option<(i32, i32)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) box_color: Option<[u8; 4]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) box_border_color: Option<[u8; 4]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) box_stroke_thickness: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) text_color: Option<[u8; 4]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) text_size: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) min_text_size: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) connector_color: Option<[u8; 4]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) connector_thickness: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) connector_elbow: Option<ConnectorElbow>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) connector_marker: Option<ConnectorMarker>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_json_defaults_and_overrides() {
+        let json_params: TemplateJsonParamaters = serde_json::from_str(
+            r#"{
+                "path_to_full_png": "full.png",
+                "path_to_side_png": "side.png",
+                "path_to_output_png": "output.png",
+                "style": { "text_size": 30 },
+                "buttons_params": [
+                    {
+                        "physical_names": ["(A2)"],
+                        "user_desc": "Red thumb button",
+                        "is_using_full_png_center_as_reference": true,
+                        "desired_box_position_relative_to_center_full_png": [0, 0],
+                        "connector_start_line_position_relative_to_center_full_png": [0, 0],
+                        "connector_end_line_position_relative_to_center_full_png": [0, 0],
+                        "style_overrides": { "box_size": [300, 80], "box_color": [255, 0, 0, 255] }
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(json_params.sheet, TemplateJsonSheetParameters::default());
+        assert_eq!(json_params.style.text_size, 30);
+        assert_eq!(json_params.style.box_size, (500, 110));
+
+        let style = json_params.buttons_params[0].get_style(&json_params.style);
+        assert_eq!(style.box_size, (300, 80));
+        assert_eq!(style.box_color, Some([255, 0, 0, 255]));
+        // NOT overridden: the global one
+        assert_eq!(style.text_size, 30);
+        // NOT set at all: from the theme
+        assert_eq!(style.connector_color, None);
+    }
+
+    #[test]
+    fn test_template_json_validate_text_sizes() {
+        let new_json_params = |style: &str, style_overrides: &str| -> TemplateJsonParamaters {
+            serde_json::from_str(&format!(
+                r#"{{
+                    "path_to_full_png": "full.png",
+                    "path_to_side_png": "side.png",
+                    "path_to_output_png": "output.png",
+                    "style": {style},
+                    "buttons_params": [
+                        {{
+                            "physical_names": ["(A2)"],
+                            "user_desc": "Red thumb button",
+                            "is_using_full_png_center_as_reference": true,
+                            "connector_start_line_position_relative_to_center_full_png": [0, 0],
+                            "style_overrides": {style_overrides}
+                        }}
+                    ]
+                }}"#
+            ))
+            .unwrap()
+        };
+
+        assert!(new_json_params("{}", r#"{ "text_size": 12 }"#)
+            .validate()
+            .is_ok());
+        // globally
+        let Err(Error::InvalidLayout(issues)) =
+            new_json_params(r#"{ "text_size": 0, "min_text_size": 0 }"#, "{}").validate()
+        else {
+            panic!("text_size 0 SHOULD be rejected");
+        };
+        assert_eq!(issues.len(), 2);
+        // only for one group
+        let Err(Error::InvalidLayout(issues)) =
+            new_json_params("{}", r#"{ "min_text_size": 0 }"#).validate()
+        else {
+            panic!("min_text_size 0 SHOULD be rejected");
+        };
+        assert_eq!(
+            issues,
+            vec!["\"Red thumb button\" style_overrides : min_text_size MUST be > 0".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_mode_get_output_path() {
+        let mode = TemplateJsonModeParameters {
+            name: "On foot (FPS)".to_string(),
+            actionmaps: vec!["player*".to_string()],
+        };
+
+        assert_eq!(
+            mode.get_output_path(Path::new("./output_L.png")),
+            PathBuf::from("./output_L_on_foot_fps.png")
+        );
+        assert_eq!(
+            mode.get_output_path(Path::new("output")),
+            PathBuf::from("output_on_foot_fps")
+        );
+    }
+
+    #[test]
+    fn test_template_json_patterns() {
+        let new_button_param = |physical_names_count: usize, pattern: &str| {
+            let physical_names: Vec<String> = (0..physical_names_count)
+                .map(|no| format!("\"({no})\""))
+                .collect();
+            serde_json::from_str::<TemplateJsonButtonOrStickParameters>(&format!(
+                r#"{{
+                    "physical_names": [{}],
+                    "user_desc": "hat",
+                    "is_using_full_png_center_as_reference": true,
+                    "connector_start_line_position_relative_to_center_full_png": [0, 0]
+                    {pattern}
+                }}"#,
+                physical_names.join(", ")
+            ))
+            .unwrap()
+        };
+
+        // the defaults
+        assert_eq!(
+            new_button_param(3, "").get_pattern().unwrap(),
+            BoxesPattern::Horizontal
+        );
+        assert_eq!(
+            new_button_param(5, "").get_pattern().unwrap(),
+            BoxesPattern::Cross {
+                center: BoxesCenter::LastName
+            }
+        );
+        assert_eq!(
+            new_button_param(8, "").get_pattern().unwrap(),
+            BoxesPattern::Ring {
+                center: BoxesCenter::Empty
+            }
+        );
+        assert_eq!(
+            new_button_param(4, "").get_pattern().unwrap(),
+            BoxesPattern::Vertical
+        );
+
+        // explicit
+        assert_eq!(
+            new_button_param(4, r#", "pattern": { "kind": "cross" }"#)
+                .get_pattern()
+                .unwrap(),
+            BoxesPattern::Cross {
+                center: BoxesCenter::None
+            }
+        );
+        assert_eq!(
+            new_button_param(
+                9,
+                r#", "pattern": { "kind": "grid", "rows": 3, "cols": 3 }"#
+            )
+            .get_pattern()
+            .unwrap(),
+            BoxesPattern::Grid { rows: 3, cols: 3 }
+        );
+        assert_eq!(
+            new_button_param(
+                7,
+                r#", "pattern": { "kind": "ring", "center": "last_name" }"#
+            )
+            .get_pattern()
+            .unwrap(),
+            BoxesPattern::Ring {
+                center: BoxesCenter::LastName
+            }
+        );
+
+        // invalid counts
+        for (physical_names_count, pattern) in [
+            (5, r#", "pattern": { "kind": "cross" }"#),
+            (3, r#", "pattern": { "kind": "cross", "center": "empty" }"#),
+            (
+                6,
+                r#", "pattern": { "kind": "grid", "rows": 3, "cols": 3 }"#,
+            ),
+            (
+                2,
+                r#", "pattern": { "kind": "grid", "rows": 0, "cols": 3 }"#,
+            ),
+            (
+                2,
+                r#", "pattern": { "kind": "ring", "center": "last_name" }"#,
+            ),
+            (0, ""),
+        ] {
+            assert!(matches!(
+                new_button_param(physical_names_count, pattern).get_pattern(),
+                Err(Error::InvalidBoxesPattern(_))
+            ));
+        }
+    }
+}
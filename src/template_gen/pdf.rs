@@ -0,0 +1,325 @@
+//! Printable multi-page pdf: one page per stick (cf `SvgCanvas`) + an optional legend page
+//!
+//! Each sheet is converted svg -> pdf "form `XObject`" with `svg2pdf`, then placed on its page:
+//! centered in the printable area (ie inside the margins), at its natural size for the given DPI,
+//! and shrunk if it does not fit.
+//! NOTE: everything stays vector, except the stick photos which are embedded as-is.
+//!
+//! cf `src/ARCHIVE/pdf_merge.rs` for the previous (lopdf) experiment.
+//!
+
+use std::path::Path;
+
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref};
+use svg2pdf::usvg::{self, fontdb, TreeParsing, TreePostProc};
+
+use crate::Error;
+
+/// 1 inch = 72 pt
+const POINTS_PER_INCH: f32 = 72.0;
+const POINTS_PER_MM: f32 = POINTS_PER_INCH / 25.4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum PaperSize {
+    #[default]
+    A4,
+    Letter,
+    A3,
+}
+
+impl PaperSize {
+    /// (width, height) in points, portrait
+    fn get_size_points(self) -> (f32, f32) {
+        match self {
+            PaperSize::A4 => (210.0 * POINTS_PER_MM, 297.0 * POINTS_PER_MM),
+            PaperSize::Letter => (8.5 * POINTS_PER_INCH, 11.0 * POINTS_PER_INCH),
+            PaperSize::A3 => (297.0 * POINTS_PER_MM, 420.0 * POINTS_PER_MM),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Orientation {
+    Portrait,
+    /// The sheets are wider than tall so this is the default
+    #[default]
+    Landscape,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PdfOptions {
+    pub paper_size: PaperSize,
+    pub orientation: Orientation,
+    /// On all 4 sides; MUST be >= 0
+    pub margin_mm: f32,
+    /// The sheets are drawn at "pixels / dpi" inches, shrunk if needed to fit in the margins
+    /// eg a 4000 px wide sheet at 300 DPI is 13.3 inches ie more than A4 landscape -> shrunk
+    /// MUST be > 0
+    pub dpi: f32,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        Self {
+            paper_size: PaperSize::default(),
+            orientation: Orientation::default(),
+            margin_mm: 10.0,
+            dpi: 300.0,
+        }
+    }
+}
+
+impl PdfOptions {
+    /// (width, height) in points
+    fn get_page_size_points(&self) -> (f32, f32) {
+        let (width, height) = self.paper_size.get_size_points();
+        match self.orientation {
+            Orientation::Portrait => (width, height),
+            Orientation::Landscape => (height, width),
+        }
+    }
+}
+
+/// Write ALL the `svg_pages` (in order) to `path_to_output_pdf`, one per page
+/// `font_data`: the font used by the svg `<text>` cf `SvgCanvas`
+pub(super) fn write_pdf(
+    svg_pages: &[String],
+    font_data: &[u8],
+    options: &PdfOptions,
+    path_to_output_pdf: &Path,
+) -> Result<(), Error> {
+    let pdf_bytes = svg_pages_to_pdf(svg_pages, font_data, options)?;
+
+    std::fs::write(path_to_output_pdf, pdf_bytes).map_err(|_err| {
+        Error::Other(format!(
+            "could not write pdf to {}",
+            path_to_output_pdf.display()
+        ))
+    })
+}
+
+fn svg_pages_to_pdf(
+    svg_pages: &[String],
+    font_data: &[u8],
+    options: &PdfOptions,
+) -> Result<Vec<u8>, Error> {
+    let mut fontdb = fontdb::Database::new();
+    fontdb.load_font_data(font_data.to_vec());
+
+    // NOT a NaN/inf page, nor a division by 0
+    if !options.dpi.is_finite() || options.dpi <= 0.0 {
+        return Err(Error::Other(format!(
+            "pdf: the DPI MUST be > 0, got {}",
+            options.dpi
+        )));
+    }
+    if !options.margin_mm.is_finite() || options.margin_mm < 0.0 {
+        return Err(Error::Other(format!(
+            "pdf: the margins MUST be >= 0, got {}mm",
+            options.margin_mm
+        )));
+    }
+
+    let (page_width, page_height) = options.get_page_size_points();
+    let margin = options.margin_mm * POINTS_PER_MM;
+    let (printable_width, printable_height) =
+        (page_width - 2.0 * margin, page_height - 2.0 * margin);
+    if printable_width <= 0.0 || printable_height <= 0.0 {
+        return Err(Error::Other(format!(
+            "pdf: margins of {}mm are too big for the page",
+            options.margin_mm
+        )));
+    }
+
+    let mut pdf = Pdf::new();
+    // NOTE: `bump` returns the current ref, and increments
+    let mut next_ref = Ref::new(1);
+    let catalog_ref = next_ref.bump();
+    let page_tree_ref = next_ref.bump();
+    let svg_name = Name(b"S1");
+
+    let mut page_refs = vec![];
+    let mut pending_pages = vec![];
+    for svg_page in svg_pages {
+        let mut tree = usvg::Tree::from_str(svg_page, &usvg::Options::default())
+            .map_err(|err| Error::Other(format!("pdf: invalid svg : {err}")))?;
+        // `svg2pdf` does NOT handle the text: convert to paths, with the embedded font
+        tree.postprocess(
+            usvg::PostProcessingSteps {
+                convert_text_into_paths: true,
+            },
+            &fontdb,
+        );
+
+        // Natural size at `dpi`, then shrink to fit; NEVER enlarged
+        let natural_width = tree.size.width() / options.dpi * POINTS_PER_INCH;
+        let natural_height = tree.size.height() / options.dpi * POINTS_PER_INCH;
+        let scale = (printable_width / natural_width)
+            .min(printable_height / natural_height)
+            .min(1.0);
+        let (width, height) = (natural_width * scale, natural_height * scale);
+        // Centered; NOTE: pdf y axis is bottom -> top
+        let (x, y) = ((page_width - width) / 2.0, (page_height - height) / 2.0);
+
+        let page_ref = next_ref.bump();
+        let content_ref = next_ref.bump();
+        let svg_ref = next_ref;
+        // `convert_tree_into` allocates its own refs starting at `svg_ref`
+        next_ref =
+            svg2pdf::convert_tree_into(&tree, svg2pdf::Options::default(), &mut pdf, svg_ref);
+
+        let mut content = Content::new();
+        content
+            .save_state()
+            .transform([width, 0.0, 0.0, height, x, y])
+            .x_object(svg_name)
+            .restore_state();
+        pdf.stream(content_ref, &content.finish());
+
+        page_refs.push(page_ref);
+        pending_pages.push((page_ref, content_ref, svg_ref));
+    }
+
+    for (page_ref, content_ref, svg_ref) in pending_pages {
+        let mut page = pdf.page(page_ref);
+        page.media_box(Rect::new(0.0, 0.0, page_width, page_height));
+        page.parent(page_tree_ref);
+        page.contents(content_ref);
+        page.resources().x_objects().pair(svg_name, svg_ref);
+        page.finish();
+    }
+
+    pdf.catalog(catalog_ref).pages(page_tree_ref);
+    let page_count = i32::try_from(page_refs.len()).map_err(Error::TryFromIntError)?;
+    pdf.pages(page_tree_ref).kids(page_refs).count(page_count);
+
+    Ok(pdf.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_svg_pages_to_pdf() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="4000" height="2000" viewBox="0 0 4000 2000">
+<rect x="10" y="10" width="500" height="110" fill="rgb(50,50,50)"/>
+</svg>"#
+            .to_string();
+
+        let pdf_bytes = svg_pages_to_pdf(
+            &[svg.clone(), svg.clone(), svg],
+            include_bytes!("../../data/BF_Modernista-Regular.ttf"),
+            &PdfOptions::default(),
+        )
+        .unwrap();
+
+        assert!(pdf_bytes.starts_with(b"%PDF"));
+        let pdf_str = String::from_utf8_lossy(&pdf_bytes);
+        assert_eq!(pdf_str.matches("/Type /Page\n").count(), 3);
+        assert!(pdf_str.contains("/Count 3"));
+        // A4 landscape
+        assert!(pdf_str.contains("/MediaBox [0 0 841.8898 595.27563]"));
+    }
+
+    #[test]
+    fn test_pdf_options_margins_too_big() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"></svg>"#;
+        assert!(svg_pages_to_pdf(
+            &[svg.to_string()],
+            include_bytes!("../../data/BF_Modernista-Regular.ttf"),
+            &PdfOptions {
+                margin_mm: 200.0,
+                ..Default::default()
+            },
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_pdf_options_invalid_dpi_or_margins() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"></svg>"#;
+        for options in [
+            PdfOptions {
+                dpi: 0.0,
+                ..Default::default()
+            },
+            PdfOptions {
+                dpi: -300.0,
+                ..Default::default()
+            },
+            PdfOptions {
+                margin_mm: -10.0,
+                ..Default::default()
+            },
+        ] {
+            assert!(
+                svg_pages_to_pdf(
+                    &[svg.to_string()],
+                    include_bytes!("../../data/BF_Modernista-Regular.ttf"),
+                    &options,
+                )
+                .is_err(),
+                "{options:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_generate_pdf_two_sticks_and_legend() {
+        // NOTE: the template params use the user provided descriptions
+        let joysticks_mappings = crate::vkb::parse_and_check_vkb_both_sticks(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/vkb_report_L.fp3").as_ref(),
+            &Some(
+                concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/data/vkb_user_provided_data.csv"
+                )
+                .into(),
+            ),
+            None,
+        )
+        .unwrap();
+        let game_buttons_mapping = crate::sc::parse_keybind_xml::parse_keybind(
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/data/layout_vkb_exported.xml"
+            )
+            .into(),
+            None,
+        )
+        .unwrap();
+        // NOTE: the L stick twice, as if it were "left" and "right"
+        let sheet = || super::super::PdfSheet {
+            game_buttons_mapping: &game_buttons_mapping,
+            joysticks_mappings: &joysticks_mappings,
+            json_template_params_path: concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/data/vkb_template_params_left.json"
+            )
+            .as_ref(),
+            game_device_id: 2,
+        };
+        let path_to_output_pdf = std::env::temp_dir().join("test_generate_pdf.pdf");
+
+        super::super::generate_pdf(
+            &[sheet(), sheet()],
+            &PdfOptions {
+                paper_size: PaperSize::Letter,
+                ..Default::default()
+            },
+            true,
+            &path_to_output_pdf,
+            Some("print"),
+            false,
+        )
+        .unwrap();
+
+        let pdf_bytes = std::fs::read(&path_to_output_pdf).unwrap();
+        assert!(pdf_bytes.starts_with(b"%PDF"));
+        let pdf_str = String::from_utf8_lossy(&pdf_bytes);
+        assert_eq!(pdf_str.matches("/Type /Page\n").count(), 3);
+        // Letter landscape
+        assert!(pdf_str.contains("/MediaBox [0 0 792 612]"));
+    }
+}
@@ -0,0 +1,440 @@
+//! Automatic placement of the button groups given ONLY their anchor on the photo
+//! ie `connector_start_line_position_relative_to_center_full_png`, WITHOUT a box position or a connector end
+//!
+//! - each group goes in a column along the edge of the sheet, aligned on its inner side: left if its anchor is left of the center
+//!   of the full png, else right; a column too tall gives the groups closest to the center to the other one
+//! - in a column: sorted by the height of the anchors, each group as close as possible to its anchor,
+//!   WITHOUT overlapping the previous one
+//! - the connector ends in the middle of the inner side of the group; 2 crossing connectors swap their groups
+//!
+//! The result is a regular template ie it can be written back and fine-tuned by hand cf `generate_auto_layout`
+//!
+//! NOTE: the groups already placed are kept as-is and NOT avoided; `validate` reports the collisions
+//!
+
+use super::layout;
+use super::params::{TemplateJsonAutoLayoutParameters, TemplateJsonParamaters};
+use crate::Error;
+
+/// start, end
+type Segment = ((i32, i32), (i32, i32));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Left,
+    Right,
+}
+
+/// A group to place; the positions are absolute
+#[derive(Debug, Clone)]
+struct GroupToPlace {
+    /// In `buttons_params`
+    index: usize,
+    reference_point: (i32, i32),
+    anchor: (i32, i32),
+    /// cf `layout::get_boxes_extent`
+    extent: (i32, i32, i32, i32),
+    /// The top of ALL its boxes cf `stack_column`
+    top: i32,
+}
+
+impl GroupToPlace {
+    fn get_size(&self) -> (i32, i32) {
+        (self.extent.2 - self.extent.0, self.extent.3 - self.extent.1)
+    }
+}
+
+/// The groups on one side of the sheet, from top to bottom
+struct Column {
+    side: Side,
+    groups: Vec<GroupToPlace>,
+}
+
+impl Column {
+    fn get_height(&self, gap: i32) -> i32 {
+        self.groups
+            .iter()
+            .map(|group| group.get_size().1 + gap)
+            .sum::<i32>()
+            - gap
+    }
+
+    /// The left of the boxes of `group`
+    /// NOTE: aligned on the INNER side ie the one of the connectors; so a wide group does NOT stick out
+    /// over the connectors of the narrow ones
+    fn get_left(&self, group: &GroupToPlace, sheet_width: i32, margin: i32) -> i32 {
+        let max_width = self
+            .groups
+            .iter()
+            .map(|group| group.get_size().0)
+            .max()
+            .unwrap_or_default();
+        match self.side {
+            Side::Left => margin + max_width - group.get_size().0,
+            Side::Right => sheet_width - margin - max_width,
+        }
+    }
+
+    /// Anchor -> the middle of the inner side of the group
+    fn get_connector(&self, group: &GroupToPlace, sheet_width: i32, margin: i32) -> Segment {
+        let (width, height) = group.get_size();
+        let left = self.get_left(group, sheet_width, margin);
+        let end_x = match self.side {
+            Side::Left => left + width,
+            Side::Right => left,
+        };
+
+        (group.anchor, (end_x, group.top + height / 2))
+    }
+}
+
+/// A copy of `json_params` where EVERY group has a box position and a connector end
+/// NOTE: the positions are relative to the reference point of each group, like the hand-written ones
+///
+/// # Errors
+/// - the sizes in the template params do NOT fit in an i32
+pub(super) fn place_boxes(
+    json_params: &TemplateJsonParamaters,
+) -> Result<TemplateJsonParamaters, Error> {
+    let sheet = &json_params.sheet;
+    let auto_layout = &json_params.auto_layout;
+    let sheet_size = (
+        i32::try_from(sheet.size.0).map_err(Error::TryFromIntError)?,
+        i32::try_from(sheet.size.1).map_err(Error::TryFromIntError)?,
+    );
+    let full_png_center = layout::get_reference_point(sheet, true)?;
+
+    let mut left_column = Column {
+        side: Side::Left,
+        groups: vec![],
+    };
+    let mut right_column = Column {
+        side: Side::Right,
+        groups: vec![],
+    };
+    for (index, button_param) in json_params.buttons_params.iter().enumerate() {
+        if button_param
+            .desired_box_position_relative_to_center_full_png
+            .is_some()
+            && button_param
+                .connector_end_line_position_relative_to_center_full_png
+                .is_some()
+        {
+            continue;
+        }
+
+        let reference_point =
+            layout::get_reference_point(sheet, button_param.is_using_full_png_center_as_reference)?;
+        let relative_anchor =
+            button_param.connector_start_line_position_relative_to_center_full_png;
+        let group = GroupToPlace {
+            index,
+            reference_point,
+            anchor: (
+                reference_point.0 + relative_anchor.0,
+                reference_point.1 + relative_anchor.1,
+            ),
+            extent: layout::get_boxes_extent(button_param, &json_params.style)?,
+            top: 0,
+        };
+        if group.anchor.0 < full_png_center.0 {
+            left_column.groups.push(group);
+        } else {
+            right_column.groups.push(group);
+        }
+    }
+
+    let available_height = sheet_size.1 - 2 * auto_layout.margin.1;
+    while move_one_group(
+        &mut left_column,
+        &mut right_column,
+        available_height,
+        auto_layout.gap,
+    ) || move_one_group(
+        &mut right_column,
+        &mut left_column,
+        available_height,
+        auto_layout.gap,
+    ) {}
+
+    let mut placed_params = json_params.clone();
+    for column in [&mut left_column, &mut right_column] {
+        column.groups.sort_by_key(|group| group.anchor.1);
+        uncross_column(column, sheet_size, auto_layout);
+
+        for group in &column.groups {
+            let left = column.get_left(group, sheet_size.0, auto_layout.margin.0);
+            let (_, connector_end) =
+                column.get_connector(group, sheet_size.0, auto_layout.margin.0);
+            let button_param = &mut placed_params.buttons_params[group.index];
+            button_param.desired_box_position_relative_to_center_full_png = Some((
+                left - group.extent.0 - group.reference_point.0,
+                group.top - group.extent.1 - group.reference_point.1,
+            ));
+            button_param.connector_end_line_position_relative_to_center_full_png = Some((
+                connector_end.0 - group.reference_point.0,
+                connector_end.1 - group.reference_point.1,
+            ));
+        }
+    }
+
+    Ok(placed_params)
+}
+
+/// `from` is too tall: give its group closest to the center to `to`, if it has room
+/// Returns whether one was moved
+fn move_one_group(from: &mut Column, to: &mut Column, available_height: i32, gap: i32) -> bool {
+    if from.groups.is_empty() || from.get_height(gap) <= available_height {
+        return false;
+    }
+
+    let closest = (0..from.groups.len()).max_by_key(|&index| match from.side {
+        Side::Left => from.groups[index].anchor.0,
+        Side::Right => -from.groups[index].anchor.0,
+    });
+    let Some(closest) = closest else {
+        return false;
+    };
+    let closest_height = from.groups[closest].get_size().1;
+    let to_height = if to.groups.is_empty() {
+        closest_height
+    } else {
+        to.get_height(gap) + gap + closest_height
+    };
+    if to_height > available_height {
+        return false;
+    }
+
+    to.groups.push(from.groups.remove(closest));
+    true
+}
+
+/// Set the `top` of each group, in order: as close as possible to its anchor, without overlapping,
+/// inside the vertical margins
+/// NOTE: if the column is too tall anyway, the bottom groups overlap/go out of the sheet
+fn stack_column(
+    column: &mut Column,
+    sheet_height: i32,
+    auto_layout: &TemplateJsonAutoLayoutParameters,
+) {
+    let (_, margin) = auto_layout.margin;
+
+    let mut min_top = margin;
+    for group in &mut column.groups {
+        group.top = (group.anchor.1 - group.get_size().1 / 2).max(min_top);
+        min_top = group.top + group.get_size().1 + auto_layout.gap;
+    }
+
+    // pushed out of the bottom: move them back up
+    let mut max_bottom = sheet_height - margin;
+    for group in column.groups.iter_mut().rev() {
+        group.top = group.top.min(max_bottom - group.get_size().1);
+        max_bottom = group.top - auto_layout.gap;
+    }
+
+    // pushed out of the top: the top wins
+    let mut min_top = margin;
+    for group in &mut column.groups {
+        group.top = group.top.max(min_top);
+        min_top = group.top + group.get_size().1 + auto_layout.gap;
+    }
+}
+
+/// `stack_column`, then swap the groups with crossing connectors until there is none
+/// NOTE: bounded; some crossings can NOT be removed eg an anchor between the connectors of 2 others
+fn uncross_column(
+    column: &mut Column,
+    sheet_size: (i32, i32),
+    auto_layout: &TemplateJsonAutoLayoutParameters,
+) {
+    let groups_count = column.groups.len();
+    for _ in 0..groups_count * groups_count {
+        stack_column(column, sheet_size.1, auto_layout);
+
+        let connectors: Vec<_> = column
+            .groups
+            .iter()
+            .map(|group| column.get_connector(group, sheet_size.0, auto_layout.margin.0))
+            .collect();
+        let crossing = (0..groups_count).find_map(|first| {
+            (first + 1..groups_count)
+                .find(|&second| is_crossing(connectors[first], connectors[second]))
+                .map(|second| (first, second))
+        });
+        match crossing {
+            Some((first, second)) => column.groups.swap(first, second),
+            None => return,
+        }
+    }
+
+    stack_column(column, sheet_size.1, auto_layout);
+}
+
+/// Do the 2 segments cross ie NOT only touch
+fn is_crossing(first: Segment, second: Segment) -> bool {
+    let orientation = |from: (i32, i32), to: (i32, i32), point: (i32, i32)| -> i64 {
+        let cross = (i64::from(to.0) - i64::from(from.0))
+            * (i64::from(point.1) - i64::from(from.1))
+            - (i64::from(to.1) - i64::from(from.1)) * (i64::from(point.0) - i64::from(from.0));
+        cross.signum()
+    };
+
+    orientation(first.0, first.1, second.0) * orientation(first.0, first.1, second.1) < 0
+        && orientation(second.0, second.1, first.0) * orientation(second.0, second.1, first.1) < 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_json_params(buttons_params: &str) -> TemplateJsonParamaters {
+        serde_json::from_str(&format!(
+            r#"{{
+                "path_to_full_png": "full.png",
+                "path_to_side_png": "side.png",
+                "path_to_output_png": "output.png",
+                "buttons_params": [{buttons_params}]
+            }}"#
+        ))
+        .unwrap()
+    }
+
+    fn new_button_param(user_desc: &str, pattern: usize, anchor: (i32, i32)) -> String {
+        let physical_names: Vec<String> = (0..pattern)
+            .map(|no| format!("\"{user_desc} {no}\""))
+            .collect();
+        format!(
+            r#"{{
+                "physical_names": [{}],
+                "user_desc": "{user_desc}",
+                "is_using_full_png_center_as_reference": true,
+                "connector_start_line_position_relative_to_center_full_png": [{}, {}]
+            }}"#,
+            physical_names.join(", "),
+            anchor.0,
+            anchor.1
+        )
+    }
+
+    /// The absolute bounding box and connector of each group cf `place_boxes`
+    fn get_placed(json_params: &TemplateJsonParamaters) -> Vec<((i32, i32, i32, i32), Segment)> {
+        let full_png_center = layout::get_reference_point(&json_params.sheet, true).unwrap();
+        let to_absolute = |relative: (i32, i32)| {
+            (
+                full_png_center.0 + relative.0,
+                full_png_center.1 + relative.1,
+            )
+        };
+
+        json_params
+            .buttons_params
+            .iter()
+            .map(|button_param| {
+                let extent = layout::get_boxes_extent(button_param, &json_params.style).unwrap();
+                let start = to_absolute(
+                    button_param
+                        .desired_box_position_relative_to_center_full_png
+                        .unwrap(),
+                );
+                (
+                    (
+                        start.0 + extent.0,
+                        start.1 + extent.1,
+                        start.0 + extent.2,
+                        start.1 + extent.3,
+                    ),
+                    (
+                        to_absolute(
+                            button_param.connector_start_line_position_relative_to_center_full_png,
+                        ),
+                        to_absolute(
+                            button_param
+                                .connector_end_line_position_relative_to_center_full_png
+                                .unwrap(),
+                        ),
+                    ),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_place_boxes_columns() {
+        // full png center: (2100, 900) on a 4000 x 2000 sheet
+        let json_params = new_json_params(
+            &[
+                new_button_param("left top", 1, (-300, -500)),
+                new_button_param("left bottom", 5, (-200, 300)),
+                new_button_param("left middle", 2, (-400, -100)),
+                new_button_param("right", 8, (200, 0)),
+            ]
+            .join(","),
+        );
+
+        let placed = get_placed(&place_boxes(&json_params).unwrap());
+
+        // the columns: the widest group against the margin, ALL aligned on the inner side
+        assert_eq!(placed[1].0 .0, 40);
+        assert_eq!(placed[0].0 .2, 40 + 3 * 500 + 2 * 10);
+        assert_eq!(placed[1].0 .2, 40 + 3 * 500 + 2 * 10);
+        assert_eq!(placed[2].0 .2, 40 + 3 * 500 + 2 * 10);
+        assert_eq!(placed[3].0 .2, 4000 - 40);
+        // in the order of the anchors, NOT the one of the template
+        assert!(placed[0].0 .3 + 30 <= placed[2].0 .1);
+        assert!(placed[2].0 .3 + 30 <= placed[1].0 .1);
+        // as close as possible to the anchor: centered on it
+        assert_eq!(i32::midpoint(placed[3].0 .1, placed[3].0 .3), 900);
+        // the connectors end on the inner side, and do NOT cross
+        assert_eq!(placed[0].1 .1 .0, placed[0].0 .2);
+        assert_eq!(placed[3].1 .1 .0, placed[3].0 .0);
+        for (first_no, first) in placed.iter().enumerate() {
+            for second in &placed[first_no + 1..] {
+                assert!(!is_crossing(first.1, second.1));
+            }
+        }
+    }
+
+    #[test]
+    fn test_place_boxes_too_tall_and_already_placed() {
+        // 14 boxes of 110 + 30 do NOT fit in 2000 - 2 * 40: some go to the right column
+        let mut buttons_params: Vec<String> = (0..14)
+            .map(|no| new_button_param(&format!("button {no}"), 1, (-50 - no, -800 + 120 * no)))
+            .collect();
+        buttons_params.push(
+            r#"{
+                "physical_names": ["(A2)"],
+                "user_desc": "placed by hand",
+                "is_using_full_png_center_as_reference": true,
+                "desired_box_position_relative_to_center_full_png": [100, -200],
+                "connector_start_line_position_relative_to_center_full_png": [10, 20],
+                "connector_end_line_position_relative_to_center_full_png": [100, -150]
+            }"#
+            .to_string(),
+        );
+        let json_params = new_json_params(&buttons_params.join(","));
+
+        let placed_params = place_boxes(&json_params).unwrap();
+        let placed = get_placed(&placed_params);
+
+        let left_count = placed[..14]
+            .iter()
+            .filter(|(extent, _)| extent.0 == 40)
+            .count();
+        assert_eq!(left_count, 13);
+        // the one closest to the center ie the largest anchor x
+        assert_eq!(placed[0].0 .2, 4000 - 40);
+        assert!(placed[..14]
+            .iter()
+            .all(|(extent, _)| extent.1 >= 40 && extent.3 <= 2000 - 40));
+
+        let by_hand = &placed_params.buttons_params[14];
+        assert_eq!(
+            by_hand.desired_box_position_relative_to_center_full_png,
+            Some((100, -200))
+        );
+        assert_eq!(
+            by_hand.connector_end_line_position_relative_to_center_full_png,
+            Some((100, -150))
+        );
+    }
+}
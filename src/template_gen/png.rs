@@ -0,0 +1,286 @@
+//! `OutputFormat::Png`: the original raster output, drawn with imageproc
+//!
+
+use std::path::Path;
+
+use image::imageops;
+use rusttype::{Font, Scale};
+
+use super::connector::{self, MarkerShape};
+use super::scene::{SceneBox, SceneConnector};
+use super::text;
+use super::SheetCanvas;
+use crate::Error;
+
+pub(super) struct PngCanvas {
+    image: image::RgbaImage,
+    font: Font<'static>,
+}
+
+impl PngCanvas {
+    pub(super) fn new(size: (u32, u32), font_data: &'static [u8]) -> Result<Self, Error> {
+        Ok(Self {
+            image: image::RgbaImage::new(size.0, size.1),
+            font: Font::try_from_bytes(font_data)
+                .ok_or_else(|| Error::Other("Failed to load font".to_string()))?,
+        })
+    }
+}
+
+impl SheetCanvas for PngCanvas {
+    fn draw_image(
+        &mut self,
+        image_bytes: &[u8],
+        top_left_position: (i32, i32),
+        size: (u32, u32),
+    ) -> Result<(), Error> {
+        let image = image::load_from_memory(image_bytes)
+            .map_err(|err| Error::Other(format!("failed to decode image : {err}")))?;
+        let image = imageops::resize(&image, size.0, size.1, imageops::FilterType::Nearest);
+        image::imageops::overlay(
+            &mut self.image,
+            &image,
+            top_left_position.0.into(),
+            top_left_position.1.into(),
+        );
+
+        Ok(())
+    }
+
+    fn draw_box(&mut self, scene_box: &SceneBox) {
+        draw_box(&mut self.image, scene_box, &self.font);
+    }
+
+    fn draw_connector(&mut self, connector: &SceneConnector) {
+        draw_connector(&mut self.image, connector);
+    }
+
+    fn save(&self, path: &Path) -> Result<(), Error> {
+        self.image
+            .save(path)
+            .map_err(|_err| Error::Other(format!("could not write image to {}", path.display())))
+    }
+}
+
+/// Draw an anti-aliased connector: the polyline, with round joins/caps, then its marker
+/// The width is the SAME whatever the angle: the coverage of a pixel is from its distance to the segments
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss
+)]
+fn draw_connector(image: &mut image::RgbaImage, connector: &SceneConnector) {
+    let line_points = connector::get_line_points(connector);
+    let marker = connector::get_marker_shape(connector);
+    let half_thickness = f64::from(connector.thickness.max(1)) / 2.0;
+
+    // the pixels which may be covered, +1 for the anti-aliasing
+    let mut extent_points: Vec<((f64, f64), f64)> = line_points
+        .iter()
+        .map(|point| (*point, half_thickness))
+        .collect();
+    match &marker {
+        Some(MarkerShape::Dot { center, radius }) => extent_points.push((*center, *radius)),
+        Some(MarkerShape::Arrow(corners)) => {
+            extent_points.extend(corners.iter().map(|corner| (*corner, 0.0)));
+        }
+        None => {}
+    }
+    let Some((min, max)) = extent_points.iter().fold(None, |extent, (point, margin)| {
+        let (min, max) = extent.unwrap_or((*point, *point));
+        Some((
+            ((point.0 - margin).min(min.0), (point.1 - margin).min(min.1)),
+            ((point.0 + margin).max(max.0), (point.1 + margin).max(max.1)),
+        ))
+    }) else {
+        return;
+    };
+    let x_range = (min.0 - 1.0).floor().max(0.0) as u32
+        ..((max.0 + 1.0).ceil().max(0.0) as u32).min(image.width());
+    let y_range = (min.1 - 1.0).floor().max(0.0) as u32
+        ..((max.1 + 1.0).ceil().max(0.0) as u32).min(image.height());
+
+    for y in y_range {
+        for x in x_range.clone() {
+            let center = (f64::from(x) + 0.5, f64::from(y) + 0.5);
+            let line_coverage = if line_points.len() == 1 {
+                half_thickness + 0.5 - get_distance(center, line_points[0])
+            } else {
+                line_points
+                    .windows(2)
+                    .map(|pair| {
+                        half_thickness + 0.5 - get_distance_to_segment(center, pair[0], pair[1])
+                    })
+                    .fold(f64::NEG_INFINITY, f64::max)
+            };
+            let marker_coverage = match &marker {
+                Some(MarkerShape::Dot {
+                    center: dot,
+                    radius,
+                }) => radius + 0.5 - get_distance(center, *dot),
+                Some(MarkerShape::Arrow(corners)) => 0.5 + get_distance_inside(center, corners),
+                None => f64::NEG_INFINITY,
+            };
+            let coverage = line_coverage.max(marker_coverage).clamp(0.0, 1.0);
+            if coverage > 0.0 {
+                blend_pixel(image.get_pixel_mut(x, y), connector.color, coverage);
+            }
+        }
+    }
+}
+
+fn get_distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
+
+fn get_distance_to_segment(point: (f64, f64), start: (f64, f64), end: (f64, f64)) -> f64 {
+    let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+    let length_squared = dx * dx + dy * dy;
+    if length_squared == 0.0 {
+        return get_distance(point, start);
+    }
+    let ratio =
+        (((point.0 - start.0) * dx + (point.1 - start.1) * dy) / length_squared).clamp(0.0, 1.0);
+    get_distance(point, (start.0 + ratio * dx, start.1 + ratio * dy))
+}
+
+/// How far `point` is inside the triangle: > 0 inside, < 0 outside
+fn get_distance_inside(point: (f64, f64), corners: &[(f64, f64); 3]) -> f64 {
+    // the edges are walked the same way whatever the order of the corners
+    let orientation = ((corners[1].0 - corners[0].0) * (corners[2].1 - corners[0].1)
+        - (corners[1].1 - corners[0].1) * (corners[2].0 - corners[0].0))
+        .signum();
+    (0..3)
+        .map(|index| {
+            let (start, end) = (corners[index], corners[(index + 1) % 3]);
+            let length = get_distance(start, end);
+            if length == 0.0 {
+                return f64::NEG_INFINITY;
+            }
+            orientation
+                * ((end.0 - start.0) * (point.1 - start.1)
+                    - (end.1 - start.1) * (point.0 - start.0))
+                / length
+        })
+        .fold(f64::INFINITY, f64::min)
+}
+
+/// `color` over `pixel`, with its alpha scaled by `coverage`
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn blend_pixel(pixel: &mut image::Rgba<u8>, color: image::Rgba<u8>, coverage: f64) {
+    let alpha = f64::from(color[3]) / 255.0 * coverage;
+    let pixel_alpha = f64::from(pixel[3]) / 255.0;
+    let blended_alpha = alpha + pixel_alpha * (1.0 - alpha);
+    if blended_alpha == 0.0 {
+        return;
+    }
+    for channel in 0..3 {
+        pixel[channel] = ((f64::from(color[channel]) * alpha
+            + f64::from(pixel[channel]) * pixel_alpha * (1.0 - alpha))
+            / blended_alpha)
+            .round() as u8;
+    }
+    pixel[3] = (blended_alpha * 255.0).round() as u8;
+}
+
+/// `https://chat.openai.com`
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_possible_wrap
+)]
+fn draw_box(image: &mut image::RgbaImage, parameters: &SceneBox, font: &Font<'static>) {
+    imageproc::drawing::draw_filled_rect_mut(
+        image,
+        imageproc::rect::Rect::at(parameters.position.0, parameters.position.1)
+            .of_size(parameters.size.0, parameters.size.1),
+        parameters.color,
+    );
+
+    // Draw border with customizable thickness; centered on the edges like a svg stroke
+    let half_thickness = parameters.stroke_thickness / 2;
+    let (left, top) = parameters.position;
+    let right = left + parameters.size.0 as i32;
+    let bottom = top + parameters.size.1 as i32;
+    for offset in -half_thickness..=half_thickness {
+        for y in [top + offset, bottom + offset] {
+            imageproc::drawing::draw_line_segment_mut(
+                image,
+                ((left - half_thickness) as f32, y as f32),
+                ((right + half_thickness) as f32, y as f32),
+                parameters.border_color,
+            );
+        }
+        for x in [left + offset, right + offset] {
+            imageproc::drawing::draw_line_segment_mut(
+                image,
+                (x as f32, (top - half_thickness) as f32),
+                (x as f32, (bottom + half_thickness) as f32),
+                parameters.border_color,
+            );
+        }
+    }
+
+    if let Some(text_params) = &parameters.text {
+        let scale = Scale::uniform(text_params.text_size as f32);
+        for (line_no, position, line) in
+            text::place_text_lines(parameters.position, parameters.size, text_params, font)
+        {
+            imageproc::drawing::draw_text_mut(
+                image,
+                text_params.get_line_color(line_no),
+                position.0,
+                position.1,
+                scale,
+                font,
+                line,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::params::ConnectorMarker;
+    use super::*;
+
+    /// The covered width along `pixels` eg a row
+    fn get_coverage(image: &image::RgbaImage, pixels: impl Iterator<Item = (u32, u32)>) -> f64 {
+        pixels
+            .map(|(x, y)| f64::from(image.get_pixel(x, y)[3]) / 255.0)
+            .sum()
+    }
+
+    #[test]
+    fn test_draw_connector_width() {
+        let draw = |end: (i32, i32)| {
+            let mut image = image::RgbaImage::new(200, 200);
+            draw_connector(
+                &mut image,
+                &SceneConnector {
+                    points: vec![(100, 100), end],
+                    thickness: 4,
+                    color: image::Rgba([0, 255, 0, 255]),
+                    marker: ConnectorMarker::None,
+                    label: None,
+                },
+            );
+            image
+        };
+
+        // horizontal/vertical: EXACTLY the thickness, and opaque
+        let image = draw((190, 100));
+        assert!((get_coverage(&image, (0..200).map(|y| (150, y))) - 4.0).abs() < 0.01);
+        assert_eq!(image.get_pixel(150, 100)[3], 255);
+        let image = draw((100, 190));
+        assert!((get_coverage(&image, (0..200).map(|x| (x, 150))) - 4.0).abs() < 0.01);
+
+        // 45°: the row crosses it on thickness * sqrt(2), NOT less
+        let image = draw((190, 190));
+        let coverage = get_coverage(&image, (0..200).map(|x| (x, 150)));
+        assert!(
+            (coverage - 4.0 * std::f64::consts::SQRT_2).abs() < 0.5,
+            "{coverage}"
+        );
+    }
+}
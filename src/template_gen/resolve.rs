@@ -0,0 +1,385 @@
+//! First step of a sheet: for each button group of the template, find the game actions of ALL its layers
+//! ie physical name -> virtual buttons -> game actions
+//!
+//! NOTE: no position here cf `layout`
+//!
+
+use super::scene::HotspotBinding;
+use super::theme::LineCategory;
+use super::TemplateJsonParamaters;
+use crate::sc::parse_keybind_xml;
+use crate::vkb::vkb_button::JoystickButtonsMapping;
+use crate::Error;
+
+/// The bindings of ONE `TemplateJsonButtonOrStickParameters`
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct ResolvedButtonGroup {
+    /// One per `physical_names`, in the same order; eg "`[SHIFT1] v_attack_all\nNO BINDING\n`"
+    /// This is the text of the boxes
+    pub(super) keybind_lines: Vec<String>,
+    /// Same shape as `keybind_lines`: one per '\n'-separated line of each text
+    /// NOTE: can be shorter eg the empty line after the last '\n'
+    pub(super) keybind_lines_categories: Vec<Vec<LineCategory>>,
+    /// Same shape as `keybind_lines_categories`: the layer of each line cf `LayersLayout`
+    pub(super) keybind_lines_layers: Vec<Vec<Layer>>,
+    /// Same as `keybind_lines` but structured cf `Hotspot`
+    pub(super) bindings: Vec<HotspotBinding>,
+}
+
+impl ResolvedButtonGroup {
+    /// The layers of the text of `physical_names[name_no]`, in the `Layer` order; eg for the columns of its box
+    pub(super) fn get_layers(&self, name_no: usize) -> Vec<Layer> {
+        let mut layers = self.keybind_lines_layers[name_no].clone();
+        layers.sort();
+        layers.dedup();
+
+        layers
+    }
+
+    /// ONLY the lines of `layer` in the text of `physical_names[name_no]`, without their prefix eg "[SHIFT1] "
+    /// Returns the text and its categories cf `keybind_lines_categories`
+    pub(super) fn get_layer_lines(
+        &self,
+        name_no: usize,
+        layer: Layer,
+    ) -> (String, Vec<LineCategory>) {
+        let mut text = String::new();
+        let mut categories = vec![];
+        for ((line, line_layer), category) in self.keybind_lines[name_no]
+            .split('\n')
+            .zip(&self.keybind_lines_layers[name_no])
+            .zip(&self.keybind_lines_categories[name_no])
+        {
+            if *line_layer == layer {
+                text.push_str(line.strip_prefix(layer.get_prefix()).unwrap_or(line));
+                text.push('\n');
+                categories.push(*category);
+            }
+        }
+
+        (text, categories)
+    }
+
+    /// The same group with ONLY the lines of `layer` eg for its own sheet; the hotspots are kept as-is
+    pub(super) fn filter_layer(&self, layer: Layer) -> Self {
+        let (keybind_lines, keybind_lines_categories): (Vec<String>, Vec<Vec<LineCategory>>) = (0
+            ..self.keybind_lines.len())
+            .map(|name_no| self.get_layer_lines(name_no, layer))
+            .unzip();
+
+        Self {
+            keybind_lines_layers: keybind_lines_categories
+                .iter()
+                .map(|categories| vec![layer; categories.len()])
+                .collect(),
+            keybind_lines,
+            keybind_lines_categories,
+            bindings: self.bindings.clone(),
+        }
+    }
+}
+
+/// The layers of a button: SHIFT for a momentary one, Tempo for a Tempo one
+/// NOTE: the short press of a Tempo, a modifier: `Base`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) enum Layer {
+    Base,
+    Shift1,
+    Shift2,
+    Long,
+    Double,
+}
+
+impl Layer {
+    /// In the order of the sheets/columns cf `LayersLayout`
+    pub(super) const ALL: [Layer; 5] = [
+        Layer::Base,
+        Layer::Shift1,
+        Layer::Shift2,
+        Layer::Long,
+        Layer::Double,
+    ];
+
+    fn from_kind(kind: &crate::button::VirtualButtonKind) -> Self {
+        match kind {
+            crate::button::VirtualButtonKind::Momentary(Some(
+                crate::button::VirtualShiftKind::Shift1,
+            )) => Layer::Shift1,
+            crate::button::VirtualButtonKind::Momentary(Some(
+                crate::button::VirtualShiftKind::Shift2,
+            )) => Layer::Shift2,
+            crate::button::VirtualButtonKind::Tempo(crate::button::VirtualTempoKind::Long) => {
+                Layer::Long
+            }
+            crate::button::VirtualButtonKind::Tempo(crate::button::VirtualTempoKind::Double) => {
+                Layer::Double
+            }
+            crate::button::VirtualButtonKind::Momentary(None)
+            | crate::button::VirtualButtonKind::Tempo(crate::button::VirtualTempoKind::Short) => {
+                Layer::Base
+            }
+        }
+    }
+
+    /// eg the title of its sheet, or the header of its column
+    pub(super) fn get_name(self) -> &'static str {
+        match self {
+            Layer::Base => "BASE",
+            Layer::Shift1 => "SHIFT1",
+            Layer::Shift2 => "SHIFT2",
+            Layer::Long => "LONG",
+            Layer::Double => "DOUBLE",
+        }
+    }
+
+    /// The prefix of its lines in `keybind_lines` cf `resolve_button_group`
+    fn get_prefix(self) -> &'static str {
+        match self {
+            Layer::Base => "[SHORT] ",
+            Layer::Shift1 => "[SHIFT1] ",
+            Layer::Shift2 => "[SHIFT2] ",
+            Layer::Long => "[LONG] ",
+            Layer::Double => "[DOUBLE] ",
+        }
+    }
+
+    /// The color of its header cf `Theme`
+    pub(super) fn get_category(self) -> LineCategory {
+        match self {
+            Layer::Base => LineCategory::Base,
+            Layer::Shift1 | Layer::Shift2 => LineCategory::Shift,
+            Layer::Long | Layer::Double => LineCategory::Tempo,
+        }
+    }
+}
+
+/// One `ResolvedButtonGroup` per `json_params.buttons_params`, in the same order
+///
+/// # Errors
+/// - a physical name of the template is NOT found on the stick
+pub(super) fn resolve_bindings(
+    game_buttons_mapping: &parse_keybind_xml::GameButtonsMapping,
+    joysticks_mappings: &JoystickButtonsMapping,
+    json_params: &TemplateJsonParamaters,
+    game_device_id: u8,
+) -> Result<Vec<ResolvedButtonGroup>, Error> {
+    json_params
+        .buttons_params
+        .iter()
+        .map(|button_param| {
+            resolve_button_group(
+                game_buttons_mapping,
+                joysticks_mappings,
+                &button_param.physical_names,
+                game_device_id,
+            )
+        })
+        .collect()
+}
+
+#[allow(clippy::too_many_lines)]
+fn resolve_button_group(
+    game_buttons_mapping: &parse_keybind_xml::GameButtonsMapping,
+    joysticks_mappings: &JoystickButtonsMapping,
+    physical_names: &[String],
+    game_device_id: u8,
+) -> Result<ResolvedButtonGroup, Error> {
+    let mut keybind_lines: Vec<String> = vec![];
+    let mut keybind_lines_categories: Vec<Vec<LineCategory>> = vec![];
+    let mut keybind_lines_layers: Vec<Vec<Layer>> = vec![];
+    let mut bindings: Vec<HotspotBinding> = vec![];
+
+    for physical_name in physical_names {
+        // First: get the corresponding VIRTUAL button ID from "physical_name" in json
+        // NOTE: ONE device; several on the same sheet are resolved one by one cf `composite`
+        let virtual_buttons =
+            joysticks_mappings.get_virtual_button_ids_from_info_or_user_desc(physical_name)?;
+
+        // A modifier which ALSO emits a joystick button eg "SHIFT1 / tap: v_target_cycle"
+        // cf `get_virtual_button_ids_from_info_or_user_desc`
+        let is_also_modifier = virtual_buttons
+            .iter()
+            .any(|button| matches!(button, crate::button::VirtualButtonOrSpecial::Special(_)));
+        let is_also_virtual = virtual_buttons
+            .iter()
+            .any(|button| matches!(button, crate::button::VirtualButtonOrSpecial::Virtual(_)));
+
+        // Next: get the game binding from this virtual_button_id
+        let mut actions_names: String = String::new();
+        let mut lines_categories: Vec<LineCategory> = vec![];
+        let mut lines_layers: Vec<Layer> = vec![];
+        for virtual_button in virtual_buttons {
+            let layer = match &virtual_button {
+                crate::button::VirtualButtonOrSpecial::Virtual(virtual_button) => {
+                    Layer::from_kind(&virtual_button.kind)
+                }
+                crate::button::VirtualButtonOrSpecial::Special(_) => Layer::Base,
+            };
+            match virtual_button {
+                crate::button::VirtualButtonOrSpecial::Virtual(virtual_button) => {
+                    let modifier: String = match &virtual_button.kind {
+                        crate::button::VirtualButtonKind::Momentary(shift) => match shift {
+                            Some(shift_kind) => match shift_kind {
+                                crate::button::VirtualShiftKind::Shift1 => "[SHIFT1] ".to_string(),
+                                crate::button::VirtualShiftKind::Shift2 => "[SHIFT2] ".to_string(),
+                            },
+                            None if is_also_modifier => "tap: ".to_string(),
+                            None => String::new(),
+                        },
+                        crate::button::VirtualButtonKind::Tempo(tempo) => match tempo {
+                            crate::button::VirtualTempoKind::Short => "[SHORT] ".to_string(),
+                            crate::button::VirtualTempoKind::Long => "[LONG] ".to_string(),
+                            crate::button::VirtualTempoKind::Double => "[DOUBLE] ".to_string(),
+                        },
+                    };
+
+                    let game_actions = game_buttons_mapping.get_action_from_virtual_button_id(
+                        *virtual_button.get_id(),
+                        game_device_id,
+                    );
+                    bindings.push(HotspotBinding {
+                        physical_name: physical_name.clone(),
+                        layer: match modifier.trim() {
+                            "" => "press".to_string(),
+                            layer => layer.to_string(),
+                        },
+                        game_actions: game_actions.cloned().unwrap_or_default(),
+                    });
+
+                    let category = match (&virtual_button.kind, game_actions) {
+                        (_, None) => LineCategory::NoBinding,
+                        (crate::button::VirtualButtonKind::Momentary(Some(_)), _) => {
+                            LineCategory::Shift
+                        }
+                        (crate::button::VirtualButtonKind::Momentary(None), _) => {
+                            LineCategory::Base
+                        }
+                        (crate::button::VirtualButtonKind::Tempo(_), _) => LineCategory::Tempo,
+                    };
+
+                    let mut action_name_with_modifier = modifier;
+
+                    if let Some(act_names) = game_actions {
+                        action_name_with_modifier.push_str(&act_names.join("\n"));
+                        lines_categories
+                            .extend(std::iter::repeat_n(category, act_names.len().max(1)));
+                    } else {
+                        action_name_with_modifier.push_str("NO BINDING");
+                        lines_categories.push(category);
+                    }
+
+                    actions_names.push_str(&action_name_with_modifier);
+
+                    actions_names.push('\n');
+                }
+                crate::button::VirtualButtonOrSpecial::Special(special_kind) => {
+                    let shift_name = match special_kind {
+                        crate::button::SpecialButtonKind::Shift1 => "SHIFT1",
+                        crate::button::SpecialButtonKind::Shift2 => "SHIFT2",
+                    };
+                    actions_names.push_str(shift_name);
+                    // NOT a game action, but listed so that eg searching "SHIFT1" finds the button
+                    bindings.push(HotspotBinding {
+                        physical_name: physical_name.clone(),
+                        layer: "modifier".to_string(),
+                        game_actions: vec![shift_name.to_string()],
+                    });
+                    if is_also_virtual {
+                        // same line as the following "tap: ..." ie that one's category
+                        actions_names.push_str(" / ");
+                    } else {
+                        lines_categories.push(LineCategory::Modifier);
+                    }
+                }
+            }
+            // ie one per line pushed above
+            lines_layers.resize(lines_categories.len(), layer);
+        }
+
+        keybind_lines.push(actions_names);
+        keybind_lines_categories.push(lines_categories);
+        keybind_lines_layers.push(lines_layers);
+    }
+
+    Ok(ResolvedButtonGroup {
+        keybind_lines,
+        keybind_lines_categories,
+        keybind_lines_layers,
+        bindings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_button_group_shift_and_tempo() {
+        let joysticks_mappings = crate::vkb::parse_and_check_vkb_both_sticks(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/vkb_report_L.fp3").as_ref(),
+            &None,
+            None,
+        )
+        .unwrap();
+        let game_buttons_mapping = crate::sc::parse_keybind_xml::parse_keybind(
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/data/layout_activation_modes.xml"
+            )
+            .into(),
+            None,
+        )
+        .unwrap();
+
+        let resolved = resolve_button_group(
+            &game_buttons_mapping,
+            &joysticks_mappings,
+            &["(A2)".to_string(), "(F1)".to_string()],
+            2,
+        )
+        .unwrap();
+
+        assert_eq!(resolved.keybind_lines.len(), 2);
+        // (A2): base 108 AND SHIFT1 100 ie the 2nd js bind of the same action
+        assert_eq!(
+            resolved.keybind_lines[0],
+            "v_attack_group2\n[SHIFT1] v_attack_group2\n[SHIFT2] NO BINDING\n"
+        );
+        assert_eq!(
+            resolved.keybind_lines_categories[0],
+            vec![
+                LineCategory::Base,
+                LineCategory::Shift,
+                LineCategory::NoBinding
+            ]
+        );
+        assert!(resolved.bindings.contains(&HotspotBinding {
+            physical_name: "(A2)".to_string(),
+            layer: "press".to_string(),
+            game_actions: vec!["v_attack_group2".to_string()],
+        }));
+        assert!(resolved.bindings.contains(&HotspotBinding {
+            physical_name: "(A2)".to_string(),
+            layer: "[SHIFT1]".to_string(),
+            game_actions: vec!["v_attack_group2".to_string()],
+        }));
+        // (F1): Tempo, long press is 96
+        assert!(resolved.keybind_lines[1].contains("[LONG] v_target_cycle_all_back"));
+        assert!(resolved.keybind_lines_categories[1].contains(&LineCategory::Tempo));
+
+        // the layers, cf `LayersLayout`
+        assert_eq!(
+            resolved.get_layers(0),
+            vec![Layer::Base, Layer::Shift1, Layer::Shift2]
+        );
+        assert!(resolved.get_layers(1).contains(&Layer::Long));
+        assert_eq!(
+            resolved.get_layer_lines(0, Layer::Shift1),
+            ("v_attack_group2\n".to_string(), vec![LineCategory::Shift])
+        );
+        let long_only = resolved.filter_layer(Layer::Long);
+        assert_eq!(long_only.keybind_lines[0], "");
+        assert!(long_only.keybind_lines[1].starts_with("v_target_cycle_all_back"));
+        assert_eq!(long_only.get_layers(1), vec![Layer::Long]);
+    }
+}
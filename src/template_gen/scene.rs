@@ -0,0 +1,155 @@
+//! The backend-neutral description of ONE sheet: what to draw, and where
+//!
+//! - built by `layout` from the resolved bindings cf `resolve`
+//! - then drawn by ANY `SheetCanvas` (png, svg, html; pdf via svg) cf `Scene::render`
+//!
+//! NOTE: no font and no pixel here; the text is measured by the renderers
+//!
+
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use super::params::ConnectorMarker;
+use super::SheetCanvas;
+use crate::Error;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct SceneText {
+    /// One line per '\n'; centered in the box cf the `draw_box` of each renderer
+    pub(super) text: String,
+    pub(super) text_size: u32,
+    pub(super) text_color: image::Rgba<u8>,
+    /// One per line eg a "[SHIFT1] ..." line in the SHIFT color cf `Theme`; `text_color` if missing
+    pub(super) line_colors: Vec<image::Rgba<u8>>,
+}
+
+impl SceneText {
+    pub(super) fn get_line_color(&self, line_no: usize) -> image::Rgba<u8> {
+        self.line_colors
+            .get(line_no)
+            .copied()
+            .unwrap_or(self.text_color)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct SceneBox {
+    pub(super) position: (i32, i32),
+    pub(super) size: (u32, u32),
+    pub(super) color: image::Rgba<u8>,
+    pub(super) border_color: image::Rgba<u8>,
+    pub(super) stroke_thickness: i32,
+    pub(super) text: Option<SceneText>,
+    /// `None` for the NON button boxes eg the background, the legend
+    pub(super) label: Option<SceneLabel>,
+}
+
+/// From a button on the photo to its box(es)
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct SceneConnector {
+    /// The button ie the anchor, [the waypoints and the elbows], then the box; at least 2
+    pub(super) points: Vec<(i32, i32)>,
+    pub(super) thickness: i32,
+    pub(super) color: image::Rgba<u8>,
+    /// At the button end ie `points[0]`
+    pub(super) marker: ConnectorMarker,
+    /// cf `SceneBox::label`
+    pub(super) label: Option<SceneLabel>,
+}
+
+impl SceneConnector {
+    /// The legs ie (from, to) for each pair of consecutive points
+    pub(super) fn get_segments(&self) -> impl Iterator<Item = ((i32, i32), (i32, i32))> + '_ {
+        self.points.windows(2).map(|pair| (pair[0], pair[1]))
+    }
+}
+
+/// Which button group (and physical button) a box/connector is for; for the warnings and `validate`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct SceneLabel {
+    /// The index in `TemplateJsonParamaters::buttons_params`
+    pub(super) group_no: usize,
+    /// cf `TemplateJsonButtonOrStickParameters::user_desc`
+    pub(super) user_desc: String,
+    /// `None` for the whole group eg its connector, or the empty center of the 8 boxes
+    pub(super) physical_name: Option<String>,
+}
+
+impl std::fmt::Display for SceneLabel {
+    /// eg `#3 "Red thumb button" (A2)`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{} \"{}\"", self.group_no, self.user_desc)?;
+        if let Some(physical_name) = &self.physical_name {
+            write!(f, " {physical_name}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// One game binding of a physical button, on one layer; cf `Hotspot`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(super) struct HotspotBinding {
+    /// cf `TemplateJsonButtonOrStickParameters::physical_names` eg "(A2)"
+    pub(super) physical_name: String,
+    /// eg "press", "[SHIFT1]", "[LONG]", "tap:", "[KEY LCtrl+F1]"
+    pub(super) layer: String,
+    /// Empty if NO BINDING
+    pub(super) game_actions: Vec<String>,
+}
+
+/// A button group of the template, with ALL its layers; cf `SheetCanvas::add_hotspot`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(super) struct Hotspot {
+    /// cf `TemplateJsonButtonOrStickParameters::user_desc`
+    pub(super) user_desc: String,
+    /// ie the connector start, on the photo
+    pub(super) anchor_position: (i32, i32),
+    pub(super) bindings: Vec<HotspotBinding>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum SceneElement {
+    /// A png/jpg file, resized to `size`
+    Image {
+        path: PathBuf,
+        top_left_position: (i32, i32),
+        size: (u32, u32),
+    },
+    Box(SceneBox),
+    Connector(SceneConnector),
+    Hotspot(Hotspot),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Scene {
+    pub(super) size: (u32, u32),
+    /// In drawing order ie the later ones are on top
+    pub(super) elements: Vec<SceneElement>,
+}
+
+impl Scene {
+    /// Draw ALL the elements, in order
+    pub(super) fn render(&self, canvas: &mut dyn SheetCanvas) -> Result<(), Error> {
+        for element in &self.elements {
+            match element {
+                SceneElement::Image {
+                    path,
+                    top_left_position,
+                    size,
+                } => {
+                    let image_bytes = std::fs::read(path).map_err(|_err| {
+                        Error::Other(format!("failed to open image {}", path.display()))
+                    })?;
+                    canvas.draw_image(&image_bytes, *top_left_position, *size)?;
+                }
+                SceneElement::Box(scene_box) => canvas.draw_box(scene_box),
+                SceneElement::Connector(connector) => canvas.draw_connector(connector),
+                SceneElement::Hotspot(hotspot) => canvas.add_hotspot(hotspot.clone()),
+            }
+        }
+
+        Ok(())
+    }
+}
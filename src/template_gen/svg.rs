@@ -0,0 +1,304 @@
+//! `OutputFormat::Svg`: the same sheet as the png, but with real vector elements
+//! - boxes -> `<rect>`, connectors -> `<polyline>` + `<circle>`/`<polygon>` for the marker, text -> `<text>` (one per line)
+//! - the stick photos are embedded as base64 `<image>`; the file is self-contained
+//! - the font is embedded with a `@font-face`; an editor without it falls back to "sans-serif"
+//!
+//! The positions are computed EXACTLY like the png cf `draw_box` so both outputs match.
+//!
+
+use std::fmt::Write;
+use std::path::Path;
+
+use base64::Engine;
+use rusttype::{Font, Scale};
+
+use super::connector::{self, MarkerShape};
+use super::scene::{SceneBox, SceneConnector};
+use super::text;
+use super::SheetCanvas;
+use crate::Error;
+
+/// The family name in "BF_Modernista-Regular.ttf"
+const FONT_FAMILY: &str = "BF_Modernista";
+
+pub(super) struct SvgCanvas {
+    size: (u32, u32),
+    font_data: &'static [u8],
+    /// To measure the text, like the png cf `draw_box`
+    font: Font<'static>,
+    /// ALL the elements, in drawing order
+    body: String,
+}
+
+impl SvgCanvas {
+    pub(super) fn new(size: (u32, u32), font_data: &'static [u8]) -> Result<Self, Error> {
+        Ok(Self {
+            size,
+            font_data,
+            font: Font::try_from_bytes(font_data)
+                .ok_or_else(|| Error::Other("Failed to load font".to_string()))?,
+            body: String::new(),
+        })
+    }
+
+    /// Append an element as-is eg for the hotspots of `HtmlCanvas`
+    pub(super) fn push_element(&mut self, element: &str) {
+        self.body.push_str(element);
+        self.body.push('\n');
+    }
+
+    pub(super) fn to_svg_string(&self) -> String {
+        let base64 = base64::engine::general_purpose::STANDARD;
+        format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="{width}" height="{height}" viewBox="0 0 {width} {height}">
+<style>@font-face {{ font-family: "{FONT_FAMILY}"; src: url(data:font/ttf;base64,{font}); }}</style>
+{body}</svg>
+"#,
+            width = self.size.0,
+            height = self.size.1,
+            font = base64.encode(self.font_data),
+            body = self.body,
+        )
+    }
+}
+
+/// eg `Rgba([50, 50, 50, 220])` -> `("rgb(50,50,50)", "0.863")`
+fn to_svg_color(color: image::Rgba<u8>) -> (String, String) {
+    let [r, g, b, a] = color.0;
+    (
+        format!("rgb({r},{g},{b})"),
+        format!("{:.3}", f32::from(a) / 255.0),
+    )
+}
+
+pub(super) fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl SheetCanvas for SvgCanvas {
+    fn draw_image(
+        &mut self,
+        image_bytes: &[u8],
+        top_left_position: (i32, i32),
+        size: (u32, u32),
+    ) -> Result<(), Error> {
+        let mime_type = image::guess_format(image_bytes)
+            .map_err(|err| Error::Other(format!("failed to decode image : {err}")))?
+            .to_mime_type();
+
+        // NOTE: "writeln!" to a String can NOT fail
+        let _ = writeln!(
+            self.body,
+            r#"<image x="{}" y="{}" width="{}" height="{}" preserveAspectRatio="none" xlink:href="data:{mime_type};base64,{}"/>"#,
+            top_left_position.0,
+            top_left_position.1,
+            size.0,
+            size.1,
+            base64::engine::general_purpose::STANDARD.encode(image_bytes)
+        );
+
+        Ok(())
+    }
+
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_possible_wrap
+    )]
+    fn draw_box(&mut self, parameters: &SceneBox) {
+        let (fill, fill_opacity) = to_svg_color(parameters.color);
+        let (stroke, stroke_opacity) = to_svg_color(parameters.border_color);
+        let _ = writeln!(
+            self.body,
+            r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{fill}" fill-opacity="{fill_opacity}" stroke="{stroke}" stroke-opacity="{stroke_opacity}" stroke-width="{}"/>"#,
+            parameters.position.0,
+            parameters.position.1,
+            parameters.size.0,
+            parameters.size.1,
+            parameters.stroke_thickness,
+        );
+
+        let Some(text_params) = &parameters.text else {
+            return;
+        };
+
+        // Same as the png cf `place_text_lines`; the only difference: svg "y" is the baseline, NOT the top
+        let ascent = self
+            .font
+            .v_metrics(Scale::uniform(text_params.text_size as f32))
+            .ascent;
+
+        let (text_fill, text_fill_opacity) = to_svg_color(text_params.text_color);
+        let _ = writeln!(
+            self.body,
+            r#"<g font-family="{FONT_FAMILY}, sans-serif" font-size="{}" fill="{text_fill}" fill-opacity="{text_fill_opacity}" xml:space="preserve">"#,
+            text_params.text_size
+        );
+        for (line_no, (x, y), line) in text::place_text_lines(
+            parameters.position,
+            parameters.size,
+            text_params,
+            &self.font,
+        ) {
+            // only when NOT the one of the `<g>`
+            let line_fill = match text_params.get_line_color(line_no) {
+                line_color if line_color == text_params.text_color => String::new(),
+                line_color => {
+                    let (line_fill, line_fill_opacity) = to_svg_color(line_color);
+                    format!(r#" fill="{line_fill}" fill-opacity="{line_fill_opacity}""#)
+                }
+            };
+            let _ = writeln!(
+                self.body,
+                r#"<text x="{x}" y="{:.1}"{line_fill}>{}</text>"#,
+                y as f32 + ascent,
+                escape_xml(line)
+            );
+        }
+        let _ = writeln!(self.body, "</g>");
+    }
+
+    fn draw_connector(&mut self, connector: &SceneConnector) {
+        let (color, opacity) = to_svg_color(connector.color);
+        let points = connector::get_line_points(connector)
+            .iter()
+            .map(|point| format!("{:.1},{:.1}", point.0, point.1))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let _ = writeln!(
+            self.body,
+            r#"<polyline points="{points}" fill="none" stroke="{color}" stroke-opacity="{opacity}" stroke-width="{}" stroke-linecap="round" stroke-linejoin="round"/>"#,
+            connector.thickness
+        );
+        match connector::get_marker_shape(connector) {
+            Some(MarkerShape::Dot { center, radius }) => {
+                let _ = writeln!(
+                    self.body,
+                    r#"<circle cx="{:.1}" cy="{:.1}" r="{radius:.1}" fill="{color}" fill-opacity="{opacity}"/>"#,
+                    center.0, center.1
+                );
+            }
+            Some(MarkerShape::Arrow(corners)) => {
+                let _ = writeln!(
+                    self.body,
+                    r#"<polygon points="{}" fill="{color}" fill-opacity="{opacity}"/>"#,
+                    corners
+                        .iter()
+                        .map(|corner| format!("{:.1},{:.1}", corner.0, corner.1))
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                );
+            }
+            None => {}
+        }
+    }
+
+    fn save(&self, path: &Path) -> Result<(), Error> {
+        std::fs::write(path, self.to_svg_string())
+            .map_err(|_err| Error::Other(format!("could not write svg to {}", path.display())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use resvg::usvg::{fontdb, TreeParsing, TreePostProc};
+    use resvg::{tiny_skia, usvg};
+
+    use super::super::params::ConnectorMarker;
+    use super::super::scene::SceneText;
+    use super::*;
+
+    const FONT_DATA: &[u8] = include_bytes!("../../data/BF_Modernista-Regular.ttf");
+
+    /// Render with resvg, like an external viewer would
+    fn render(svg: &str) -> tiny_skia::Pixmap {
+        let mut fontdb = fontdb::Database::new();
+        fontdb.load_font_data(FONT_DATA.to_vec());
+        assert_eq!(fontdb.faces().next().unwrap().families[0].0, FONT_FAMILY);
+
+        let mut tree = usvg::Tree::from_data(svg.as_bytes(), &usvg::Options::default()).unwrap();
+        tree.postprocess(
+            usvg::PostProcessingSteps {
+                convert_text_into_paths: true,
+            },
+            &fontdb,
+        );
+
+        let size = tree.size.to_int_size();
+        let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height()).unwrap();
+        resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+
+        pixmap
+    }
+
+    #[test]
+    fn test_svg_canvas_render() {
+        let mut canvas = SvgCanvas::new((400, 200), FONT_DATA).unwrap();
+
+        // a tiny red png
+        let mut png_bytes = vec![];
+        image::RgbaImage::from_pixel(2, 2, image::Rgba([255, 0, 0, 255]))
+            .write_to(
+                &mut std::io::Cursor::new(&mut png_bytes),
+                image::ImageOutputFormat::Png,
+            )
+            .unwrap();
+        canvas.draw_image(&png_bytes, (0, 0), (100, 100)).unwrap();
+
+        canvas.draw_box(&SceneBox {
+            position: (200, 20),
+            size: (150, 60),
+            color: image::Rgba([50, 50, 50, 255]),
+            border_color: image::Rgba([50, 50, 50, 255]),
+            stroke_thickness: 2,
+            text: Some(SceneText {
+                text: "[SHIFT1] v_attack_all\n<NO BINDING>".to_string(),
+                text_size: 24,
+                text_color: image::Rgba([220, 220, 220, 255]),
+                line_colors: vec![
+                    image::Rgba([220, 220, 220, 255]),
+                    image::Rgba([255, 0, 0, 255]),
+                ],
+            }),
+            label: None,
+        });
+        canvas.draw_connector(&SceneConnector {
+            points: vec![(100, 50), (200, 50)],
+            thickness: 4,
+            color: image::Rgba([0, 255, 0, 255]),
+            marker: ConnectorMarker::Arrow,
+            label: None,
+        });
+
+        let svg = canvas.to_svg_string();
+        assert!(svg.contains("&lt;NO BINDING&gt;"));
+        // ONLY the line with its own color
+        assert_eq!(svg.matches(r#"fill="rgb(255,0,0)""#).count(), 1);
+
+        let pixmap = render(&svg);
+        assert_eq!((pixmap.width(), pixmap.height()), (400, 200));
+        // image, box, connector; and transparent elsewhere
+        let pixel = |x, y| pixmap.pixel(x, y).unwrap();
+        assert_eq!(
+            (
+                pixel(50, 50).red(),
+                pixel(50, 50).green(),
+                pixel(50, 50).alpha()
+            ),
+            (255, 0, 255)
+        );
+        assert_eq!(pixel(150, 50).green(), 255);
+        // the arrow is wider than the line
+        assert_eq!(pixel(112, 53).green(), 255);
+        assert_eq!(pixel(150, 53).alpha(), 0);
+        assert_eq!(pixel(205, 75).alpha(), 255);
+        assert_eq!(pixel(380, 190).alpha(), 0);
+        // the text is rendered ie the box is NOT uniform
+        assert!((200..350)
+            .flat_map(|x| (20..80).map(move |y| (x, y)))
+            .any(|(x, y)| pixel(x, y).red() > 100));
+    }
+}
@@ -0,0 +1,341 @@
+//! The text of the boxes: measure, wrap, fit, and place
+//!
+//! - `fit_text`: used by `layout`; wrap the lines on word/underscore boundaries, then shrink down to
+//!   `min_text_size`, then truncate with "..."
+//! - `place_text_lines`: used by ALL the renderers so the png and the svg match
+//!
+//! NOTE: the text is centered in the box, both horizontally and vertically, with `TEXT_MARGIN` around it
+//!
+
+use rusttype::{Font, Scale};
+
+use super::scene::SceneText;
+
+/// Inside the box, on each side
+pub(super) const TEXT_MARGIN: i32 = 8;
+
+const ELLIPSIS: &str = "...";
+
+/// The same for ALL the lines, whatever the glyphs
+#[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+pub(super) fn get_line_height(font: &Font<'static>, text_size: u32) -> i32 {
+    let v_metrics = font.v_metrics(Scale::uniform(text_size as f32));
+    (v_metrics.ascent - v_metrics.descent).ceil() as i32
+}
+
+#[allow(clippy::cast_precision_loss)]
+pub(super) fn get_line_width(font: &Font<'static>, text_size: u32, line: &str) -> i32 {
+    imageproc::drawing::text_size(Scale::uniform(text_size as f32), font, line).0
+}
+
+/// The lines to draw ie WITHOUT the empty ones at the end eg "a\nb\n" -> "a", "b"
+fn get_lines(text: &str) -> Vec<&str> {
+    match text.trim_end_matches('\n') {
+        "" => vec![],
+        text => text.split('\n').collect(),
+    }
+}
+
+/// The top left corner of each line; the line numbers are the ones of `SceneText::line_colors`
+#[allow(clippy::cast_possible_wrap)]
+pub(super) fn place_text_lines<'t>(
+    position: (i32, i32),
+    size: (u32, u32),
+    text: &'t SceneText,
+    font: &Font<'static>,
+) -> Vec<(usize, (i32, i32), &'t str)> {
+    let line_height = get_line_height(font, text.text_size);
+    let lines = get_lines(&text.text);
+    let max_line_width = lines
+        .iter()
+        .map(|line| get_line_width(font, text.text_size, line))
+        .max()
+        .unwrap_or_default();
+    let block_height = get_lines_height(font, text.text_size, lines.len());
+
+    let x = position.0 + size.0 as i32 / 2 - max_line_width / 2;
+    let y = position.1 + size.1 as i32 / 2 - block_height / 2;
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(line_no, line)| {
+            (
+                line_no,
+                (
+                    x,
+                    y + line_height * i32::try_from(line_no).unwrap_or_default(),
+                ),
+                line,
+            )
+        })
+        .collect()
+}
+
+/// cf `fit_text`
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct FittedText {
+    pub(super) text: SceneText,
+    /// Even at `min_text_size` some lines were cut/dropped
+    pub(super) is_truncated: bool,
+}
+
+/// Make `text` fit in a box of `box_size`:
+/// - the largest size in `min_text_size..=text.text_size` for which the wrapped lines fit
+/// - else: at `min_text_size`, drop the lines below the box and cut the ones too wide, with "..."
+///
+/// NOTE: if it already fits, it is returned as-is
+pub(super) fn fit_text(
+    text: &SceneText,
+    box_size: (u32, u32),
+    min_text_size: u32,
+    font: &Font<'static>,
+) -> FittedText {
+    let (inner_width, inner_height) = get_inner_size(box_size);
+    let min_text_size = min_text_size.min(text.text_size);
+
+    for text_size in (min_text_size..=text.text_size).rev() {
+        let (lines, line_colors) = wrap_lines(text, text_size, inner_width, font);
+        let is_fitting = get_lines_height(font, text_size, lines.len()) <= inner_height
+            && lines
+                .iter()
+                .all(|line| get_line_width(font, text_size, line) <= inner_width);
+        if is_fitting {
+            if text_size == text.text_size && lines.len() == get_lines(&text.text).len() {
+                return FittedText {
+                    text: text.clone(),
+                    is_truncated: false,
+                };
+            }
+            return FittedText {
+                text: SceneText {
+                    text: lines.join("\n"),
+                    text_size,
+                    line_colors,
+                    ..text.clone()
+                },
+                is_truncated: false,
+            };
+        }
+    }
+
+    // Still too big: truncate at the min size
+    let (mut lines, mut line_colors) = wrap_lines(text, min_text_size, inner_width, font);
+    let line_height = get_line_height(font, min_text_size);
+    // NOTE: a text size of 0 is rejected with the params cf `TemplateJsonParamaters::validate`; NOT a panic anyway
+    let max_lines = usize::try_from(
+        inner_height
+            .checked_div(line_height)
+            .unwrap_or_default()
+            .max(1),
+    )
+    .unwrap_or(1);
+    let is_dropping_lines = lines.len() > max_lines;
+    lines.truncate(max_lines);
+    line_colors.truncate(max_lines);
+    let lines_count = lines.len();
+    for (line_no, line) in lines.iter_mut().enumerate() {
+        let is_last_of_dropped = is_dropping_lines && line_no + 1 == lines_count;
+        if is_last_of_dropped || get_line_width(font, min_text_size, line) > inner_width {
+            *line = cut_line(line, min_text_size, inner_width, font);
+        }
+    }
+
+    FittedText {
+        text: SceneText {
+            text: lines.join("\n"),
+            text_size: min_text_size,
+            line_colors,
+            ..text.clone()
+        },
+        is_truncated: true,
+    }
+}
+
+/// The height a box needs for `text` at its size, with its width unchanged ie wrapped
+pub(super) fn get_needed_box_height(
+    text: &SceneText,
+    box_size: (u32, u32),
+    font: &Font<'static>,
+) -> u32 {
+    let (inner_width, _) = get_inner_size(box_size);
+    let (lines, _) = wrap_lines(text, text.text_size, inner_width, font);
+
+    u32::try_from(get_lines_height(font, text.text_size, lines.len()) + 2 * TEXT_MARGIN)
+        .unwrap_or(u32::MAX)
+}
+
+fn get_inner_size(box_size: (u32, u32)) -> (i32, i32) {
+    (
+        i32::try_from(box_size.0).unwrap_or(i32::MAX) - 2 * TEXT_MARGIN,
+        i32::try_from(box_size.1).unwrap_or(i32::MAX) - 2 * TEXT_MARGIN,
+    )
+}
+
+fn get_lines_height(font: &Font<'static>, text_size: u32, lines_count: usize) -> i32 {
+    get_line_height(font, text_size).saturating_mul(i32::try_from(lines_count).unwrap_or(i32::MAX))
+}
+
+/// ALL the lines of `text` wrapped at `max_width`, with their colors
+/// NOTE: a wrapped line keeps the color of the original one
+fn wrap_lines(
+    text: &SceneText,
+    text_size: u32,
+    max_width: i32,
+    font: &Font<'static>,
+) -> (Vec<String>, Vec<image::Rgba<u8>>) {
+    let mut lines = vec![];
+    let mut line_colors = vec![];
+    for (line_no, line) in get_lines(&text.text).into_iter().enumerate() {
+        for wrapped_line in wrap_line(line, text_size, max_width, font) {
+            lines.push(wrapped_line);
+            line_colors.push(text.get_line_color(line_no));
+        }
+    }
+
+    (lines, line_colors)
+}
+
+/// Greedy; break AFTER a ' ' or a '_' eg "`v_weapon_cycle_missile_fwd`" -> "`v_weapon_cycle_`" + "`missile_fwd`"
+/// NOTE: a word without any break point can still be wider than `max_width`
+fn wrap_line(line: &str, text_size: u32, max_width: i32, font: &Font<'static>) -> Vec<String> {
+    if get_line_width(font, text_size, line) <= max_width {
+        return vec![line.to_string()];
+    }
+
+    let mut wrapped_lines = vec![];
+    let mut current_line = String::new();
+    for token in line.split_inclusive([' ', '_']) {
+        let candidate = format!("{current_line}{token}");
+        if current_line.is_empty()
+            || get_line_width(font, text_size, candidate.trim_end()) <= max_width
+        {
+            current_line = candidate;
+        } else {
+            wrapped_lines.push(current_line.trim_end().to_string());
+            current_line = token.to_string();
+        }
+    }
+    if !current_line.is_empty() {
+        wrapped_lines.push(current_line.trim_end().to_string());
+    }
+
+    wrapped_lines
+}
+
+/// The longest start of `line` which fits with "..." appended
+fn cut_line(line: &str, text_size: u32, max_width: i32, font: &Font<'static>) -> String {
+    let mut cut = line.trim_end().to_string();
+    while !cut.is_empty()
+        && get_line_width(font, text_size, &format!("{cut}{ELLIPSIS}")) > max_width
+    {
+        cut.pop();
+    }
+
+    format!("{cut}{ELLIPSIS}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FONT_DATA: &[u8] = include_bytes!("../../data/BF_Modernista-Regular.ttf");
+
+    fn get_font() -> Font<'static> {
+        Font::try_from_bytes(FONT_DATA).unwrap()
+    }
+
+    fn new_text(text: &str) -> SceneText {
+        SceneText {
+            text: text.to_string(),
+            text_size: 24,
+            text_color: image::Rgba([220, 220, 220, 255]),
+            line_colors: vec![
+                image::Rgba([1, 1, 1, 255]),
+                image::Rgba([2, 2, 2, 255]),
+                image::Rgba([3, 3, 3, 255]),
+                image::Rgba([4, 4, 4, 255]),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_fit_text_unchanged() {
+        let font = get_font();
+        let text = new_text("v_attack_group2\n[SHIFT1] NO BINDING\n");
+
+        assert_eq!(
+            fit_text(&text, (500, 110), 12, &font),
+            FittedText {
+                text: text.clone(),
+                is_truncated: false
+            }
+        );
+    }
+
+    #[test]
+    fn test_fit_text_wrap_and_shrink() {
+        let font = get_font();
+        let text = new_text(
+            "v_weapon_gimbal_mode_cycle_fixed_auto\nv_weapon_bombing_toggle_desired_impact_point\n[SHIFT1] v_weapon_pip_toggle_lead_lag\n[SHIFT2] NO BINDING\n",
+        );
+
+        let fitted = fit_text(&text, (300, 200), 12, &font);
+        assert!(!fitted.is_truncated);
+        let lines: Vec<&str> = fitted.text.text.split('\n').collect();
+        assert!(lines.len() > 4);
+        // on the underscores; nothing lost
+        assert_eq!(
+            lines.concat().replace(' ', ""),
+            text.text.replace(['\n', ' '], "")
+        );
+        assert!(lines.iter().all(
+            |line| get_line_width(&font, fitted.text.text_size, line) <= 300 - 2 * TEXT_MARGIN
+        ));
+        // the wrapped lines keep their color
+        assert_eq!(fitted.text.line_colors[0], image::Rgba([1, 1, 1, 255]));
+        assert_eq!(
+            fitted.text.line_colors.last(),
+            Some(&image::Rgba([4, 4, 4, 255]))
+        );
+        assert_eq!(fitted.text.line_colors.len(), lines.len());
+    }
+
+    #[test]
+    fn test_fit_text_truncated() {
+        let font = get_font();
+        let text = new_text("a\nb\nc\nd\n");
+
+        let fitted = fit_text(&text, (500, 40), 20, &font);
+        assert!(fitted.is_truncated);
+        assert_eq!(fitted.text.text_size, 20);
+        assert_eq!(fitted.text.text, "a...");
+    }
+
+    #[test]
+    fn test_place_text_lines_centered() {
+        let font = get_font();
+        let text = new_text("a\nb\n");
+        let line_height = get_line_height(&font, 24);
+
+        let placed = place_text_lines((100, 100), (500, 110), &text, &font);
+        assert_eq!(placed.len(), 2);
+        assert_eq!(placed[0].1 .1, 100 + 55 - line_height);
+        assert_eq!(placed[1].1 .1, 100 + 55);
+        assert_eq!(placed[1].2, "b");
+
+        assert!(place_text_lines((0, 0), (500, 110), &new_text(""), &font).is_empty());
+    }
+
+    #[test]
+    fn test_text_size_zero_no_panic() {
+        let font = get_font();
+        let text = SceneText {
+            text_size: 0,
+            ..new_text("a\nb\n")
+        };
+
+        assert_eq!(place_text_lines((0, 0), (500, 110), &text, &font).len(), 2);
+        // too small a box: truncated, with a line height of 0
+        assert!(fit_text(&text, (10, 10), 0, &font).is_truncated);
+    }
+}
@@ -0,0 +1,186 @@
+//! The colors of a sheet: background, boxes, text, connectors, and the text of each category of line
+//! eg "[SHIFT1] ..." lines in blue, "NO BINDING" in grey
+//!
+//! - built-in: "dark" (the default, ie the original sheet), "light" (alias "print"), "high-contrast",
+//!   "colour-blind" (Okabe-Ito palette)
+//! - OR the path to a json file with the same fields; the missing ones are taken from "dark"
+//!
+//! Selected with `--theme` or `"theme"` in the template params; the explicit colors of the template
+//! params `style` (and `style_overrides`) have priority over the theme.
+//!
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+
+/// The names of the built-in themes, for the error messages
+const BUILT_IN_THEMES: &[&str] = &["dark", "light", "print", "high-contrast", "colour-blind"];
+
+/// What a text line of a box is about cf `ResolvedButtonGroup::keybind_lines_categories`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum LineCategory {
+    /// A simple press ie NO prefix; also "tap:"
+    Base,
+    /// "[SHIFT1] ..." / "[SHIFT2] ..."
+    Shift,
+    /// "[SHORT] ..." / "[LONG] ..." / "[DOUBLE] ..."
+    Tempo,
+    /// The button IS a SHIFT eg "SHIFT1"
+    Modifier,
+    /// "... NO BINDING"; whatever the prefix
+    NoBinding,
+}
+
+/// Colors are RGBA eg `[50, 50, 50, 220]`
+/// The category colors are optional: `None` means `text_color`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+#[allow(clippy::struct_field_names)]
+pub(super) struct Theme {
+    /// Alpha 0 means transparent ie NO background at all
+    pub(super) background_color: [u8; 4],
+    pub(super) box_color: [u8; 4],
+    pub(super) box_border_color: [u8; 4],
+    pub(super) text_color: [u8; 4],
+    pub(super) connector_color: [u8; 4],
+    pub(super) shift_color: Option<[u8; 4]>,
+    pub(super) tempo_color: Option<[u8; 4]>,
+    pub(super) modifier_color: Option<[u8; 4]>,
+    pub(super) no_binding_color: Option<[u8; 4]>,
+}
+
+impl Default for Theme {
+    /// "dark"
+    fn default() -> Self {
+        Self {
+            background_color: [0, 0, 0, 0],
+            box_color: [50, 50, 50, 220],
+            box_border_color: [50, 50, 50, 220],
+            text_color: [220, 220, 220, 255],
+            connector_color: [0, 255, 0, 255],
+            shift_color: None,
+            tempo_color: None,
+            modifier_color: None,
+            no_binding_color: None,
+        }
+    }
+}
+
+impl Theme {
+    /// A built-in theme name, or the path to a json theme file
+    ///
+    /// # Errors
+    /// - NOT a built-in theme, and NOT a valid json file
+    pub(super) fn from_name_or_path(name_or_path: &str) -> Result<Self, Error> {
+        let theme = match name_or_path {
+            "dark" => Self::default(),
+            "light" | "print" => Self {
+                background_color: [255, 255, 255, 255],
+                box_color: [255, 255, 255, 255],
+                box_border_color: [60, 60, 60, 255],
+                text_color: [20, 20, 20, 255],
+                connector_color: [90, 90, 90, 255],
+                shift_color: Some([0, 70, 160, 255]),
+                tempo_color: Some([150, 60, 0, 255]),
+                modifier_color: Some([0, 110, 60, 255]),
+                no_binding_color: Some([150, 150, 150, 255]),
+            },
+            "high-contrast" => Self {
+                background_color: [0, 0, 0, 255],
+                box_color: [0, 0, 0, 255],
+                box_border_color: [255, 255, 255, 255],
+                text_color: [255, 255, 255, 255],
+                connector_color: [255, 255, 0, 255],
+                shift_color: Some([0, 255, 255, 255]),
+                tempo_color: Some([255, 255, 0, 255]),
+                modifier_color: Some([0, 255, 0, 255]),
+                no_binding_color: Some([170, 170, 170, 255]),
+            },
+            // Okabe-Ito: distinguishable with the common color vision deficiencies
+            "colour-blind" | "color-blind" => Self {
+                background_color: [255, 255, 255, 255],
+                box_color: [245, 245, 245, 255],
+                box_border_color: [0, 0, 0, 255],
+                text_color: [0, 0, 0, 255],
+                connector_color: [0, 114, 178, 255],
+                shift_color: Some([0, 114, 178, 255]),
+                tempo_color: Some([213, 94, 0, 255]),
+                modifier_color: Some([0, 158, 115, 255]),
+                no_binding_color: Some([153, 153, 153, 255]),
+            },
+            path => Self::read_theme_file(Path::new(path))?,
+        };
+
+        Ok(theme)
+    }
+
+    fn read_theme_file(path: &Path) -> Result<Self, Error> {
+        let file = std::fs::File::open(path).map_err(|_err| {
+            Error::UnknownTheme(format!(
+                "{} ; expected one of {BUILT_IN_THEMES:?} or a json file",
+                path.display()
+            ))
+        })?;
+
+        serde_json::from_reader(std::io::BufReader::new(file))
+            .map_err(|err| Error::UnknownTheme(format!("{} : {err}", path.display())))
+    }
+
+    /// The color of a text line; `text_color` is the one of the box cf `TemplateJsonStyleParameters`
+    pub(super) fn get_line_color(
+        &self,
+        category: LineCategory,
+        text_color: [u8; 4],
+    ) -> image::Rgba<u8> {
+        let color = match category {
+            LineCategory::Base => None,
+            LineCategory::Shift => self.shift_color,
+            LineCategory::Tempo => self.tempo_color,
+            LineCategory::Modifier => self.modifier_color,
+            LineCategory::NoBinding => self.no_binding_color,
+        };
+
+        image::Rgba(color.unwrap_or(text_color))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_from_name_or_path() {
+        for name in BUILT_IN_THEMES {
+            assert!(Theme::from_name_or_path(name).is_ok());
+        }
+        assert_eq!(Theme::from_name_or_path("dark").unwrap(), Theme::default());
+        assert_eq!(
+            Theme::from_name_or_path("print").unwrap(),
+            Theme::from_name_or_path("light").unwrap()
+        );
+        assert!(matches!(
+            Theme::from_name_or_path("neon"),
+            Err(Error::UnknownTheme(_))
+        ));
+
+        // A partial user theme: the rest is "dark"
+        let path = std::env::temp_dir().join("test_theme_from_name_or_path.json");
+        std::fs::write(
+            &path,
+            r#"{ "text_color": [0, 0, 0, 255], "no_binding_color": [255, 0, 0, 255] }"#,
+        )
+        .unwrap();
+        let theme = Theme::from_name_or_path(path.to_str().unwrap()).unwrap();
+        assert_eq!(theme.box_color, Theme::default().box_color);
+        assert_eq!(
+            theme.get_line_color(LineCategory::NoBinding, theme.text_color),
+            image::Rgba([255, 0, 0, 255])
+        );
+        assert_eq!(
+            theme.get_line_color(LineCategory::Shift, theme.text_color),
+            image::Rgba([0, 0, 0, 255])
+        );
+    }
+}
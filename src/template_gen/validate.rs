@@ -0,0 +1,265 @@
+//! Check a `Scene` after `layout`, before rendering; the template params are tuned by trial and error so:
+//! - an element (partly) out of the sheet ie clipped
+//! - 2 button boxes overlapping
+//! - a connector over a box of ANOTHER group (its own boxes are where it ends)
+//!
+//! NOTE: only reported; `--strict` refuses to render cf `generate_template`
+//!
+
+use super::scene::{Scene, SceneBox, SceneElement, SceneLabel};
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum LayoutIssue {
+    /// eg "box #3 "Red thumb button" (A2)" or "image full.png"
+    OutOfSheet { element: String },
+    BoxesOverlap {
+        first: SceneLabel,
+        second: SceneLabel,
+    },
+    ConnectorOverBox {
+        connector: SceneLabel,
+        scene_box: SceneLabel,
+    },
+}
+
+impl std::fmt::Display for LayoutIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LayoutIssue::OutOfSheet { element } => write!(f, "{element} is out of the sheet"),
+            LayoutIssue::BoxesOverlap { first, second } => {
+                write!(f, "box {first} overlaps box {second}")
+            }
+            LayoutIssue::ConnectorOverBox {
+                connector,
+                scene_box,
+            } => write!(f, "the connector of {connector} crosses box {scene_box}"),
+        }
+    }
+}
+
+/// left, top, right, bottom; i64 so that NO overflow
+#[derive(Debug, Clone, Copy)]
+struct Rect(i64, i64, i64, i64);
+
+impl Rect {
+    fn new(position: (i32, i32), size: (u32, u32)) -> Self {
+        let (left, top) = (i64::from(position.0), i64::from(position.1));
+        Self(left, top, left + i64::from(size.0), top + i64::from(size.1))
+    }
+
+    fn contains(&self, other: &Rect) -> bool {
+        other.0 >= self.0 && other.1 >= self.1 && other.2 <= self.2 && other.3 <= self.3
+    }
+
+    fn contains_point(&self, point: (i32, i32)) -> bool {
+        (self.0..=self.2).contains(&i64::from(point.0))
+            && (self.1..=self.3).contains(&i64::from(point.1))
+    }
+
+    /// NOTE: strict ie touching is fine eg 2 boxes side by side
+    fn overlaps(&self, other: &Rect) -> bool {
+        self.0 < other.2 && other.0 < self.2 && self.1 < other.3 && other.1 < self.3
+    }
+
+    /// Liang-Barsky: does the segment go through the INSIDE of the rect
+    #[allow(clippy::cast_precision_loss)]
+    fn is_crossed_by(&self, start: (i32, i32), end: (i32, i32)) -> bool {
+        let (x0, y0) = (f64::from(start.0), f64::from(start.1));
+        let (dx, dy) = (f64::from(end.0) - x0, f64::from(end.1) - y0);
+        let (mut t_min, mut t_max) = (0.0_f64, 1.0_f64);
+        for (p, q) in [
+            (-dx, x0 - self.0 as f64),
+            (dx, self.2 as f64 - x0),
+            (-dy, y0 - self.1 as f64),
+            (dy, self.3 as f64 - y0),
+        ] {
+            if p == 0.0 {
+                if q <= 0.0 {
+                    return false;
+                }
+            } else {
+                let t = q / p;
+                if p < 0.0 {
+                    t_min = t_min.max(t);
+                } else {
+                    t_max = t_max.min(t);
+                }
+            }
+        }
+
+        t_min < t_max
+    }
+}
+
+/// ALL the issues, in the order of `scene.elements`
+pub(super) fn validate_scene(scene: &Scene) -> Vec<LayoutIssue> {
+    let sheet = Rect::new((0, 0), scene.size);
+    let button_boxes: Vec<(&SceneBox, &SceneLabel, Rect)> = scene
+        .elements
+        .iter()
+        .filter_map(|element| match element {
+            SceneElement::Box(scene_box) => scene_box.label.as_ref().map(|label| {
+                (
+                    scene_box,
+                    label,
+                    Rect::new(scene_box.position, scene_box.size),
+                )
+            }),
+            _ => None,
+        })
+        .collect();
+
+    let mut issues = vec![];
+    for element in &scene.elements {
+        match element {
+            SceneElement::Image {
+                path,
+                top_left_position,
+                size,
+            } => {
+                if !sheet.contains(&Rect::new(*top_left_position, *size)) {
+                    issues.push(LayoutIssue::OutOfSheet {
+                        element: format!("image {}", path.display()),
+                    });
+                }
+            }
+            SceneElement::Box(scene_box) => {
+                if !sheet.contains(&Rect::new(scene_box.position, scene_box.size)) {
+                    issues.push(LayoutIssue::OutOfSheet {
+                        element: match &scene_box.label {
+                            Some(label) => format!("box {label}"),
+                            None => format!("box at {:?}", scene_box.position),
+                        },
+                    });
+                }
+            }
+            SceneElement::Connector(connector) => {
+                let Some(label) = &connector.label else {
+                    continue;
+                };
+                if !connector
+                    .points
+                    .iter()
+                    .all(|point| sheet.contains_point(*point))
+                {
+                    issues.push(LayoutIssue::OutOfSheet {
+                        element: format!("the connector of {label}"),
+                    });
+                }
+                for (_, box_label, rect) in &button_boxes {
+                    if box_label.group_no != label.group_no
+                        && connector
+                            .get_segments()
+                            .any(|(start, end)| rect.is_crossed_by(start, end))
+                    {
+                        issues.push(LayoutIssue::ConnectorOverBox {
+                            connector: label.clone(),
+                            scene_box: (*box_label).clone(),
+                        });
+                    }
+                }
+            }
+            SceneElement::Hotspot(_) => {}
+        }
+    }
+
+    for (index, (_, first, first_rect)) in button_boxes.iter().enumerate() {
+        for (_, second, second_rect) in &button_boxes[index + 1..] {
+            if first_rect.overlaps(second_rect) {
+                issues.push(LayoutIssue::BoxesOverlap {
+                    first: (*first).clone(),
+                    second: (*second).clone(),
+                });
+            }
+        }
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::params::ConnectorMarker;
+    use super::super::scene::SceneConnector;
+    use super::*;
+
+    fn new_label(group_no: usize, physical_name: Option<&str>) -> SceneLabel {
+        SceneLabel {
+            group_no,
+            user_desc: format!("group {group_no}"),
+            physical_name: physical_name.map(ToString::to_string),
+        }
+    }
+
+    fn new_box(position: (i32, i32), label: SceneLabel) -> SceneElement {
+        SceneElement::Box(SceneBox {
+            position,
+            size: (100, 50),
+            color: image::Rgba([50, 50, 50, 220]),
+            border_color: image::Rgba([50, 50, 50, 220]),
+            stroke_thickness: 2,
+            text: None,
+            label: Some(label),
+        })
+    }
+
+    fn new_line(start: (i32, i32), end: (i32, i32), label: SceneLabel) -> SceneElement {
+        SceneElement::Connector(SceneConnector {
+            points: vec![start, end],
+            thickness: 4,
+            color: image::Rgba([0, 255, 0, 255]),
+            marker: ConnectorMarker::None,
+            label: Some(label),
+        })
+    }
+
+    #[test]
+    fn test_validate_scene_ok() {
+        let scene = Scene {
+            size: (1000, 500),
+            elements: vec![
+                // side by side ie touching is fine
+                new_box((0, 0), new_label(0, Some("(A1)"))),
+                new_box((100, 0), new_label(0, Some("(A2)"))),
+                // ends on its own box
+                new_line((500, 400), (50, 25), new_label(0, None)),
+                new_box((600, 300), new_label(1, Some("(B1)"))),
+            ],
+        };
+
+        assert_eq!(validate_scene(&scene), vec![]);
+    }
+
+    #[test]
+    fn test_validate_scene_issues() {
+        let scene = Scene {
+            size: (1000, 500),
+            elements: vec![
+                new_box((950, 0), new_label(0, Some("(A1)"))),
+                new_box((50, 25), new_label(1, Some("(B1)"))),
+                new_box((0, 0), new_label(1, Some("(B2)"))),
+                // ends on its own box: fine
+                new_line((500, 400), (960, 10), new_label(0, None)),
+                new_line((0, 40), (300, 40), new_label(2, None)),
+                new_line((0, 0), (1200, 40), new_label(3, None)),
+            ],
+        };
+
+        let issues: Vec<String> = validate_scene(&scene)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            issues,
+            vec![
+                r#"box #0 "group 0" (A1) is out of the sheet"#,
+                r#"the connector of #2 "group 2" crosses box #1 "group 1" (B1)"#,
+                r#"the connector of #2 "group 2" crosses box #1 "group 1" (B2)"#,
+                r#"the connector of #3 "group 3" is out of the sheet"#,
+                r#"the connector of #3 "group 3" crosses box #0 "group 0" (A1)"#,
+                r#"the connector of #3 "group 3" crosses box #1 "group 1" (B2)"#,
+                r#"box #1 "group 1" (B1) overlaps box #1 "group 1" (B2)"#,
+            ]
+        );
+    }
+}
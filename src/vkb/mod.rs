@@ -1,13 +1,19 @@
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 use crate::Error;
 
 use self::vkb_button::JoystickButtonsMapping;
 
 pub(crate) mod vkb_button;
+mod vkb_desc_markup;
+mod vkb_fp3;
+mod vkb_lang;
+pub(crate) mod vkb_planner;
+mod vkb_user_data;
 mod vkb_xml;
 
-fn parse_report(xml_path: PathBuf) -> Result<vkb_xml::VkbReport, Error> {
+fn parse_report(xml_path: &Path) -> Result<vkb_xml::VkbReport, Error> {
     let vkb_report = vkb_xml::VkbReport::new(xml_path)?;
 
     Ok(vkb_report)
@@ -15,12 +21,16 @@ fn parse_report(xml_path: PathBuf) -> Result<vkb_xml::VkbReport, Error> {
 
 fn check_report(
     vkb_report: vkb_xml::VkbReport,
-    vkb_user_provided_data: Option<csv::Reader<std::fs::File>>,
+    map_physical_button_id_to_user_desc: Option<HashMap<u8, String>>,
 ) -> Result<vkb_button::JoystickButtonsMapping, Error> {
     let mut vkb_buttons = vkb_button::JoystickButtonsMapping::try_from(vkb_report)?;
 
-    if let Some(vkb_user_provided_data) = vkb_user_provided_data {
-        vkb_buttons.inject_user_provided_desc(vkb_user_provided_data)?;
+    if let Some(map_physical_button_id_to_user_desc) = map_physical_button_id_to_user_desc {
+        vkb_user_data::validate(
+            &map_physical_button_id_to_user_desc,
+            &vkb_buttons.map_physical_button_id_to_infos,
+        )?;
+        vkb_buttons.inject_user_provided_desc(&map_physical_button_id_to_user_desc);
     }
 
     vkb_buttons.log_free_virtual_buttons();
@@ -30,21 +40,26 @@ fn check_report(
 
 /// Parse and process both the L and R sticks
 ///
+/// params:
+/// - `side`: eg "L"/"R"; only used to select the rows of `vkb_user_provided_data_path` cf `vkb_user_data.rs`
+///
 /// # Errors
 /// - `Error::Csv` if the csv path could not be read
+/// - `Error::InvalidUserProvidedData` if the csv does not match the report
 /// - if `stick_fp3_report_path` could not be parsed by `parse_report`
 /// - if `stick_fp3_report_path` failed at `check_report`
 ///   NOTE: for now we are only logging the errors/duplicated buttons etc but that MAY change
 ///
 pub fn parse_and_check_vkb_both_sticks(
-    stick_fp3_report_path: PathBuf,
+    stick_fp3_report_path: &Path,
     vkb_user_provided_data_path: &Option<PathBuf>,
+    side: Option<&str>,
 ) -> Result<JoystickButtonsMapping, Error> {
     let vkb_user_provided_data = match vkb_user_provided_data_path {
-        Some(ref vkb_user_provided_data_path) => {
-            let rdr = csv::Reader::from_path(vkb_user_provided_data_path).map_err(Error::Csv)?;
-            Some(rdr)
-        }
+        Some(ref vkb_user_provided_data_path) => Some(vkb_user_data::read_user_provided_data(
+            vkb_user_provided_data_path,
+            side,
+        )?),
         None => None,
     };
 
@@ -62,15 +77,16 @@ mod tests {
     use std::collections::hash_map;
 
     use crate::button::{
-        PhysicalButton, PhysicalButtonKind, ShiftKind, VirtualButton, VirtualButtonKind,
-        VirtualButtonOrSpecial,
+        PhysicalButton, PhysicalButtonKind, ShiftKind, SpecialButtonKind, VirtualButton,
+        VirtualButtonKind, VirtualButtonOrSpecial,
     };
 
     use super::*;
 
+    #[allow(clippy::too_many_lines)]
     fn get_sample_mappings() -> JoystickButtonsMapping {
         // for simplicity both L and R sticks use the same config
-        let config = JoystickButtonsMapping {
+        JoystickButtonsMapping {
             map_virtual_button_id_to_parent_physical_buttons: hash_map::HashMap::from([
                 // Most basic case: a standard button, no user-injected data (so user_desc is empty)
                 (
@@ -85,7 +101,7 @@ mod tests {
                         },
                         "(A4 left)".to_string(),
                         "- Button with momentary action".to_string(),
-                        "".to_string(),
+                        String::new(),
                     )],
                 ),
                 // More advanced case: the key (108) is NOT found in PhysicalButtonKind
@@ -118,13 +134,31 @@ mod tests {
                                 button_id_shift2: 80,
                             }),
                         },
-                        "".to_string(),
+                        String::new(),
                         "- Button with momentary action".to_string(),
                         "A1 8-way ministick NW".to_string(),
                     )],
                 ),
+                // A SHIFT1 modifier which ALSO emits a joystick button
+                (
+                    120,
+                    vec![PhysicalButton::new(
+                        11,
+                        PhysicalButtonKind::Shift1,
+                        "(D1)".to_string(),
+                        " SHIFT1 ".to_string(),
+                        "Pinky lever".to_string(),
+                    )],
+                ),
             ]),
             map_physical_button_id_to_children_virtual_buttons: hash_map::HashMap::from([
+                (
+                    11,
+                    vec![VirtualButton {
+                        id: 120,
+                        kind: VirtualButtonKind::Momentary(None),
+                    }],
+                ),
                 (
                     27,
                     vec![VirtualButton {
@@ -147,7 +181,13 @@ mod tests {
                     }],
                 ),
             ]),
-            map_special_buttons: hash_map::HashMap::new(),
+            map_special_buttons: hash_map::HashMap::from([
+                ("(D1)".to_string(), SpecialButtonKind::Shift1),
+                ("(Rapid fire press)".to_string(), SpecialButtonKind::Shift2),
+            ]),
+            map_physical_button_id_to_infos: hash_map::HashMap::new(),
+            logical_buttons_count: Some(128),
+            undefined_physical_buttons: vec![],
             // physical_buttons_with_desc: vec![
             //     PhysicalButtonWithDesc {
             //         id: 27,
@@ -168,9 +208,7 @@ mod tests {
             //         user_desc: "A1 8-way ministick NW".to_string(),
             //     },
             // ],
-        };
-
-        config
+        }
     }
 
     #[test]
@@ -217,4 +255,33 @@ mod tests {
             })]
         );
     }
+
+    #[test]
+    fn test_get_virtual_button_id_from_info_or_user_desc_modifier() {
+        let sample_mappings = get_sample_mappings();
+
+        // ONLY a modifier
+        assert_eq!(
+            sample_mappings
+                .get_virtual_button_ids_from_info_or_user_desc("(Rapid fire press)")
+                .unwrap(),
+            vec![VirtualButtonOrSpecial::Special(SpecialButtonKind::Shift2)]
+        );
+
+        // BOTH a modifier and a virtual button; by "info" and by "user_desc"
+        for info_or_user_desc in ["(D1)", "Pinky lever"] {
+            assert_eq!(
+                sample_mappings
+                    .get_virtual_button_ids_from_info_or_user_desc(info_or_user_desc)
+                    .unwrap(),
+                vec![
+                    VirtualButtonOrSpecial::Special(SpecialButtonKind::Shift1),
+                    VirtualButtonOrSpecial::Virtual(VirtualButton {
+                        id: 120,
+                        kind: VirtualButtonKind::Momentary(None)
+                    })
+                ]
+            );
+        }
+    }
 }
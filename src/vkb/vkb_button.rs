@@ -5,12 +5,9 @@
 
 use std::collections::HashMap;
 
-use scraper::ElementRef;
-use scraper::Html;
-use scraper::Selector;
-
+use super::vkb_desc_markup::{parse_markup, select, text_siblings_after_first, MarkupNode};
+use super::vkb_lang::VkbReportLanguage;
 use super::vkb_xml::VkbReport;
-use super::vkb_xml::VkbXmlButton;
 use crate::button::PhysicalButton;
 use crate::button::SpecialButtonKind;
 use crate::button::VirtualButton;
@@ -21,7 +18,7 @@ use crate::button::VirtualTempoKind;
 use crate::button::{PhysicalButtonKind, ShiftKind, TempoKind};
 use crate::Error;
 
-/// Custom `TryFrom<VkbXmlButton>` allowing us to link a parent to a Virtual button
+// Custom `TryFrom<VkbXmlButton>` allowing us to link a parent to a Virtual button
 // impl Button {
 //     fn try_from(xml_button: VkbXmlButton, parent: &Option<Button>) -> Result<Self, Error> {
 //         match xml_button {
@@ -54,7 +51,7 @@ pub struct JoystickButtonsMapping {
     ///
     /// Result: the same in-game function can be done using two different buttons
     ///
-    /// Note that is NOT detected by VkbDevCfg, probably because this NOT (necessarily) a bug;
+    /// Note that is NOT detected by `VkbDevCfg`, probably because this NOT (necessarily) a bug;
     /// this is mostly a "waste of space".
     pub(crate) map_virtual_button_id_to_parent_physical_buttons: HashMap<u8, Vec<PhysicalButton>>,
     /// Inverse of `map_virtual_button_id_to_physical_button`
@@ -62,42 +59,36 @@ pub struct JoystickButtonsMapping {
     // pub(crate) physical_buttons_with_desc: Vec<PhysicalButtonWithDesc>,
     /// MAP eg "(D1)" -> Shift1
     pub(crate) map_special_buttons: HashMap<String, SpecialButtonKind>,
+    /// The "No defined function" physical buttons ie `PhysicalButtonKind::Undefined`
+    /// They have NO children virtual buttons so they are NOT in the two maps above; they are free real estate.
+    pub(crate) undefined_physical_buttons: Vec<PhysicalButton>,
+    /// ALL the physical buttons in the report, whatever their kind
+    /// A Vec b/c the same line can be used twice eg #21 "(A1 right)" POV and #21 "(A3 down)"
+    pub(crate) map_physical_button_id_to_infos: HashMap<u8, Vec<String>>,
+    /// From the report header eg "Number of logical buttons : 128"; None if not found
+    /// The game does NOT see the virtual buttons above it.
+    pub(crate) logical_buttons_count: Option<u8>,
 }
 
 impl JoystickButtonsMapping {
+    /// Set the "`user_desc`" of ALL the physical buttons; cf `vkb_user_data.rs`
+    /// NOTE: the ids not in `map_physical_button_id_to_user_desc` are left as-is ie usually empty
     pub(crate) fn inject_user_provided_desc(
         &mut self,
-        vkb_user_provided_data: csv::Reader<std::fs::File>,
-    ) -> Result<(), Error> {
-        let csv_records: Vec<_> = vkb_user_provided_data.into_records().collect();
-
-        // for button_with_desc in &mut self.physical_buttons_with_desc {
-        //     let user_desc = csv_records[button_with_desc.id as usize - 1]
-        //         .as_ref()
-        //         .expect("MISSING CSV RECORD")
-        //         .get(1)
-        //         .expect("MISSING CSV COLUMN")
-        //         .to_string();
-        //     button_with_desc.user_desc = user_desc;
-        // }
-
-        // ALSO update the other field
-        for (_virtual_button_id, physical_buttons_parents) in &mut self
+        map_physical_button_id_to_user_desc: &HashMap<u8, String>,
+    ) {
+        for physical_button in self
             .map_virtual_button_id_to_parent_physical_buttons
-            .iter_mut()
+            .values_mut()
+            .flatten()
+            .chain(self.undefined_physical_buttons.iter_mut())
         {
-            for physical_button_parent in physical_buttons_parents.iter_mut() {
-                let user_desc = csv_records[*physical_button_parent.get_id() as usize - 1]
-                    .as_ref()
-                    .map_err(|_| Error::OtherXmlParsingError("MISSING CSV RECORD".to_string()))?
-                    .get(1)
-                    .ok_or_else(|| Error::OtherXmlParsingError("MISSING CSV COLUMN".to_string()))?
-                    .to_string();
-                physical_button_parent.set_user_desc(&user_desc);
+            if let Some(user_desc) =
+                map_physical_button_id_to_user_desc.get(physical_button.get_id())
+            {
+                physical_button.set_user_desc(user_desc);
             }
         }
-
-        Ok(())
     }
 
     /// CHECK/LOG the "free" Virtual buttons
@@ -110,20 +101,28 @@ impl JoystickButtonsMapping {
     /// NOTE: it is allowed to do it in `VKBDevCfg` but it's a waste of time to try because in the end you will
     /// get two physical buttons activating the conflicting virtual one.
     pub(crate) fn log_free_virtual_buttons(&self) -> Vec<u8> {
+        let unused_virtual_buttons = self.get_free_virtual_buttons();
+
+        log::info!("unused virtual buttons : {unused_virtual_buttons:?}");
+
+        unused_virtual_buttons
+    }
+
+    /// The "free" Virtual buttons, in increasing order; cf `log_free_virtual_buttons` for the rules
+    /// NOTE: ALL the physical lines count, whatever their kind eg "No defined function", SHIFT, keyboard...
+    /// and the IDs stop at `logical_buttons_count` (128 if unknown) b/c the game does NOT see the ones above
+    pub(crate) fn get_free_virtual_buttons(&self) -> Vec<u8> {
         const NB_VIRTUAL_BUTTONS: u8 = 128;
 
         let mut unused_virtual_buttons = vec![];
 
-        for i in 1..NB_VIRTUAL_BUTTONS {
+        for i in 1..=self.logical_buttons_count.unwrap_or(NB_VIRTUAL_BUTTONS) {
             if !self
                 .map_virtual_button_id_to_parent_physical_buttons
                 .contains_key(&i)
             {
                 // cf [2]
-                if self
-                    .map_physical_button_id_to_children_virtual_buttons
-                    .contains_key(&i)
-                {
+                if self.map_physical_button_id_to_infos.contains_key(&i) {
                     log::debug!("log_free_virtual_buttons : VIRTUAL {i} is a physical line!");
                 } else {
                     unused_virtual_buttons.push(i);
@@ -131,11 +130,49 @@ impl JoystickButtonsMapping {
             }
         }
 
-        log::info!("unused virtual buttons : {unused_virtual_buttons:?}");
-
         unused_virtual_buttons
     }
 
+    /// Find a PHYSICAL button with at least one child virtual button,
+    /// whose "info" == `info_or_user_desc` or "`user_desc`" == `info_or_user_desc`
+    pub(crate) fn find_physical_button(&self, info_or_user_desc: &str) -> Option<&PhysicalButton> {
+        self.map_virtual_button_id_to_parent_physical_buttons
+            .values()
+            .flatten()
+            .find(|parent_physical_button| {
+                info_or_user_desc == parent_physical_button.get_info()
+                    || info_or_user_desc == parent_physical_button.get_user_desc()
+            })
+    }
+
+    /// The "info" of ALL the physical buttons acting as `shift_kind` eg `["(D1)"]`; sorted
+    /// They are either "SHIFT only" (in `map_special_buttons`) or ALSO emitting a joystick button (in the parents)
+    /// Empty if this SHIFT is not defined on the stick.
+    pub(crate) fn get_shift_buttons_infos(&self, shift_kind: &VirtualShiftKind) -> Vec<&String> {
+        let (special_kind, physical_kind) = match shift_kind {
+            VirtualShiftKind::Shift1 => (SpecialButtonKind::Shift1, PhysicalButtonKind::Shift1),
+            VirtualShiftKind::Shift2 => (SpecialButtonKind::Shift2, PhysicalButtonKind::Shift2),
+        };
+
+        let mut shift_buttons_infos: Vec<&String> = self
+            .map_special_buttons
+            .iter()
+            .filter(|(_, other_special_kind)| **other_special_kind == special_kind)
+            .map(|(info, _)| info)
+            .chain(
+                self.map_virtual_button_id_to_parent_physical_buttons
+                    .values()
+                    .flatten()
+                    .filter(|physical_button| *physical_button.get_kind() == physical_kind)
+                    .map(PhysicalButton::get_info),
+            )
+            .collect();
+        shift_buttons_infos.sort();
+        shift_buttons_infos.dedup();
+
+        shift_buttons_infos
+    }
+
     /// Let's say `info_or_user_desc` = "A1 8-way ministick N" or "(A2)"
     /// We want to return the corresponding VIRTUAL BUTTON IDS (plural!)
     /// That way when a loop in the game binding, we can easily get the corresponding label from it eg "deploy landing gear" etc
@@ -143,43 +180,31 @@ impl JoystickButtonsMapping {
     /// We are looking for a VIRTUAL BUTTON (ID) whose PARENT (PHYSICAL) BUTTON
     /// has "info" == `info_or_user_desc` or "`user_desc`" == `info_or_user_desc`
     ///
+    /// A SHIFT1/SHIFT2 button which ALSO emits a joystick button returns BOTH roles, the `Special` one FIRST
+    /// eg `[Special(Shift1), Virtual(12)]` -> rendered as "SHIFT1 / tap: `v_target_cycle`"
+    ///
     // TODO is this OK? should it return a Vec? Add more tests with STD+SHIFT1+SHIFT2 from real bininds
     // and check
     pub(crate) fn get_virtual_button_ids_from_info_or_user_desc(
         &self,
         info_or_user_desc: &str,
     ) -> Result<Vec<VirtualButtonOrSpecial>, Error> {
-        // SHORTCUT to handle SHIFT1/SHIT2
+        // SHIFT1/SHIT2
         // They are (usually) NOT bound to ingame actions because they are (usually) ONLY a modifier
-        // so the look up in `map_virtual_button_id_to_parent_physical_buttons` will NOT return anything
-        // TODO this probably is NOT handling when the button is BOTH a modifier AND a virtual button
-        if let Some(special_kind) = self.map_special_buttons.get(info_or_user_desc) {
-            return Ok(vec![VirtualButtonOrSpecial::Special(special_kind.clone())]);
-        };
-
-        let mut found_physical_button_id: Option<u8> = None;
+        // so the look up in `map_virtual_button_id_to_parent_physical_buttons` will (usually) NOT return anything
+        // BUT they CAN also emit a joystick button -> NO shortcut here, cf the end of this function
+        let special_kind = self.map_special_buttons.get(info_or_user_desc).cloned();
 
         // First: loop for the target PHYSICAL button; cf docstring
-        for parent_physical_buttons in self
-            .map_virtual_button_id_to_parent_physical_buttons
-            .values()
-        {
-            for parent_physical_button in parent_physical_buttons {
-                if info_or_user_desc == parent_physical_button.get_info()
-                    || info_or_user_desc == parent_physical_button.get_user_desc()
-                {
-                    found_physical_button_id = Some(*parent_physical_button.get_id());
-                    break;
-                }
-            }
-        }
+        let found_physical_button = self.find_physical_button(info_or_user_desc);
 
         // Next we MUST get ALL the children VIRTUAL buttons
-        match found_physical_button_id {
-            Some(found_physical_button_id) => {
+        match (found_physical_button, special_kind) {
+            (Some(found_physical_button), _) => {
+                let found_physical_button_id = found_physical_button.get_id();
                 let buttons = self
                     .map_physical_button_id_to_children_virtual_buttons
-                    .get(&found_physical_button_id)
+                    .get(found_physical_button_id)
                     .ok_or_else(|| {
                         Error::OtherXmlParsingError(format!(
                             "could not find {found_physical_button_id} in map"
@@ -187,12 +212,22 @@ impl JoystickButtonsMapping {
                     })?
                     .clone();
 
-                Ok(buttons
+                // The modifier role; `map_special_buttons` is keyed by "info" so this is needed when
+                // found by "user_desc"
+                let special_kind = match found_physical_button.get_kind() {
+                    PhysicalButtonKind::Shift1 => Some(SpecialButtonKind::Shift1),
+                    PhysicalButtonKind::Shift2 => Some(SpecialButtonKind::Shift2),
+                    _ => None,
+                };
+
+                Ok(special_kind
+                    .map(VirtualButtonOrSpecial::Special)
                     .into_iter()
-                    .map(VirtualButtonOrSpecial::Virtual)
+                    .chain(buttons.into_iter().map(VirtualButtonOrSpecial::Virtual))
                     .collect())
             }
-            None => Err(Error::ButtonNotFound {
+            (None, Some(special_kind)) => Ok(vec![VirtualButtonOrSpecial::Special(special_kind)]),
+            (None, None) => Err(Error::ButtonNotFound {
                 info_or_user_desc: info_or_user_desc.to_string(),
             }),
         }
@@ -202,8 +237,8 @@ impl JoystickButtonsMapping {
 impl TryFrom<VkbReport> for JoystickButtonsMapping {
     type Error = Error;
 
-    // TODO(add-CHECK) this should be 2 maps; one parent->children and one child->parent; that way we can display proper
-    // warnings to find where the duplicates originate
+    // NOTE: the duplicates are only logged here; cf `checks::duplicates` for a report with their origins
+    #[allow(clippy::too_many_lines)]
     fn try_from(vkb_report: VkbReport) -> Result<Self, Self::Error> {
         let mut map_virtual_button_id_to_parent_physical_buttons: HashMap<u8, Vec<PhysicalButton>> =
             HashMap::new();
@@ -212,107 +247,116 @@ impl TryFrom<VkbReport> for JoystickButtonsMapping {
             Vec<VirtualButton>,
         > = HashMap::new();
         let mut map_special_buttons: HashMap<String, SpecialButtonKind> = HashMap::new();
+        let mut undefined_physical_buttons = vec![];
+        let mut map_physical_button_id_to_infos: HashMap<u8, Vec<String>> = HashMap::new();
 
-        let vkb_buttons = vkb_report.get_all_buttons();
-
+        let lang = VkbReportLanguage::detect(vkb_report.get_header())?;
+        let logical_buttons_count =
+            lang.parse_header_logical_buttons_count(vkb_report.get_header());
         // We loop on all b2/b3 buttons from the xml
         // IMPORTANT:
         // - b2 are the physical buttons, they are the PARENT
         // - b3 are virtual/logical ones: these are the ones bound in-game
-        let mut current_parent = None;
-        for vkb_button in &vkb_buttons {
-            match vkb_button {
-                VkbXmlButton::B2(b2_xml) => {
-                    // Try to build a Button(Physical) from a B2 xml field
-                    let physical_button =
-                        parse_b2_button_desc_xml_escaped(&b2_xml.m7.desc_xml_escaped)?;
-
-                    // TODO(re-add CHECK): the "m5" field SHOULD match the parsed button ID
-                    // if b2_xml.m5.physical_button_id.parse::<u8>().unwrap() != button.get_id() {
-                    //     return Err(Error::UnexpectedXmlDesc(format!(
-                    //         "m5 field value does not match: {:?}",
-                    //         b2_xml
-                    //     )));
-                    // }
-
-                    // Store the modifier buttons separately
-                    // cf `get_virtual_button_ids_from_info_or_user_desc` for how it's used
-                    match physical_button.get_kind() {
-                        PhysicalButtonKind::Shift1 => {
-                            map_special_buttons.insert(
-                                physical_button.get_info().to_string(),
-                                SpecialButtonKind::Shift1,
-                            );
-                        }
-                        PhysicalButtonKind::Shift2 => {
-                            map_special_buttons.insert(
-                                physical_button.get_info().to_string(),
-                                SpecialButtonKind::Shift2,
-                            );
-                        }
-                        _ => {}
-                    };
+        for b2_xml in vkb_report.get_all_buttons() {
+            // Try to build a Button(Physical) from a B2 xml field
+            let physical_button =
+                parse_b2_button_desc_xml_escaped(&b2_xml.desc, lang).map_err(|err| {
+                    Error::ReportParsing {
+                        offset: b2_xml.offset,
+                        msg: err.to_string(),
+                    }
+                })?;
 
-                    current_parent = Some(physical_button.clone());
+            // TODO(re-add CHECK): the "m5" field SHOULD match the parsed button ID
+            // if b2_xml.physical_button_id.parse::<u8>().unwrap() != button.get_id() {
+            //     return Err(Error::UnexpectedXmlDesc(format!(
+            //         "m5 field value does not match: {:?}",
+            //         b2_xml
+            //     )));
+            // }
+
+            map_physical_button_id_to_infos
+                .entry(*physical_button.get_id())
+                .or_default()
+                .push(physical_button.get_info().clone());
+
+            // Store the modifier buttons separately
+            // cf `get_virtual_button_ids_from_info_or_user_desc` for how it's used
+            match physical_button.get_kind() {
+                PhysicalButtonKind::Shift1 => {
+                    map_special_buttons.insert(
+                        physical_button.get_info().clone(),
+                        SpecialButtonKind::Shift1,
+                    );
                 }
-                VkbXmlButton::B3(b3_xml) => {
-                    // CHECK when processing a Virtual button, we MUST have a valid parent
-                    let current_parent = current_parent.clone().ok_or_else(|| {
-                        Error::OtherXmlParsingError("parent is not yet set".to_string())
-                    })?;
-
-                    // Try to build a Button(Virtual) from a B3 xml field
-                    let virtual_button = parse_b3_button_desc_xml_escaped(
-                        &b3_xml.m9.desc_xml_escaped,
-                        &current_parent,
-                    )?;
+                PhysicalButtonKind::Shift2 => {
+                    map_special_buttons.insert(
+                        physical_button.get_info().clone(),
+                        SpecialButtonKind::Shift2,
+                    );
+                }
+                PhysicalButtonKind::Undefined => {
+                    undefined_physical_buttons.push(physical_button.clone());
+                }
+                _ => {}
+            }
 
-                    // CHECK: the "m8" field SHOULD match the parsed button ID
-                    // NO! cf `test_parse_b3_button_desc_xml_escaped`
-                    // if b3_xml.m8.virtual_button_id.parse::<u8>().unwrap() != button.get_id() {
-                    //     return Err(Error::UnexpectedXmlDesc(format!(
-                    //         "m8 field value does not match: {:?}",
-                    //         b3_xml
-                    //     )));
-                    // }
-
-                    let virtual_button_id = virtual_button.get_id();
-
-                    map_virtual_button_id_to_parent_physical_buttons
-                        .entry(*virtual_button_id)
-                        // NOT inserted = the virtual button was already processed!
-                        .and_modify(|parents| {
-                            parents.push(current_parent.clone());
-                            log::warn!(
-                                "virtual button duplicated : {} from physical : {parents:?}",
-                                virtual_button_id
-                            );
-                        })
-                        // inserted = nothing to do
-                        .or_insert(vec![current_parent.clone()]);
-
-                    map_physical_button_id_to_children_virtual_buttons
-                        .entry(*current_parent.get_id())
-                        // NOT inserted = the virtual button was already processed!
-                        .and_modify(|children| {
-                            children.push(virtual_button.clone());
-                            // is this a warning???
-                            log::info!(
-                                "physical button duplicated : {} from logical : {children:?}",
-                                virtual_button_id
-                            );
-                        })
-                        // inserted = nothing to do
-                        .or_insert(vec![virtual_button.clone()]);
+            let current_parent = physical_button;
+            for b3_xml in &b2_xml.children {
+                // Try to build a Button(Virtual) from a B3 xml field
+                let virtual_button =
+                    parse_b3_button_desc_xml_escaped(&b3_xml.desc, &current_parent, lang).map_err(
+                        |err| Error::ReportParsing {
+                            offset: b3_xml.offset,
+                            msg: err.to_string(),
+                        },
+                    )?;
 
-                    // NOTE: NOT an error; it can just happen; for example right now for the 8 way ministick switch
-                    // they entries have the same physical ID and logical ID
-                    if current_parent.get_id() == virtual_button_id {
+                // CHECK: the "m8" field SHOULD match the parsed button ID
+                // NO! cf `test_parse_b3_button_desc_xml_escaped`
+                // if b3_xml.virtual_button_id.parse::<u8>().unwrap() != button.get_id() {
+                //     return Err(Error::UnexpectedXmlDesc(format!(
+                //         "m8 field value does not match: {:?}",
+                //         b3_xml
+                //     )));
+                // }
+
+                let virtual_button_id = virtual_button.get_id();
+
+                map_virtual_button_id_to_parent_physical_buttons
+                    .entry(*virtual_button_id)
+                    // NOT inserted = the virtual button was already processed!
+                    .and_modify(|parents| {
+                        parents.push(current_parent.clone());
+                        log::warn!(
+                            "virtual button duplicated : {} from physical : {parents:?}",
+                            virtual_button_id
+                        );
+                    })
+                    // inserted = nothing to do
+                    .or_insert(vec![current_parent.clone()]);
+
+                map_physical_button_id_to_children_virtual_buttons
+                    .entry(*current_parent.get_id())
+                    // NOT inserted = the virtual button was already processed!
+                    .and_modify(|children| {
+                        children.push(virtual_button.clone());
+                        // is this a warning???
                         log::info!(
-                            "Virtual button and parent (physical) button have the same ID??? {}",
+                            "physical button duplicated : {} from logical : {children:?}",
                             virtual_button_id
                         );
-                    }
+                    })
+                    // inserted = nothing to do
+                    .or_insert(vec![virtual_button.clone()]);
+
+                // NOTE: NOT an error; it can just happen; for example right now for the 8 way ministick switch
+                // they entries have the same physical ID and logical ID
+                if current_parent.get_id() == virtual_button_id {
+                    log::info!(
+                        "Virtual button and parent (physical) button have the same ID??? {}",
+                        virtual_button_id
+                    );
                 }
             }
         }
@@ -321,6 +365,9 @@ impl TryFrom<VkbReport> for JoystickButtonsMapping {
             map_virtual_button_id_to_parent_physical_buttons,
             map_physical_button_id_to_children_virtual_buttons,
             map_special_buttons,
+            undefined_physical_buttons,
+            map_physical_button_id_to_infos,
+            logical_buttons_count,
         })
     }
 }
@@ -355,36 +402,30 @@ struct ButtonIdAndInfo {
 /// - or 2 strings if there are two useful lines
 /// - or none at all
 ///
-/// NOTE: the given "element" SHOULD be the first element in tree order b/c we only iterate `forward("next_sibling`")
-fn extract_text_next_siblings(element: &ElementRef<'_>) -> (Option<String>, Option<String>) {
-    let mut text_siblings = Vec::new();
-
-    // Iterate over the next siblings
-    let mut current = element.next_sibling();
-    while let Some(sibling) = current {
-        // if let NodeRef::Text(text) = sibling.value() {
-        if let Some(text) = sibling.value().as_text() {
-            let text = text.trim();
-            let text = text.replacen('\n', "", 1);
-            text_siblings.push(text);
-        }
-        current = sibling.next_sibling();
-    }
+/// NOTE: only the siblings of the FIRST "b" element are considered, cf `text_siblings_after_first`
+fn extract_text_next_siblings(fragment: &[MarkupNode]) -> (Option<String>, Option<String>) {
+    let text_siblings: Vec<String> = text_siblings_after_first(fragment, "b")
+        .into_iter()
+        .map(|text| text.trim().replacen('\n', "", 1))
+        .collect();
 
     match &text_siblings[..] {
         [] => (None, None),
-        [first] => (Some(first.to_string()), None),
-        [first, second] => (Some(first.to_string()), Some(second.to_string())),
+        [first] => (Some(first.clone()), None),
+        [first, second] => (Some(first.clone()), Some(second.clone())),
         _ => todo!(),
     }
 }
 
 #[allow(clippy::too_many_lines)]
-fn parse_b2_button_desc_xml_escaped(desc_xml_escaped: &str) -> Result<PhysicalButton, Error> {
+fn parse_b2_button_desc_xml_escaped(
+    desc_xml_escaped: &str,
+    lang: &VkbReportLanguage,
+) -> Result<PhysicalButton, Error> {
     // let lines: Vec<&str> = desc_xml_escaped.split("\r\n").collect();
     // let first_line = lines[0];
 
-    let fragment = Html::parse_fragment(desc_xml_escaped);
+    let fragment = parse_markup(desc_xml_escaped);
 
     // log::debug!("parse_desc_xml fragment : {:#?}", fragment.tree);
     // for node in fragment.tree.nodes() {
@@ -393,9 +434,7 @@ fn parse_b2_button_desc_xml_escaped(desc_xml_escaped: &str) -> Result<PhysicalBu
 
     // the selected inner_html should contain something like:
     // "#1 (E1) ", "#2  - Encoder 2/4", etc
-    let b_selector = Selector::parse("b")
-        .map_err(|_| Error::OtherXmlParsingError("missing <b> selector".to_string()))?;
-    let b_nodes: Vec<_> = fragment.select(&b_selector).collect();
+    let b_nodes = select(&fragment, "b");
 
     // log::debug!("b_nodes [{}] : {:?}", b_nodes.len(), b_nodes);
     // for b_node in b_nodes.iter() {
@@ -403,14 +442,17 @@ fn parse_b2_button_desc_xml_escaped(desc_xml_escaped: &str) -> Result<PhysicalBu
     // }
 
     // Only extract the ID from the FIRST "b" node
-    let button_id_info = extract_button_id_from_inner_html(&b_nodes[0].inner_html())?;
+    let first_b_node = b_nodes
+        .first()
+        .ok_or_else(|| Error::UnexpectedXmlDesc(desc_xml_escaped.to_string()))?;
+    let button_id_info = extract_button_id_from_inner_html(&first_b_node.inner_text())?;
     // IF there are more "b" nodes, they contains only additional info like:
     // "<b>- Button with momentary action</b>"
     // "<b>TEMPO </b>"
     // etc
     assert!(b_nodes.len() <= 2, "more b nodes than expected!");
     let remaining_b_node = &b_nodes.get(1);
-    let texts = extract_text_next_siblings(&b_nodes[0]);
+    let texts = extract_text_next_siblings(&fragment);
 
     // Now we have various cases:
     //
@@ -428,66 +470,63 @@ fn parse_b2_button_desc_xml_escaped(desc_xml_escaped: &str) -> Result<PhysicalBu
         unimplemented!("SHOULD NOT be here, this is only for b3 field???")
     };
 
-    let remaining_b_node_inner_html = remaining_b_node.inner_html();
-    let kind = if remaining_b_node_inner_html.contains("TEMPO") {
+    let remaining_b_node_inner_html = remaining_b_node.inner_text();
+    let kind = if remaining_b_node_inner_html.contains(lang.tempo) {
         let (Some(text), None) = texts else {
             unimplemented!("TEMPO: SHOULD NOT be here!")
         };
-        if text.contains("Virtual button Short #")
-            && text.contains("Virtual button Long #")
-            && !text.contains("Virtual button Double Short #")
+        if text.contains(lang.tempo_short)
+            && text.contains(lang.tempo_long)
+            && !text.contains(lang.tempo_double)
         {
             let short_id = text
-                .split("Virtual button Short #")
+                .split(lang.tempo_short)
                 .last()
                 .ok_or_else(|| {
                     Error::OtherXmlParsingError("Virtual button Short/Long err1".to_string())
                 })?
-                .split("Virtual button Long #")
+                .split(lang.tempo_long)
                 .next()
                 .ok_or_else(|| {
                     Error::OtherXmlParsingError("Virtual button Short/Long err2".to_string())
                 })?;
-            let long_id = text.split("Virtual button Long #").last().ok_or_else(|| {
+            let long_id = text.split(lang.tempo_long).last().ok_or_else(|| {
                 Error::OtherXmlParsingError("Virtual button Short/Long err3".to_string())
             })?;
             PhysicalButtonKind::Tempo(TempoKind::Tempo2 {
                 button_id_short: short_id.parse().map_err(Error::ParseIntError)?,
                 button_id_long: long_id.parse().map_err(Error::ParseIntError)?,
             })
-        } else if text.contains("Virtual button Short #")
-            && text.contains("Virtual button Long #")
-            && text.contains("Virtual button Double Short #")
+        } else if text.contains(lang.tempo_short)
+            && text.contains(lang.tempo_long)
+            && text.contains(lang.tempo_double)
         {
             let short_id = text
-                .split("Virtual button Short #")
+                .split(lang.tempo_short)
                 .last()
                 .ok_or_else(|| {
                     Error::OtherXmlParsingError("Virtual button Short/Long/Double err1".to_string())
                 })?
-                .split("Virtual button Long #")
+                .split(lang.tempo_long)
                 .next()
                 .ok_or_else(|| {
                     Error::OtherXmlParsingError("Virtual button Short/Long/Double err2".to_string())
                 })?;
             let long_id = text
-                .split("Virtual button Long #")
+                .split(lang.tempo_long)
                 .last()
                 .ok_or_else(|| {
                     Error::OtherXmlParsingError("Virtual button Short/Long/Double err3".to_string())
                 })?
-                .split("Virtual button Double Short #")
+                .split(lang.tempo_double)
                 .next()
                 .ok_or_else(|| {
                     Error::OtherXmlParsingError("Virtual button Short/Long/Double err4".to_string())
                 })?
                 .replace('\n', "");
-            let double_id = text
-                .split("Virtual button Double Short #")
-                .last()
-                .ok_or_else(|| {
-                    Error::OtherXmlParsingError("Virtual button Short/Long/Double err5".to_string())
-                })?;
+            let double_id = text.split(lang.tempo_double).last().ok_or_else(|| {
+                Error::OtherXmlParsingError("Virtual button Short/Long/Double err5".to_string())
+            })?;
             PhysicalButtonKind::Tempo(TempoKind::Tempo3 {
                 button_id_short: short_id.parse().map_err(Error::ParseIntError)?,
                 button_id_long: long_id.parse().map_err(Error::ParseIntError)?,
@@ -496,27 +535,27 @@ fn parse_b2_button_desc_xml_escaped(desc_xml_escaped: &str) -> Result<PhysicalBu
         } else {
             todo!()
         }
-    } else if remaining_b_node_inner_html.contains("Encoder") {
+    } else if remaining_b_node_inner_html.contains(lang.encoder) {
         PhysicalButtonKind::Encoder
-    } else if remaining_b_node_inner_html.contains("Button with momentary action") {
+    } else if remaining_b_node_inner_html.contains(lang.momentary) {
         match texts {
             (None, None) => PhysicalButtonKind::Momentary { shift: None },
             (None, Some(_)) => unimplemented!(
                 "SHOULD NOT be here, SHOULD NOT be able to get a second txt without a first!"
             ),
             (Some(text), None) => {
-                if text.contains("Virtual button with SHIFT1 =")
-                    && text.contains("Virtual button with SHIFT2 =")
+                if text.contains(lang.virtual_button_with_shift1)
+                    && text.contains(lang.virtual_button_with_shift2)
                 {
                     let shift1_id = text
-                        .split("Virtual button with SHIFT1 = ")
+                        .split(lang.virtual_button_with_shift1)
                         .last()
                         .ok_or_else(|| {
                             Error::OtherXmlParsingError(
                                 "Button with momentary action err1".to_string(),
                             )
                         })?
-                        .split("Virtual button with SHIFT2 = ")
+                        .split(lang.virtual_button_with_shift2)
                         .next()
                         .ok_or_else(|| {
                             Error::OtherXmlParsingError(
@@ -524,7 +563,7 @@ fn parse_b2_button_desc_xml_escaped(desc_xml_escaped: &str) -> Result<PhysicalBu
                             )
                         })?;
                     let shift2_id = text
-                        .split("Virtual button with SHIFT2 = ")
+                        .split(lang.virtual_button_with_shift2)
                         .last()
                         .ok_or_else(|| {
                             Error::OtherXmlParsingError(
@@ -537,11 +576,11 @@ fn parse_b2_button_desc_xml_escaped(desc_xml_escaped: &str) -> Result<PhysicalBu
                             button_id_shift2: shift2_id.parse().map_err(Error::ParseIntError)?,
                         }),
                     }
-                } else if text.contains("Virtual button with SHIFT1 =")
-                    && !text.contains("Virtual button with SHIFT2 =")
+                } else if text.contains(lang.virtual_button_with_shift1)
+                    && !text.contains(lang.virtual_button_with_shift2)
                 {
                     let shift1_id = text
-                        .split("Virtual button with SHIFT1 = ")
+                        .split(lang.virtual_button_with_shift1)
                         .last()
                         .ok_or_else(|| {
                             Error::OtherXmlParsingError(
@@ -553,11 +592,11 @@ fn parse_b2_button_desc_xml_escaped(desc_xml_escaped: &str) -> Result<PhysicalBu
                             button_id_shift1: shift1_id.parse().map_err(Error::ParseIntError)?,
                         }),
                     }
-                } else if !text.contains("Virtual button with SHIFT1 =")
-                    && text.contains("Virtual button with SHIFT2 =")
+                } else if !text.contains(lang.virtual_button_with_shift1)
+                    && text.contains(lang.virtual_button_with_shift2)
                 {
                     let shift1_id = text
-                        .split("Virtual button with SHIFT2 = ")
+                        .split(lang.virtual_button_with_shift2)
                         .last()
                         .ok_or_else(|| {
                             Error::OtherXmlParsingError(
@@ -575,11 +614,11 @@ fn parse_b2_button_desc_xml_escaped(desc_xml_escaped: &str) -> Result<PhysicalBu
             }
             (Some(_), Some(_)) => todo!(),
         }
-    } else if remaining_b_node_inner_html.contains(" SHIFT1 ") {
+    } else if remaining_b_node_inner_html.contains(lang.shift1) {
         PhysicalButtonKind::Shift1
-    } else if remaining_b_node_inner_html.contains(" SHIFT2 alternate action") {
+    } else if remaining_b_node_inner_html.contains(lang.shift2) {
         PhysicalButtonKind::Shift2
-    } else if remaining_b_node_inner_html.contains("Point of view Switch") {
+    } else if remaining_b_node_inner_html.contains(lang.pov) {
         let direction = texts
             .1
             .ok_or_else(|| {
@@ -588,18 +627,18 @@ fn parse_b2_button_desc_xml_escaped(desc_xml_escaped: &str) -> Result<PhysicalBu
                 )
             })?
             .split(' ')
-            .last()
+            .next_back()
             .ok_or_else(|| {
                 Error::OtherXmlParsingError("could not parse Point of view Switch".to_string())
             })?
             .to_string();
         PhysicalButtonKind::Pov { direction }
-    } else if remaining_b_node_inner_html.contains("No defined function") {
+    } else if remaining_b_node_inner_html.contains(lang.undefined) {
         PhysicalButtonKind::Undefined
-    } else if remaining_b_node_inner_html.contains("Microstick Mode Switch") {
+    } else if remaining_b_node_inner_html.contains(lang.microstick_mode_switch) {
         PhysicalButtonKind::MicrostickModeSwitch
     } else {
-        todo!("not TEMPO");
+        return Err(Error::UnexpectedXmlDesc(desc_xml_escaped.to_string()));
     };
 
     // else if remaining_b_node.is_some() && remaining_b_node.unwrap().parent().unwrap().s
@@ -609,7 +648,7 @@ fn parse_b2_button_desc_xml_escaped(desc_xml_escaped: &str) -> Result<PhysicalBu
         kind,
         button_id_info
             .info
-            .ok_or_else(|| Error::MissingXmlInfo(button_id_info.id))?,
+            .ok_or(Error::MissingXmlInfo(button_id_info.id))?,
         remaining_b_node_inner_html,
         String::new(),
     );
@@ -621,12 +660,13 @@ fn parse_b2_button_desc_xml_escaped(desc_xml_escaped: &str) -> Result<PhysicalBu
 fn parse_b3_button_desc_xml_escaped(
     desc_xml_escaped: &str,
     parent_button: &PhysicalButton,
+    lang: &VkbReportLanguage,
 ) -> Result<VirtualButton, Error> {
     // eg with "<b>#5 </b> Joystick button : #11" ->
     // physical_button_id_str: 5
     // virtual_button_id_str: 11
     let splitted = desc_xml_escaped
-        .split("Joystick button : #")
+        .split(lang.joystick_button)
         .collect::<Vec<_>>();
     let physical_button_id_str = splitted[0].split("<b>#").collect::<Vec<_>>()[1]
         .split(" </b>")
@@ -734,11 +774,10 @@ fn parse_b3_button_desc_xml_escaped(
                     }
                 }
             },
-            PhysicalButtonKind::Shift1 => {
-                unimplemented!("Physical parent button SHOULD NOT be Shift1")
-            }
-            PhysicalButtonKind::Shift2 => {
-                unimplemented!("Physical parent button SHOULD NOT be Shift2")
+            // A modifier which ALSO emits a joystick button when pressed (alone)
+            // cf `get_virtual_button_ids_from_info_or_user_desc`
+            PhysicalButtonKind::Shift1 | PhysicalButtonKind::Shift2 => {
+                VirtualButtonKind::Momentary(None)
             }
             PhysicalButtonKind::Pov { direction: _ } => {
                 unimplemented!("Physical parent button SHOULD NOT be Pov")
@@ -757,6 +796,7 @@ fn parse_b3_button_desc_xml_escaped(
 
 #[cfg(test)]
 mod tests {
+    use crate::vkb::vkb_lang;
     use crate::vkb::vkb_xml::VkbReport;
 
     use super::*;
@@ -787,6 +827,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::too_many_lines)]
     fn test_parse_b2_button_desc_xml_escaped() {
         // Here are all (?) the possible cases for a "b2" desc field:
         // "<b>#1 (E1) </b> / <b>#2  - Encoder 2/4</b>\r\nVirtual buttons : #61 / #62"
@@ -808,7 +849,7 @@ mod tests {
                 PhysicalButton::new(
                         1,
                         PhysicalButtonKind::Encoder
-                        , "(E1)".to_string(), "#2  - Encoder 2/4".to_string(), "".to_string(),
+                        , "(E1)".to_string(), "#2  - Encoder 2/4".to_string(), String::new(),
                 )
             ),
             (
@@ -816,7 +857,7 @@ mod tests {
                 PhysicalButton::new(
                         3,
                         PhysicalButtonKind::Momentary{ shift: None },
-                        "(E2)".to_string(), "- Button with momentary action".to_string(), "".to_string(),
+                        "(E2)".to_string(), "- Button with momentary action".to_string(), String::new(),
                 )
             ),
             (
@@ -824,7 +865,7 @@ mod tests {
                 PhysicalButton::new(
                         4,
                         PhysicalButtonKind::Momentary{ shift: None },
-                        "".to_string(), "- Button with momentary action".to_string(), "".to_string(),
+                        String::new(), "- Button with momentary action".to_string(), String::new(),
                 )
             ),
             (
@@ -832,7 +873,7 @@ mod tests {
                 PhysicalButton::new(
                         5,
                         PhysicalButtonKind::Tempo(TempoKind::Tempo2 { button_id_short: 5, button_id_long: 94 }),
-                         "(F3)".to_string(), "TEMPO ".to_string(),  "".to_string(),
+                         "(F3)".to_string(), "TEMPO ".to_string(),  String::new(),
                 )
             ),
             (
@@ -840,7 +881,7 @@ mod tests {
                 PhysicalButton::new(
                         5,
                         PhysicalButtonKind::Tempo(TempoKind::Tempo3 { button_id_short: 5, button_id_long: 94, button_id_double: 95 } ),  "(F3)".to_string(),
-                        "TEMPO ".to_string(), "".to_string(),
+                        "TEMPO ".to_string(), String::new(),
                 )
             ),
             (
@@ -850,7 +891,7 @@ mod tests {
                         PhysicalButtonKind::Momentary { shift: None },
                         "(Fire 2-nd stage)".to_string(),
                         "- Button with momentary action".to_string(),
-                        "".to_string(),
+                        String::new(),
                 )
             ),
             (
@@ -859,14 +900,14 @@ mod tests {
                         10,
                         PhysicalButtonKind::Momentary { shift: Some(ShiftKind::Shift12 { button_id_shift1: 64, button_id_shift2: 91 }) },
                         "(Fire 1-st stage)".to_string(),
-                         "- Button with momentary action".to_string(), "".to_string(),
+                         "- Button with momentary action".to_string(), String::new(),
                  )
             ),
             (
                 "<b>#11 (D1) </b><b> SHIFT1 </b>",
                 PhysicalButton::new(
                         11,
-                        PhysicalButtonKind::Shift1, "(D1)".to_string(), " SHIFT1 ".to_string(), "".to_string(),
+                        PhysicalButtonKind::Shift1, "(D1)".to_string(), " SHIFT1 ".to_string(), String::new(),
                 )
             ),
             (
@@ -876,7 +917,7 @@ mod tests {
                         PhysicalButtonKind::Momentary { shift: Some(ShiftKind::Shift12 { button_id_shift1: 13, button_id_shift2: 90 }) },
                         "(A2)".to_string(),
                         "- Button with momentary action".to_string(),
-                        "".to_string()
+                        String::new()
                 )
             ),
             (
@@ -886,7 +927,7 @@ mod tests {
                         PhysicalButtonKind::Pov { direction: "Down".to_string() },
                         "(A1 down)".to_string(),
                         "Point of view Switch".to_string(),
-                         "".to_string(),
+                         String::new(),
                 )
             ),
             (
@@ -896,7 +937,7 @@ mod tests {
                         PhysicalButtonKind::Momentary { shift: Some(ShiftKind::Shift1 { button_id_shift1: 37 }) },
                         "(Rapid fire forward)".to_string(),
                         "- Button with momentary action".to_string(),
-                        "".to_string(),
+                        String::new(),
                 )
             ),
             (
@@ -904,7 +945,7 @@ mod tests {
                 PhysicalButton::new(
                         37,
                         PhysicalButtonKind::Undefined
-                        , "".to_string(), " No defined function".to_string(), "".to_string(),
+                        , String::new(), " No defined function".to_string(), String::new(),
                 )
             ),
             (
@@ -912,7 +953,7 @@ mod tests {
                 PhysicalButton::new(
                         9,
                         PhysicalButtonKind::Momentary { shift: Some(ShiftKind::Shift12 { button_id_shift1: 63, button_id_shift2: 92 }) },
-                        "(Fire 2-nd stage)".to_string(), "- Button with momentary action".to_string(), "".to_string(),
+                        "(Fire 2-nd stage)".to_string(), "- Button with momentary action".to_string(), String::new(),
                 )
             ),
         ];
@@ -921,7 +962,7 @@ mod tests {
         // This seem to mean we need to combine this b2 with the one from the previous page0?
 
         for (input, expected_result) in test_inputs_vs_expected_results {
-            let button = parse_b2_button_desc_xml_escaped(input).unwrap();
+            let button = parse_b2_button_desc_xml_escaped(input, &vkb_lang::ENGLISH).unwrap();
             assert_eq!(button, expected_result);
         }
     }
@@ -930,7 +971,7 @@ mod tests {
     fn test_parse_b3_button_desc_xml_escaped() {
         let test_inputs_vs_expected_results = vec![
             (
-                r#"<b>#61 </b> Joystick button : #61"#,
+                r"<b>#61 </b> Joystick button : #61",
                 VirtualButton {
                     id: 61,
                     kind: VirtualButtonKind::Momentary(None),
@@ -939,7 +980,7 @@ mod tests {
             (
                 // REALLY IMPORTANT to have proper duplication detection:
                 // in this case the game WILL see "button 53" NOT "button 7"
-                r#"<b>#7 </b> Joystick button : #53"#,
+                r"<b>#7 </b> Joystick button : #53",
                 VirtualButton {
                     id: 53,
                     kind: VirtualButtonKind::Momentary(None),
@@ -955,11 +996,37 @@ mod tests {
             String::new(),
         );
         for (input, expected_result) in test_inputs_vs_expected_results {
-            let button = parse_b3_button_desc_xml_escaped(input, &parent).unwrap();
+            let button =
+                parse_b3_button_desc_xml_escaped(input, &parent, &vkb_lang::ENGLISH).unwrap();
             assert_eq!(button, expected_result);
         }
     }
 
+    #[test]
+    fn test_parse_b3_button_desc_xml_escaped_shift_parent() {
+        // A SHIFT1 modifier which ALSO emits a joystick button
+        let parent = PhysicalButton::new(
+            11,
+            PhysicalButtonKind::Shift1,
+            "(D1)".to_string(),
+            " SHIFT1 ".to_string(),
+            String::new(),
+        );
+
+        assert_eq!(
+            parse_b3_button_desc_xml_escaped(
+                "<b>#11 </b> Joystick button : #120",
+                &parent,
+                &vkb_lang::ENGLISH
+            )
+            .unwrap(),
+            VirtualButton {
+                id: 120,
+                kind: VirtualButtonKind::Momentary(None),
+            }
+        );
+    }
+
     #[test]
     fn test_buttons_try_from_vkb_report_simplified() {
         let vkb_report = VkbReport::new(
@@ -967,12 +1034,19 @@ mod tests {
                 env!("CARGO_MANIFEST_DIR"),
                 "/tests/data/vkb_report_simplified.fp3"
             )
-            .into(),
+            .as_ref(),
         )
         .unwrap();
 
         let vkb_buttons = vkb_report.get_all_buttons();
-        assert!(vkb_buttons.len() == 8);
+        assert_eq!(
+            vkb_buttons.len()
+                + vkb_buttons
+                    .iter()
+                    .map(|b2| b2.children.len())
+                    .sum::<usize>(),
+            8
+        );
 
         // TODO(re-add)? is it worth it?
         // for vkb_button in vkb_buttons {
@@ -991,7 +1065,7 @@ mod tests {
                 env!("CARGO_MANIFEST_DIR"),
                 "/tests/data/vkb_report_simplified.fp3"
             )
-            .into(),
+            .as_ref(),
         )
         .unwrap();
 
@@ -1001,10 +1075,32 @@ mod tests {
     #[test]
     fn test_button_map_vkb_report_full() {
         let vkb_report = VkbReport::new(
-            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/vkb_report_L.fp3").into(),
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/vkb_report_L.fp3").as_ref(),
         )
         .unwrap();
 
         assert!(JoystickButtonsMapping::try_from(vkb_report).is_ok());
     }
+
+    #[test]
+    fn test_get_free_virtual_buttons() {
+        let vkb_report = VkbReport::new(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/vkb_report_L.fp3").as_ref(),
+        )
+        .unwrap();
+        let mut joysticks_mappings = JoystickButtonsMapping::try_from(vkb_report).unwrap();
+
+        // "Number of logical buttons : 128" -> 128 IS a valid ID
+        assert_eq!(joysticks_mappings.logical_buttons_count, Some(128));
+        let free_virtual_buttons = joysticks_mappings.get_free_virtual_buttons();
+        assert_eq!(free_virtual_buttons.last(), Some(&128));
+        // "#37 No defined function": NO child, but still a physical line
+        assert!(!free_virtual_buttons.contains(&37));
+
+        joysticks_mappings.logical_buttons_count = Some(100);
+        assert!(joysticks_mappings
+            .get_free_virtual_buttons()
+            .iter()
+            .all(|virtual_button_id| *virtual_button_id <= 100));
+    }
 }
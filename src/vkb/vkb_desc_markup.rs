@@ -0,0 +1,288 @@
+//! The descriptions in the report ("m7" and "m9" fields) are NOT plain text, they contain a small
+//! html-like markup eg:
+//! "<b>#12 (A2) </b><b>- Button with momentary action</b>\r\nVirtual button with SHIFT1 = 13"
+//! "<font color="#000000">Virtual button with SHIFT1 = 63\r\nVirtual button with SHIFT2 = 92"
+//!
+//! In practice only `<b>` and `<font>` are used so we tokenize it ourselves instead of pulling a full html parser.
+//! The result is a small tree, good enough for what `vkb_button.rs` needs:
+//! - all the `<b>` elements, in order
+//! - the text nodes following the first `<b>`
+//!
+//! NOTE: `<font>` only changes the color, so it is "transparent": its children are moved into its parent.
+//!
+
+/// One node of a parsed description
+#[derive(Debug, PartialEq)]
+pub(super) enum MarkupNode {
+    /// CR/LF normalized to "\n" and entities decoded
+    Text(String),
+    Element {
+        name: String,
+        children: Vec<MarkupNode>,
+    },
+}
+
+impl MarkupNode {
+    /// Concatenation of all the descendant text nodes
+    /// eg "<b>#12 (A2) </b>" -> "#12 (A2) "
+    pub(super) fn inner_text(&self) -> String {
+        match self {
+            MarkupNode::Text(text) => text.clone(),
+            MarkupNode::Element { children, .. } => {
+                children.iter().map(MarkupNode::inner_text).collect()
+            }
+        }
+    }
+
+    fn is_element(&self, element_name: &str) -> bool {
+        matches!(self, MarkupNode::Element { name, .. } if name == element_name)
+    }
+}
+
+/// Tags whose content is kept but the tag itself is dropped, cf mod doc
+const TRANSPARENT_ELEMENTS: [&str; 1] = ["font"];
+
+/// Parse a description eg "<b>#1 (E1) </b> / <b>#2  - Encoder 2/4</b>\r\nVirtual buttons : #61 / #62"
+///
+/// This NEVER fails: unknown/unclosed tags are closed at the end, stray "</x>" are ignored,
+/// and a "<" without a matching ">" is kept as text.
+pub(super) fn parse_markup(desc: &str) -> Vec<MarkupNode> {
+    // stack of the currently open elements; the bottom one is the root (no name)
+    let mut stack: Vec<(String, Vec<MarkupNode>)> = vec![(String::new(), vec![])];
+    let mut text = String::new();
+
+    let mut remaining = desc;
+    while !remaining.is_empty() {
+        let tag = remaining
+            .strip_prefix('<')
+            .and_then(|after_lt| after_lt.split_once('>'));
+
+        let Some((tag_content, after_tag)) = tag else {
+            // plain text: up to the next "<" (or the end)
+            let next_lt = remaining
+                .char_indices()
+                .skip(1)
+                .find(|(_, c)| *c == '<')
+                .map_or(remaining.len(), |(pos, _)| pos);
+            text.push_str(&remaining[..next_lt]);
+            remaining = &remaining[next_lt..];
+            continue;
+        };
+        remaining = after_tag;
+
+        flush_text(&mut stack, &mut text);
+
+        if let Some(closing_name) = tag_content.strip_prefix('/') {
+            let closing_name = closing_name.trim().to_lowercase();
+            // Only close if it was opened; else a stray closing tag
+            if stack.iter().skip(1).any(|(name, _)| *name == closing_name) {
+                loop {
+                    let is_target = stack.last().is_some_and(|(name, _)| *name == closing_name);
+                    close_element(&mut stack);
+                    if is_target {
+                        break;
+                    }
+                }
+            }
+        } else {
+            let is_self_closing = tag_content.ends_with('/');
+            let name = tag_content
+                .trim_end_matches('/')
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_lowercase();
+            stack.push((name, vec![]));
+            if is_self_closing {
+                close_element(&mut stack);
+            }
+        }
+    }
+
+    flush_text(&mut stack, &mut text);
+    while stack.len() > 1 {
+        close_element(&mut stack);
+    }
+
+    stack
+        .pop()
+        .map(|(_, children)| children)
+        .unwrap_or_default()
+}
+
+/// All the elements named `name` in document order (pre-order, like a css selector)
+pub(super) fn select<'a>(nodes: &'a [MarkupNode], name: &str) -> Vec<&'a MarkupNode> {
+    let mut selected = vec![];
+    for node in nodes {
+        if node.is_element(name) {
+            selected.push(node);
+        }
+        if let MarkupNode::Element { children, .. } = node {
+            selected.extend(select(children, name));
+        }
+    }
+    selected
+}
+
+/// All the text nodes which are the NEXT siblings of the first element named `name`
+/// eg for `<b>#18 (A1 down) </b> <b>Point of view Switch</b> POV1  Down` and "b" -> `[" ", " POV1  Down"]`
+pub(super) fn text_siblings_after_first<'a>(nodes: &'a [MarkupNode], name: &str) -> Vec<&'a str> {
+    if let Some(position) = nodes.iter().position(|node| node.is_element(name)) {
+        return nodes[position + 1..]
+            .iter()
+            .filter_map(|node| match node {
+                MarkupNode::Text(text) => Some(text.as_str()),
+                MarkupNode::Element { .. } => None,
+            })
+            .collect();
+    }
+
+    nodes
+        .iter()
+        .find_map(|node| match node {
+            MarkupNode::Element { children, .. } if !select(children, name).is_empty() => {
+                Some(text_siblings_after_first(children, name))
+            }
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+fn flush_text(stack: &mut [(String, Vec<MarkupNode>)], text: &mut String) {
+    if text.is_empty() {
+        return;
+    }
+    let decoded = decode_entities(&text.replace("\r\n", "\n").replace('\r', "\n"));
+    text.clear();
+    if let Some((_, children)) = stack.last_mut() {
+        push_merging_text(children, MarkupNode::Text(decoded));
+    }
+}
+
+fn close_element(stack: &mut Vec<(String, Vec<MarkupNode>)>) {
+    let Some((name, children)) = stack.pop() else {
+        return;
+    };
+    let Some((_, parent_children)) = stack.last_mut() else {
+        return;
+    };
+
+    if TRANSPARENT_ELEMENTS.contains(&name.as_str()) {
+        for child in children {
+            push_merging_text(parent_children, child);
+        }
+    } else {
+        parent_children.push(MarkupNode::Element { name, children });
+    }
+}
+
+/// Push a node; two consecutive text nodes are merged into one
+fn push_merging_text(nodes: &mut Vec<MarkupNode>, node: MarkupNode) {
+    if let (Some(MarkupNode::Text(previous)), MarkupNode::Text(text)) = (nodes.last_mut(), &node) {
+        previous.push_str(text);
+        return;
+    }
+    nodes.push(node);
+}
+
+/// Decode the few entities that can appear in a description; anything unknown is kept as-is
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut remaining = text;
+    while let Some(amp) = remaining.find('&') {
+        decoded.push_str(&remaining[..amp]);
+        remaining = &remaining[amp..];
+
+        let entity = remaining[1..].find(';').map(|end| &remaining[1..=end]);
+        let replacement = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => entity
+                .strip_prefix('#')
+                .and_then(|code| match code.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => code.parse().ok(),
+                })
+                .and_then(char::from_u32),
+        });
+
+        if let (Some(entity), Some(replacement)) = (entity, replacement) {
+            decoded.push(replacement);
+            remaining = &remaining[entity.len() + 2..];
+        } else {
+            decoded.push('&');
+            remaining = &remaining[1..];
+        }
+    }
+    decoded.push_str(remaining);
+
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(text: &str) -> MarkupNode {
+        MarkupNode::Text(text.to_string())
+    }
+
+    fn b(inner: &str) -> MarkupNode {
+        MarkupNode::Element {
+            name: "b".to_string(),
+            children: vec![text(inner)],
+        }
+    }
+
+    #[test]
+    fn test_parse_markup() {
+        let test_inputs_vs_expected_results = vec![
+            (
+                "<b>#1 (E1) </b> / <b>#2  - Encoder 2/4</b>\r\nVirtual buttons : #61 / #62",
+                vec![
+                    b("#1 (E1) "),
+                    text(" / "),
+                    b("#2  - Encoder 2/4"),
+                    text("\nVirtual buttons : #61 / #62"),
+                ],
+            ),
+            (
+                "<b>#39 </b><b> No defined function</b>",
+                vec![b("#39 "), b(" No defined function")],
+            ),
+            (
+                "<b>#9 </b><b>- Button</b>\r\n<font color=\"#000000\">SHIFT1 = 63\r\nSHIFT2 = 92</font>",
+                vec![b("#9 "), b("- Button"), text("\nSHIFT1 = 63\nSHIFT2 = 92")],
+            ),
+            // unclosed/stray tags, entities, "<" alone
+            (
+                "<b>a &amp; b</i> 1 < 2",
+                vec![b("a & b 1 < 2")],
+            ),
+        ];
+
+        for (input, expected_result) in test_inputs_vs_expected_results {
+            assert_eq!(parse_markup(input), expected_result, "input: {input:?}");
+        }
+    }
+
+    #[test]
+    fn test_select_and_text_siblings() {
+        let nodes = parse_markup("<b>#18 (A1 down) </b> <b>Point of view Switch</b> POV1  Down");
+
+        let b_nodes: Vec<_> = select(&nodes, "b")
+            .into_iter()
+            .map(MarkupNode::inner_text)
+            .collect();
+        assert_eq!(b_nodes, vec!["#18 (A1 down) ", "Point of view Switch"]);
+
+        assert_eq!(
+            text_siblings_after_first(&nodes, "b"),
+            vec![" ", " POV1  Down"]
+        );
+    }
+}
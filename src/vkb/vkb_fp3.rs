@@ -0,0 +1,168 @@
+//! `FastReport` can save a prepared report (.fp3) either:
+//! - as plain xml; this is what `VKBDevCfg` does by default
+//! - or "compressed" ie the same xml, gzip'ed
+//!
+//! This mod sniffs the file content (NOT the extension!) and returns the xml string ready for `vkb_xml.rs`
+//!
+
+use std::io::Read;
+use std::path::Path;
+
+use crate::Error;
+
+/// What we found at the start of the .fp3 file
+#[derive(Debug, PartialEq)]
+enum Fp3Format {
+    /// "<?xml ..." or directly "<preparedreport>"; possibly with a BOM
+    Xml,
+    /// magic 1F 8B; `FastReport` "Compressed" option
+    Gzip,
+    /// magic 78 01/5E/9C/DA; raw zlib stream
+    Zlib,
+    /// magic "PK"; eg a report zipped by hand, or an export to another format
+    Zip,
+    /// anything else eg a binary export
+    Unknown,
+}
+
+impl Fp3Format {
+    fn sniff(bytes: &[u8]) -> Self {
+        let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+        let first_non_blank = bytes.iter().find(|b| !b.is_ascii_whitespace());
+
+        match bytes {
+            [0x1F, 0x8B, ..] => Fp3Format::Gzip,
+            [0x78, 0x01 | 0x5E | 0x9C | 0xDA, ..] => Fp3Format::Zlib,
+            [b'P', b'K', 0x03, 0x04, ..] => Fp3Format::Zip,
+            _ if first_non_blank == Some(&b'<') => Fp3Format::Xml,
+            _ => Fp3Format::Unknown,
+        }
+    }
+}
+
+/// Read a .fp3 report, decompress it if needed, and return the xml
+///
+/// # Errors
+/// - `Error::ReadError` if the file could not be read or decompressed
+/// - `Error::UnsupportedReportFormat` if the content is neither xml nor a compressed xml
+pub(super) fn read_fp3_to_string(fp3_path: &Path) -> Result<String, Error> {
+    let bytes = std::fs::read(fp3_path).map_err(|err| Error::ReadError { err })?;
+
+    let xml_bytes = match Fp3Format::sniff(&bytes) {
+        Fp3Format::Xml => bytes,
+        Fp3Format::Gzip => {
+            log::info!(
+                "read_fp3_to_string: {} is gzip compressed",
+                fp3_path.display()
+            );
+            decompress(flate2::read::GzDecoder::new(bytes.as_slice()))?
+        }
+        Fp3Format::Zlib => {
+            log::info!(
+                "read_fp3_to_string: {} is zlib compressed",
+                fp3_path.display()
+            );
+            decompress(flate2::read::ZlibDecoder::new(bytes.as_slice()))?
+        }
+        Fp3Format::Zip => {
+            return Err(Error::UnsupportedReportFormat(format!(
+                "{} is a zip archive; extract the .fp3 report from it first",
+                fp3_path.display()
+            )))
+        }
+        Fp3Format::Unknown => {
+            return Err(Error::UnsupportedReportFormat(format!(
+                "{} is not a FastReport xml report; in VKBDevCfg use \"save\" (not \"export\")",
+                fp3_path.display()
+            )))
+        }
+    };
+
+    // CHECK: a compressed container MUST contain the xml; not eg another binary format
+    if Fp3Format::sniff(&xml_bytes) != Fp3Format::Xml {
+        return Err(Error::UnsupportedReportFormat(format!(
+            "{}: decompressed content is not xml",
+            fp3_path.display()
+        )));
+    }
+
+    String::from_utf8(xml_bytes).map_err(|_err| {
+        Error::UnsupportedReportFormat(format!("{}: report is not utf-8", fp3_path.display()))
+    })
+}
+
+fn decompress(mut decoder: impl Read) -> Result<Vec<u8>, Error> {
+    let mut xml_bytes = vec![];
+    decoder
+        .read_to_end(&mut xml_bytes)
+        .map_err(|err| Error::ReadError { err })?;
+
+    Ok(xml_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    const SIMPLIFIED_REPORT: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/data/vkb_report_simplified.fp3"
+    );
+
+    fn write_tmp(name: &str, content: &[u8]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_sniff() {
+        assert_eq!(Fp3Format::sniff(b"<?xml version"), Fp3Format::Xml);
+        assert_eq!(
+            Fp3Format::sniff(b"\xEF\xBB\xBF\r\n<preparedreport>"),
+            Fp3Format::Xml
+        );
+        assert_eq!(Fp3Format::sniff(&[0x1F, 0x8B, 0x08]), Fp3Format::Gzip);
+        assert_eq!(Fp3Format::sniff(&[0x78, 0x9C, 0x00]), Fp3Format::Zlib);
+        assert_eq!(Fp3Format::sniff(b"PK\x03\x04"), Fp3Format::Zip);
+        assert_eq!(Fp3Format::sniff(b"TPF0"), Fp3Format::Unknown);
+    }
+
+    #[test]
+    fn test_read_fp3_gzip() {
+        let xml = std::fs::read(SIMPLIFIED_REPORT).unwrap();
+        let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(&xml).unwrap();
+        let path = write_tmp("sc_keymap_test_report_gzip.fp3", &encoder.finish().unwrap());
+
+        assert_eq!(
+            read_fp3_to_string(&path).unwrap().as_bytes(),
+            xml.as_slice()
+        );
+    }
+
+    #[test]
+    fn test_read_fp3_zlib() {
+        let xml = std::fs::read(SIMPLIFIED_REPORT).unwrap();
+        let mut encoder = flate2::write::ZlibEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(&xml).unwrap();
+        let path = write_tmp("sc_keymap_test_report_zlib.fp3", &encoder.finish().unwrap());
+
+        assert_eq!(
+            read_fp3_to_string(&path).unwrap().as_bytes(),
+            xml.as_slice()
+        );
+    }
+
+    #[test]
+    fn test_read_fp3_unsupported() {
+        let path = write_tmp("sc_keymap_test_report_binary.fp3", b"TPF0\x00\x01\x02");
+
+        assert!(matches!(
+            read_fp3_to_string(&path),
+            Err(Error::UnsupportedReportFormat(_))
+        ));
+    }
+}
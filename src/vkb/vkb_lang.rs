@@ -0,0 +1,150 @@
+//! The `VKBDevCfg` report is generated in the UI language of the configurator.
+//! The xml STRUCTURE (b2/b3/m7/m9...) does not change, but every description does:
+//! "Button with momentary action", "Virtual button with SHIFT1 =", "Joystick button : #", etc.
+//!
+//! This mod contains the "grammar" ie all the literals matched by `vkb_button.rs`, one table per language.
+//! The language is auto-detected from the report header cf `VkbReportLanguage::detect`.
+//!
+//! NOTE: ONLY English for now; it is the one covered by the reports in `tests/data`.
+//! A new language MUST come with a real report in that language in `tests/data`; the literals can NOT be guessed.
+//! Until then a report in another language is rejected upfront cf `Error::UnsupportedReportLanguage`.
+//! If a report fails to parse with `Error::UnexpectedXmlDesc` check the literals below first!
+//!
+
+use crate::Error;
+
+/// All the literals needed to parse the b2/b3 descriptions of a given report
+#[derive(Debug, PartialEq)]
+pub(crate) struct VkbReportLanguage {
+    pub(crate) name: &'static str,
+    /// Something ONLY found in the header (b1.m2) of a report in this language
+    /// eg "Number of logical buttons : 128"
+    pub(crate) header_marker: &'static str,
+    /// eg "<b>- Button with momentary action</b>"
+    pub(crate) momentary: &'static str,
+    /// eg "Virtual button with SHIFT1 = 50"
+    pub(crate) virtual_button_with_shift1: &'static str,
+    /// eg "Virtual button with SHIFT2 = 51"
+    pub(crate) virtual_button_with_shift2: &'static str,
+    /// eg "<b> SHIFT1 </b>"
+    pub(crate) shift1: &'static str,
+    /// eg "<b> SHIFT2 alternate action</b>"
+    pub(crate) shift2: &'static str,
+    /// eg "<b>TEMPO </b>"
+    pub(crate) tempo: &'static str,
+    /// eg "Virtual button Short #5"
+    pub(crate) tempo_short: &'static str,
+    /// eg "Virtual button Long #94"
+    pub(crate) tempo_long: &'static str,
+    /// eg "Virtual button Double Short #95"
+    pub(crate) tempo_double: &'static str,
+    /// eg "<b>#2  - Encoder 2/4</b>"
+    pub(crate) encoder: &'static str,
+    /// eg "<b>Point of view Switch</b> POV1  Down"
+    pub(crate) pov: &'static str,
+    /// eg "<b> No defined function</b>"
+    pub(crate) undefined: &'static str,
+    /// eg "<b>Microstick Mode Switch </b>"
+    pub(crate) microstick_mode_switch: &'static str,
+    /// b3 only: eg "<b>#5 </b> Joystick button : #11"
+    pub(crate) joystick_button: &'static str,
+}
+
+pub(crate) const ENGLISH: VkbReportLanguage = VkbReportLanguage {
+    name: "English",
+    header_marker: "Number of logical buttons",
+    momentary: "Button with momentary action",
+    virtual_button_with_shift1: "Virtual button with SHIFT1 = ",
+    virtual_button_with_shift2: "Virtual button with SHIFT2 = ",
+    shift1: " SHIFT1 ",
+    shift2: " SHIFT2 alternate action",
+    tempo: "TEMPO",
+    tempo_short: "Virtual button Short #",
+    tempo_long: "Virtual button Long #",
+    tempo_double: "Virtual button Double Short #",
+    encoder: "Encoder",
+    pov: "Point of view Switch",
+    undefined: "No defined function",
+    microstick_mode_switch: "Microstick Mode Switch",
+    joystick_button: "Joystick button : #",
+};
+
+/// All the supported languages; the order only matters for `detect`
+pub(crate) const ALL_LANGUAGES: [&VkbReportLanguage; 1] = [&ENGLISH];
+
+impl VkbReportLanguage {
+    /// Return the "Number of logical buttons" from the report header, if any
+    /// eg "Number of logical buttons : 128" -> Some(128)
+    pub(crate) fn parse_header_logical_buttons_count(&self, header: Option<&str>) -> Option<u8> {
+        header?
+            .lines()
+            .find(|line| line.contains(self.header_marker))?
+            .rsplit(':')
+            .next()?
+            .trim()
+            .parse()
+            .ok()
+    }
+
+    /// Find the language of a report from its header (ie the "b1.m2" field)
+    /// Fallback to English ONLY if the header is missing (it is only on the first page0): nothing to detect from.
+    ///
+    /// # Errors
+    /// - `Error::UnsupportedReportLanguage` if the header is NOT in any of `ALL_LANGUAGES`; NOT guessed, else the
+    ///   report would fail later with a confusing `Error::UnexpectedXmlDesc`
+    pub(crate) fn detect(header: Option<&str>) -> Result<&'static VkbReportLanguage, Error> {
+        let Some(header) = header else {
+            log::warn!(
+                "VkbReportLanguage: no header in report; assuming {}",
+                ENGLISH.name
+            );
+            return Ok(&ENGLISH);
+        };
+
+        let lang = ALL_LANGUAGES
+            .into_iter()
+            .find(|lang| header.contains(lang.header_marker))
+            .ok_or_else(|| Error::UnsupportedReportLanguage(header.to_string()))?;
+        log::info!("VkbReportLanguage: detected {}", lang.name);
+
+        Ok(lang)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(
+            VkbReportLanguage::detect(Some(
+                "Report generated by VKB Device Configurator v0.92.51\r\nNumber of logical buttons : 128"
+            ))
+            .unwrap(),
+            &ENGLISH
+        );
+        // unknown: NOT guessed
+        assert!(matches!(
+            VkbReportLanguage::detect(Some("Anzahl der logischen Tasten : 128")),
+            Err(Error::UnsupportedReportLanguage(_))
+        ));
+        // no header: nothing to detect from
+        assert_eq!(VkbReportLanguage::detect(None).unwrap(), &ENGLISH);
+    }
+
+    #[test]
+    fn test_parse_header_logical_buttons_count() {
+        assert_eq!(
+            ENGLISH.parse_header_logical_buttons_count(Some(
+                "Controller : VKB NJoy32 XT2 PRO  v2.165\r\n \r\nNumber of logical buttons : 128\r\nNumber of HATs : 1"
+            )),
+            Some(128)
+        );
+        assert_eq!(
+            ENGLISH.parse_header_logical_buttons_count(Some("Number of logical buttons : 64")),
+            Some(64)
+        );
+        assert_eq!(ENGLISH.parse_header_logical_buttons_count(None), None);
+    }
+}
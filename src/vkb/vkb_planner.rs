@@ -0,0 +1,603 @@
+//! Propose concrete virtual button IDs when adding SHIFT1/SHIFT2/TEMPO lines to existing physical buttons
+//! eg "add SHIFT1 and SHIFT2 to (A2), make (F1) a Tempo3"
+//!
+//! The IDs are picked from `JoystickButtonsMapping::get_free_virtual_buttons` ie following the
+//! "physical line number" rule, and:
+//! - NOT bound in the game layout (if given), by ANY rebind of an action; that way the existing keybinds are not messed up
+//! - preferably as a contiguous block for a given physical button
+//!
+//! The result is a step-by-step list to enter in `VKBDevCfg`, one line per field to change.
+//!
+
+use crate::button::{PhysicalButton, PhysicalButtonKind, ShiftKind, TempoKind, VirtualButtonKind};
+use crate::sc::parse_keybind_xml::GameButtonsMapping;
+use crate::Error;
+
+use super::vkb_button::JoystickButtonsMapping;
+use super::vkb_lang;
+
+#[derive(Debug, PartialEq)]
+enum PlannedTempo {
+    Tempo2,
+    Tempo3,
+}
+
+/// One clause of the user request
+#[derive(Debug, PartialEq)]
+enum PlanRequest {
+    /// eg "add SHIFT1 and SHIFT2 to (A2)"
+    AddShift {
+        info_or_user_desc: String,
+        shift1: bool,
+        shift2: bool,
+    },
+    /// eg "make (F1) a Tempo3"
+    MakeTempo {
+        info_or_user_desc: String,
+        tempo: PlannedTempo,
+    },
+}
+
+/// One line of the final list eg
+/// "#12 (A2) : set "Virtual button with SHIFT1" to #100"
+#[derive(Debug, PartialEq)]
+pub struct PlanStep {
+    pub physical_button_id: u8,
+    /// eg "(A2)" or "(A2) "Thumb red button""
+    pub physical_label: String,
+    /// What to do in `VKBDevCfg`
+    pub instruction: String,
+    /// `None` when nothing has to be allocated eg "change the type to TEMPO"
+    pub virtual_button_id: Option<u8>,
+}
+
+impl core::fmt::Display for PlanStep {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "#{} {} : {}",
+            self.physical_button_id, self.physical_label, self.instruction
+        )
+    }
+}
+
+/// Parse a request eg "add SHIFT1 and SHIFT2 to (A2), make (F1) a Tempo3"
+/// Clauses are separated by "," or ";" and are case-insensitive, EXCEPT for the button labels.
+fn parse_plan_request(request: &str) -> Result<Vec<PlanRequest>, Error> {
+    let mut plan_requests = vec![];
+
+    for clause in request
+        .split([',', ';'])
+        .map(str::trim)
+        .filter(|clause| !clause.is_empty())
+    {
+        let invalid = || Error::InvalidPlanRequest(clause.to_string());
+        let (verb, rest) = clause.split_once(' ').ok_or_else(invalid)?;
+
+        match verb.to_lowercase().as_str() {
+            "add" => {
+                let (what, info_or_user_desc) = rest.rsplit_once(" to ").ok_or_else(invalid)?;
+                let what = what.to_lowercase();
+                let mut shift1 = false;
+                let mut shift2 = false;
+                for token in what
+                    .split([' ', '+', '&'])
+                    .filter(|token| !token.is_empty() && *token != "and")
+                {
+                    match token {
+                        "shift1" => shift1 = true,
+                        "shift2" => shift2 = true,
+                        _ => return Err(invalid()),
+                    }
+                }
+                if !shift1 && !shift2 {
+                    return Err(invalid());
+                }
+                plan_requests.push(PlanRequest::AddShift {
+                    info_or_user_desc: info_or_user_desc.trim().to_string(),
+                    shift1,
+                    shift2,
+                });
+            }
+            "make" => {
+                let (info_or_user_desc, tempo) = rest
+                    .rsplit_once(" a ")
+                    .or_else(|| rest.rsplit_once(" an "))
+                    .ok_or_else(invalid)?;
+                let tempo = match tempo.trim().to_lowercase().as_str() {
+                    "tempo2" => PlannedTempo::Tempo2,
+                    "tempo3" => PlannedTempo::Tempo3,
+                    _ => return Err(invalid()),
+                };
+                plan_requests.push(PlanRequest::MakeTempo {
+                    info_or_user_desc: info_or_user_desc.trim().to_string(),
+                    tempo,
+                });
+            }
+            _ => return Err(invalid()),
+        }
+    }
+
+    Ok(plan_requests)
+}
+
+/// Pick `nb_needed` IDs from `free_virtual_buttons` (sorted), preferably contiguous, and remove them
+fn allocate(free_virtual_buttons: &mut Vec<u8>, nb_needed: usize) -> Result<Vec<u8>, Error> {
+    if nb_needed > free_virtual_buttons.len() {
+        return Err(Error::NotEnoughFreeVirtualButtons {
+            needed: nb_needed,
+            available: free_virtual_buttons.len(),
+        });
+    }
+
+    // First block of `nb_needed` consecutive IDs, else the lowest ones
+    let start = free_virtual_buttons
+        .windows(nb_needed.max(1))
+        .position(|window| {
+            window
+                .windows(2)
+                .all(|pair| u16::from(pair[0]) + 1 == u16::from(pair[1]))
+        })
+        .unwrap_or(0);
+    let allocated: Vec<u8> = free_virtual_buttons
+        .drain(start..start + nb_needed)
+        .collect();
+
+    Ok(allocated)
+}
+
+fn get_physical_label(physical_button: &PhysicalButton) -> String {
+    if physical_button.get_user_desc().is_empty() {
+        physical_button.get_info().clone()
+    } else {
+        format!(
+            "{} \"{}\"",
+            physical_button.get_info(),
+            physical_button.get_user_desc()
+        )
+    }
+}
+
+/// eg "Virtual button with SHIFT1 = " -> "Virtual button with SHIFT1"
+fn field_name(literal: &str) -> &str {
+    literal.trim_end_matches(['=', '#', ' '])
+}
+
+/// Propose the virtual button IDs for `request` cf mod doc
+///
+/// params:
+/// - `game_buttons_mapping` and `game_device_id`: if given, the IDs bound in-game are NOT proposed
+///
+/// # Errors
+/// - `Error::InvalidPlanRequest` if the request could not be parsed, or is not applicable to the button
+/// - `Error::ButtonNotFound` if a label does not match any physical button
+/// - `Error::NotEnoughFreeVirtualButtons`
+///
+#[allow(clippy::too_many_lines)]
+pub fn plan_virtual_buttons(
+    joysticks_mappings: &JoystickButtonsMapping,
+    game_buttons_mapping: Option<&GameButtonsMapping>,
+    game_device_id: Option<u8>,
+    request: &str,
+) -> Result<Vec<PlanStep>, Error> {
+    // NOTE: the steps use the English field names, whatever the language of the report
+    let lang = &vkb_lang::ENGLISH;
+
+    let mut free_virtual_buttons: Vec<u8> = joysticks_mappings
+        .get_free_virtual_buttons()
+        .into_iter()
+        .filter(|virtual_button_id| {
+            match (game_buttons_mapping, game_device_id) {
+                (Some(game_buttons_mapping), Some(game_device_id)) => {
+                    // the 2nd, 3rd, etc rebinds of an action count too
+                    let rebinds = game_buttons_mapping
+                        .get_rebinds_from_virtual_button_id(*virtual_button_id, game_device_id);
+                    if !rebinds.is_empty() {
+                        let actions: Vec<&String> =
+                            rebinds.iter().map(|(action_name, _)| *action_name).collect();
+                        log::info!(
+                            "plan_virtual_buttons: {virtual_button_id} is free but bound in-game to {actions:?}"
+                        );
+                    }
+                    rebinds.is_empty()
+                }
+                _ => true,
+            }
+        })
+        .collect();
+    if game_buttons_mapping.is_none() || game_device_id.is_none() {
+        log::warn!("plan_virtual_buttons: no game mapping/device id; the proposed IDs MAY be bound in-game!");
+    }
+
+    let mut steps = vec![];
+
+    for plan_request in parse_plan_request(request)? {
+        let info_or_user_desc = match &plan_request {
+            PlanRequest::AddShift {
+                info_or_user_desc, ..
+            }
+            | PlanRequest::MakeTempo {
+                info_or_user_desc, ..
+            } => info_or_user_desc.clone(),
+        };
+        let physical_button = joysticks_mappings
+            .find_physical_button(&info_or_user_desc)
+            .ok_or_else(|| Error::ButtonNotFound {
+                info_or_user_desc: info_or_user_desc.clone(),
+            })?;
+        let mut push_step = |instruction: String, virtual_button_id: Option<u8>| {
+            steps.push(PlanStep {
+                physical_button_id: *physical_button.get_id(),
+                physical_label: get_physical_label(physical_button),
+                instruction,
+                virtual_button_id,
+            });
+        };
+        let not_applicable = |why: &str| {
+            Error::InvalidPlanRequest(format!(
+                "{info_or_user_desc} is {:?}: {why}",
+                physical_button.get_kind()
+            ))
+        };
+
+        match plan_request {
+            PlanRequest::AddShift { shift1, shift2, .. } => {
+                let PhysicalButtonKind::Momentary { shift } = physical_button.get_kind() else {
+                    return Err(not_applicable("SHIFT1/SHIFT2 need a momentary button"));
+                };
+                let (has_shift1, has_shift2) = match shift {
+                    None => (false, false),
+                    Some(ShiftKind::Shift1 { .. }) => (true, false),
+                    Some(ShiftKind::Shift2 { .. }) => (false, true),
+                    Some(ShiftKind::Shift12 { .. }) => (true, true),
+                };
+
+                let fields: Vec<&str> = [
+                    (shift1 && !has_shift1, lang.virtual_button_with_shift1),
+                    (shift2 && !has_shift2, lang.virtual_button_with_shift2),
+                ]
+                .into_iter()
+                .filter_map(|(is_needed, literal)| is_needed.then_some(field_name(literal)))
+                .collect();
+                if fields.is_empty() {
+                    push_step("nothing to do, already set".to_string(), None);
+                    continue;
+                }
+
+                for (field, virtual_button_id) in fields
+                    .iter()
+                    .zip(allocate(&mut free_virtual_buttons, fields.len())?)
+                {
+                    push_step(
+                        format!("set \"{field}\" to #{virtual_button_id}"),
+                        Some(virtual_button_id),
+                    );
+                }
+            }
+            PlanRequest::MakeTempo { tempo, .. } => {
+                let nb_needed = match (physical_button.get_kind(), &tempo) {
+                    (PhysicalButtonKind::Momentary { shift: None }, PlannedTempo::Tempo2)
+                    | (PhysicalButtonKind::Tempo(TempoKind::Tempo2 { .. }), PlannedTempo::Tempo3) => {
+                        1
+                    }
+                    (PhysicalButtonKind::Momentary { shift: None }, PlannedTempo::Tempo3) => 2,
+                    (PhysicalButtonKind::Tempo(TempoKind::Tempo2 { .. }), PlannedTempo::Tempo2)
+                    | (PhysicalButtonKind::Tempo(TempoKind::Tempo3 { .. }), _) => 0,
+                    (PhysicalButtonKind::Momentary { shift: Some(_) }, _) => {
+                        return Err(not_applicable(
+                            "remove its SHIFT1/SHIFT2 virtual buttons first",
+                        ));
+                    }
+                    _ => return Err(not_applicable("TEMPO needs a momentary button")),
+                };
+                if nb_needed == 0 {
+                    push_step("nothing to do, already a TEMPO".to_string(), None);
+                    continue;
+                }
+
+                // The "Short" one keeps the current virtual button -> the existing keybind is kept
+                let short_virtual_button_id = joysticks_mappings
+                    .map_physical_button_id_to_children_virtual_buttons
+                    .get(physical_button.get_id())
+                    .and_then(|children| {
+                        children.iter().find(|child| {
+                            matches!(
+                                child.kind,
+                                VirtualButtonKind::Momentary(None)
+                                    | VirtualButtonKind::Tempo(
+                                        crate::button::VirtualTempoKind::Short
+                                    )
+                            )
+                        })
+                    })
+                    .map(|child| *child.get_id());
+                push_step(
+                    format!(
+                        "change to \"{}\" ({tempo:?}); \"{}\" stays #{}",
+                        lang.tempo,
+                        field_name(lang.tempo_short),
+                        short_virtual_button_id
+                            .map_or_else(|| "?".to_string(), |id| id.to_string())
+                    ),
+                    None,
+                );
+
+                let fields = [field_name(lang.tempo_long), field_name(lang.tempo_double)];
+                let fields = match physical_button.get_kind() {
+                    // Tempo2 -> Tempo3: ONLY the "Double" one is missing
+                    PhysicalButtonKind::Tempo(_) => &fields[1..],
+                    _ => &fields[..nb_needed],
+                };
+                for (field, virtual_button_id) in fields
+                    .iter()
+                    .zip(allocate(&mut free_virtual_buttons, fields.len())?)
+                {
+                    push_step(
+                        format!("set \"{field}\" to #{virtual_button_id}"),
+                        Some(virtual_button_id),
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::button::{VirtualButton, VirtualTempoKind};
+
+    use super::*;
+
+    /// (F1) is a plain Momentary on #1, (F2) a Tempo2 on #2 with "Short" #2 and "Long" #3
+    /// -> with `logical_buttons_count` the free IDs are ONLY #4..=`logical_buttons_count`
+    fn get_sample_mappings(logical_buttons_count: u8) -> JoystickButtonsMapping {
+        let physical_1 = PhysicalButton::new(
+            1,
+            PhysicalButtonKind::Momentary { shift: None },
+            "(F1)".to_string(),
+            "- Button with momentary action".to_string(),
+            String::new(),
+        );
+        let physical_2 = PhysicalButton::new(
+            2,
+            PhysicalButtonKind::Tempo(TempoKind::Tempo2 {
+                button_id_short: 2,
+                button_id_long: 3,
+            }),
+            "(F2)".to_string(),
+            "TEMPO".to_string(),
+            String::new(),
+        );
+        JoystickButtonsMapping {
+            map_virtual_button_id_to_parent_physical_buttons: HashMap::from([
+                (1, vec![physical_1]),
+                (2, vec![physical_2.clone()]),
+                (3, vec![physical_2]),
+            ]),
+            map_physical_button_id_to_children_virtual_buttons: HashMap::from([
+                (
+                    1,
+                    vec![VirtualButton {
+                        id: 1,
+                        kind: VirtualButtonKind::Momentary(None),
+                    }],
+                ),
+                (
+                    2,
+                    vec![
+                        VirtualButton {
+                            id: 2,
+                            kind: VirtualButtonKind::Tempo(VirtualTempoKind::Short),
+                        },
+                        VirtualButton {
+                            id: 3,
+                            kind: VirtualButtonKind::Tempo(VirtualTempoKind::Long),
+                        },
+                    ],
+                ),
+            ]),
+            map_special_buttons: HashMap::new(),
+            undefined_physical_buttons: vec![],
+            map_physical_button_id_to_infos: HashMap::from([
+                (1, vec!["(F1)".to_string()]),
+                (2, vec!["(F2)".to_string()]),
+            ]),
+            logical_buttons_count: Some(logical_buttons_count),
+        }
+    }
+
+    #[test]
+    fn test_parse_plan_request() {
+        assert_eq!(
+            parse_plan_request("add SHIFT1 and SHIFT2 to (A2), make (F1) a Tempo3").unwrap(),
+            vec![
+                PlanRequest::AddShift {
+                    info_or_user_desc: "(A2)".to_string(),
+                    shift1: true,
+                    shift2: true,
+                },
+                PlanRequest::MakeTempo {
+                    info_or_user_desc: "(F1)".to_string(),
+                    tempo: PlannedTempo::Tempo3,
+                },
+            ]
+        );
+        assert_eq!(
+            parse_plan_request("Add shift2 to Thumb red button;").unwrap(),
+            vec![PlanRequest::AddShift {
+                info_or_user_desc: "Thumb red button".to_string(),
+                shift1: false,
+                shift2: true,
+            }]
+        );
+        assert!(matches!(
+            parse_plan_request("add SHIFT3 to (A2)"),
+            Err(Error::InvalidPlanRequest(_))
+        ));
+        assert!(matches!(
+            parse_plan_request("make (F1) a Tempo4"),
+            Err(Error::InvalidPlanRequest(_))
+        ));
+    }
+
+    #[test]
+    fn test_allocate_prefers_contiguous() {
+        let mut free_virtual_buttons = vec![3, 5, 6, 8, 9, 10];
+
+        assert_eq!(
+            allocate(&mut free_virtual_buttons, 3).unwrap(),
+            vec![8, 9, 10]
+        );
+        assert_eq!(allocate(&mut free_virtual_buttons, 2).unwrap(), vec![5, 6]);
+        assert_eq!(allocate(&mut free_virtual_buttons, 1).unwrap(), vec![3]);
+        assert!(matches!(
+            allocate(&mut free_virtual_buttons, 1),
+            Err(Error::NotEnoughFreeVirtualButtons {
+                needed: 1,
+                available: 0
+            })
+        ));
+    }
+
+    #[test]
+    fn test_plan_virtual_buttons_report_l() {
+        let joysticks_mappings = crate::vkb::parse_and_check_vkb_both_sticks(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/vkb_report_L.fp3").as_ref(),
+            &None,
+            None,
+        )
+        .unwrap();
+        let game_buttons_mapping = crate::sc::parse_keybind_xml::parse_keybind(
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/data/layout_vkb_exported.xml"
+            )
+            .into(),
+            None,
+        )
+        .unwrap();
+
+        let steps = plan_virtual_buttons(
+            &joysticks_mappings,
+            Some(&game_buttons_mapping),
+            Some(2),
+            "add SHIFT1 and SHIFT2 to (A1 push), make (F1) a Tempo3",
+        )
+        .unwrap();
+
+        // (A1 push): 2 new IDs; (F1) is already a Tempo3
+        assert_eq!(steps.len(), 3);
+        let allocated: Vec<u8> = steps
+            .iter()
+            .filter_map(|step| step.virtual_button_id)
+            .collect();
+        assert_eq!(allocated.len(), 2);
+        assert_eq!(allocated[0] + 1, allocated[1]);
+        for virtual_button_id in allocated {
+            assert!(joysticks_mappings
+                .get_free_virtual_buttons()
+                .contains(&virtual_button_id));
+            assert!(game_buttons_mapping
+                .get_rebinds_from_virtual_button_id(virtual_button_id, 2)
+                .is_empty());
+        }
+        assert_eq!(steps[2].instruction, "nothing to do, already a TEMPO");
+    }
+
+    #[test]
+    fn test_plan_virtual_buttons_secondary_rebind() {
+        let joysticks_mappings = crate::vkb::parse_and_check_vkb_both_sticks(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/vkb_report_L.fp3").as_ref(),
+            &None,
+            None,
+        )
+        .unwrap();
+        // "v_attack_group2": js2_button108 then js2_button84; 84 is the lowest free virtual button
+        let game_buttons_mapping = crate::sc::parse_keybind_xml::parse_keybind(
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/data/layout_secondary_rebind_free_id.xml"
+            )
+            .into(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(joysticks_mappings.get_free_virtual_buttons()[0], 84);
+
+        let steps = plan_virtual_buttons(
+            &joysticks_mappings,
+            Some(&game_buttons_mapping),
+            Some(2),
+            "add SHIFT1 to (A1 push)",
+        )
+        .unwrap();
+
+        assert_eq!(steps.len(), 1);
+        assert_ne!(steps[0].virtual_button_id, Some(84));
+    }
+
+    #[test]
+    fn test_plan_virtual_buttons_make_tempo() {
+        let joysticks_mappings = get_sample_mappings(6);
+        assert_eq!(joysticks_mappings.get_free_virtual_buttons(), vec![4, 5, 6]);
+
+        let steps = plan_virtual_buttons(
+            &joysticks_mappings,
+            None,
+            None,
+            "make (F1) a Tempo3, make (F2) a Tempo3",
+        )
+        .unwrap();
+
+        assert_eq!(
+            steps
+                .iter()
+                .map(|step| (
+                    step.physical_button_id,
+                    step.instruction.as_str(),
+                    step.virtual_button_id
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                // Momentary -> Tempo3: "Long" AND "Double"
+                (
+                    1,
+                    "change to \"TEMPO\" (Tempo3); \"Virtual button Short\" stays #1",
+                    None
+                ),
+                (1, "set \"Virtual button Long\" to #4", Some(4)),
+                (1, "set \"Virtual button Double Short\" to #5", Some(5)),
+                // Tempo2 -> Tempo3: ONLY "Double"
+                (
+                    2,
+                    "change to \"TEMPO\" (Tempo3); \"Virtual button Short\" stays #2",
+                    None
+                ),
+                (2, "set \"Virtual button Double Short\" to #6", Some(6)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan_virtual_buttons_not_enough_free() {
+        // #4 and #5 ONLY: (F1) takes both, nothing left for (F2)
+        let joysticks_mappings = get_sample_mappings(5);
+
+        assert!(matches!(
+            plan_virtual_buttons(
+                &joysticks_mappings,
+                None,
+                None,
+                "make (F1) a Tempo3, make (F2) a Tempo3",
+            ),
+            Err(Error::NotEnoughFreeVirtualButtons {
+                needed: 1,
+                available: 0
+            })
+        ));
+    }
+}
@@ -0,0 +1,294 @@
+//! The user-provided csv eg `data/vkb_user_provided_data.csv`:
+//! ```csv
+//! Physical Button ID,Additional description,Side,Group,Note
+//! 3,Sw1 Up (base of stick),,,
+//! 12,Thumb red button,L,Thumb,
+//! ```
+//!
+//! - the rows are keyed by "Physical Button ID"; the order and missing rows do NOT matter
+//! - "Side", "Group" and "Note" are optional (both the columns and the values)
+//!   With "Side" one file can describe both sticks: a row with a side only applies to this side,
+//!   a row without a side applies to both. Then the side MUST be given cf `filter_records_for_side`.
+//!
+//! Validated against the report cf `validate`; ALL the issues are returned at once.
+//!
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::Error;
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct UserProvidedRecord {
+    #[serde(rename = "Physical Button ID")]
+    physical_button_id: u8,
+    #[serde(rename = "Additional description", default)]
+    user_desc: String,
+    /// eg "L", "R"; case-insensitive
+    #[serde(rename = "Side", default)]
+    side: Option<String>,
+    #[serde(rename = "Group", default)]
+    _group: Option<String>,
+    #[serde(rename = "Note", default)]
+    _note: Option<String>,
+}
+
+/// Read the csv and keep the rows applicable to `side`
+/// Returns "physical button id" -> "user desc"; rows without a description are skipped.
+///
+/// # Errors
+/// - `Error::Csv` if the file could not be read, or a row is malformed eg "Physical Button ID" is not a number
+/// - `Error::InvalidUserProvidedData` for duplicated IDs, or rows for several sides without `side`
+pub(super) fn read_user_provided_data(
+    vkb_user_provided_data_path: &Path,
+    side: Option<&str>,
+) -> Result<HashMap<u8, String>, Error> {
+    let mut rdr = csv::Reader::from_path(vkb_user_provided_data_path).map_err(Error::Csv)?;
+    let records = rdr
+        .deserialize()
+        .collect::<Result<Vec<UserProvidedRecord>, _>>()
+        .map_err(Error::Csv)?;
+
+    filter_records_for_side(records, side)
+}
+
+fn filter_records_for_side(
+    records: Vec<UserProvidedRecord>,
+    side: Option<&str>,
+) -> Result<HashMap<u8, String>, Error> {
+    // Without a side, ONLY one side can be used; else the rows of L and R would be reported as duplicated
+    if side.is_none() {
+        let mut sides: Vec<String> = records
+            .iter()
+            .filter_map(|record| record.side.as_deref().map(str::trim))
+            .filter(|record_side| !record_side.is_empty())
+            .map(str::to_uppercase)
+            .collect();
+        sides.sort();
+        sides.dedup();
+        if sides.len() > 1 {
+            return Err(Error::InvalidUserProvidedData(vec![format!(
+                "the file has rows for several sides ({}): --vkb-side is required",
+                sides.join(", ")
+            )]));
+        }
+    }
+
+    let mut issues = vec![];
+    // id -> (desc, is_side_specific)
+    let mut map_physical_button_id_to_user_desc: HashMap<u8, (String, bool)> = HashMap::new();
+
+    for record in records {
+        let record_side = record
+            .side
+            .as_deref()
+            .map(str::trim)
+            .filter(|record_side| !record_side.is_empty());
+        let is_side_specific = match (record_side, side) {
+            (None, _) => false,
+            (Some(record_side), Some(side)) if record_side.eq_ignore_ascii_case(side) => true,
+            (Some(_), Some(_)) => continue,
+            (Some(record_side), None) => {
+                log::warn!(
+                    "user provided data: row {} is for side \"{record_side}\" but no side was given; using it anyway",
+                    record.physical_button_id
+                );
+                true
+            }
+        };
+
+        let user_desc = record.user_desc.trim().to_string();
+        if user_desc.is_empty() {
+            continue;
+        }
+
+        match map_physical_button_id_to_user_desc.get(&record.physical_button_id) {
+            // A side-specific row overrides a generic one, and NOT the other way around
+            Some((_, false)) if is_side_specific => {}
+            Some((_, true)) if !is_side_specific => continue,
+            Some((previous_user_desc, _)) => {
+                issues.push(format!(
+                    "physical button {} is duplicated: \"{previous_user_desc}\" and \"{user_desc}\"",
+                    record.physical_button_id
+                ));
+                continue;
+            }
+            None => {}
+        }
+        map_physical_button_id_to_user_desc
+            .insert(record.physical_button_id, (user_desc, is_side_specific));
+    }
+
+    if !issues.is_empty() {
+        return Err(Error::InvalidUserProvidedData(issues));
+    }
+
+    Ok(map_physical_button_id_to_user_desc
+        .into_iter()
+        .map(|(physical_button_id, (user_desc, _))| (physical_button_id, user_desc))
+        .collect())
+}
+
+/// CHECK the user data against the report:
+/// - the IDs MUST be physical buttons in the report
+/// - a description MUST NOT be the "info" of ANOTHER button eg "(A2)"; else the lookup by label is ambiguous
+///
+/// # Errors
+/// - `Error::InvalidUserProvidedData` with ALL the issues found
+pub(super) fn validate(
+    map_physical_button_id_to_user_desc: &HashMap<u8, String>,
+    map_physical_button_id_to_infos: &HashMap<u8, Vec<String>>,
+) -> Result<(), Error> {
+    let mut issues = vec![];
+
+    let mut physical_button_ids: Vec<_> = map_physical_button_id_to_user_desc.keys().collect();
+    physical_button_ids.sort();
+    for physical_button_id in physical_button_ids {
+        let user_desc = &map_physical_button_id_to_user_desc[physical_button_id];
+
+        if !map_physical_button_id_to_infos.contains_key(physical_button_id) {
+            issues.push(format!(
+                "physical button {physical_button_id} (\"{user_desc}\") is not in the report"
+            ));
+        }
+
+        if let Some((other_physical_button_id, _)) = map_physical_button_id_to_infos
+            .iter()
+            .filter(|(other_physical_button_id, _)| *other_physical_button_id != physical_button_id)
+            .find(|(_, infos)| infos.contains(user_desc))
+        {
+            issues.push(format!(
+                "physical button {physical_button_id}: \"{user_desc}\" is the info of physical button {other_physical_button_id}"
+            ));
+        }
+    }
+
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::InvalidUserProvidedData(issues))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(physical_button_id: u8, user_desc: &str, side: Option<&str>) -> UserProvidedRecord {
+        UserProvidedRecord {
+            physical_button_id,
+            user_desc: user_desc.to_string(),
+            side: side.map(str::to_string),
+            _group: None,
+            _note: None,
+        }
+    }
+
+    #[test]
+    fn test_read_user_provided_data() {
+        let map_physical_button_id_to_user_desc = read_user_provided_data(
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/data/vkb_user_provided_data.csv"
+            )
+            .as_ref(),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            map_physical_button_id_to_user_desc.get(&3).unwrap(),
+            "Sw1 Up (base of stick)"
+        );
+        // empty rows are skipped
+        assert!(!map_physical_button_id_to_user_desc.contains_key(&1));
+    }
+
+    #[test]
+    fn test_filter_records_for_side() {
+        let records = vec![
+            record(3, "Sw1 Up", None),
+            record(12, "Thumb red button", Some("L")),
+            record(12, "Thumb black button", Some("r")),
+            record(3, "Sw1 Up (right stick)", Some("R")),
+        ];
+
+        let left = filter_records_for_side(
+            records
+                .iter()
+                .map(|r| record(r.physical_button_id, &r.user_desc, r.side.as_deref()))
+                .collect(),
+            Some("L"),
+        )
+        .unwrap();
+        assert_eq!(
+            left,
+            HashMap::from([
+                (3, "Sw1 Up".to_string()),
+                (12, "Thumb red button".to_string())
+            ])
+        );
+
+        let right = filter_records_for_side(records, Some("R")).unwrap();
+        assert_eq!(
+            right,
+            HashMap::from([
+                (3, "Sw1 Up (right stick)".to_string()),
+                (12, "Thumb black button".to_string())
+            ])
+        );
+
+        assert!(matches!(
+            filter_records_for_side(vec![record(3, "a", None), record(3, "b", None)], None),
+            Err(Error::InvalidUserProvidedData(issues)) if issues.len() == 1
+        ));
+    }
+
+    #[test]
+    fn test_filter_records_for_side_several_sides_without_side() {
+        // NOT "physical button 12 is duplicated"
+        let Err(Error::InvalidUserProvidedData(issues)) = filter_records_for_side(
+            vec![
+                record(12, "Thumb red button", Some("L")),
+                record(12, "Thumb black button", Some("r")),
+            ],
+            None,
+        ) else {
+            panic!("several sides without a side MUST be an error");
+        };
+        assert_eq!(
+            issues,
+            vec!["the file has rows for several sides (L, R): --vkb-side is required".to_string()]
+        );
+
+        // only one side: used anyway
+        assert_eq!(
+            filter_records_for_side(vec![record(12, "Thumb red button", Some("L"))], None).unwrap(),
+            HashMap::from([(12, "Thumb red button".to_string())])
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let map_physical_button_id_to_infos = HashMap::from([
+            (12, vec!["(A2)".to_string()]),
+            (14, vec!["(A3 push)".to_string()]),
+        ]);
+
+        assert!(validate(
+            &HashMap::from([(12, "Thumb red button".to_string())]),
+            &map_physical_button_id_to_infos
+        )
+        .is_ok());
+
+        let Err(Error::InvalidUserProvidedData(issues)) = validate(
+            &HashMap::from([(14, "(A2)".to_string()), (99, "Not a button".to_string())]),
+            &map_physical_button_id_to_infos,
+        ) else {
+            panic!("validate SHOULD have failed");
+        };
+        assert_eq!(issues.len(), 2);
+    }
+}
@@ -2,81 +2,51 @@
 //! There is NO processing/cleaning up/checks done here!
 //! These are done by the next step see `vkb_button.rs`
 //!
-// TODO see also https://github.com/tafia/quick-xml/blob/master/examples/read_nodes_serde.rs
+//! The report is read in a single pass with quick-xml's event reader, building the b2 -> b3 tree directly.
+//! We only care about a handful of elements, everything else is skipped:
+//! - "b1.m2": the header; needed to detect the language of the report
+//! - "b2.m5"/"b2.m7": a physical button ie the PARENT
+//! - "b3.m8"/"b3.m9": a virtual button ie a CHILD of the previous b2
+//!
+//! "sourcepages" (the report design) and "dictionary" are skipped entirely.
+//!
+//! Each b2/b3 keeps its byte offset in the file, so that errors can point to the faulty entry.
+//!
 
-use std::path::PathBuf;
+use std::path::Path;
 
-use serde::Deserialize;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
 
+use super::vkb_fp3::read_fp3_to_string;
 use crate::Error;
 
-/// Maps eg
-/// <m7 t="0" h="32,12105"
-/// u="&#60;font color=&#34;#000000&#34;&#62;Virtual button with SHIFT1 = 63&#13;&#10;Virtual button with SHIFT2 = 92" />
-///
-#[derive(Deserialize, Debug, Clone, PartialEq)]
-pub(super) struct M7 {
-    /// This is the description; xml escaped!
-    #[serde(rename = "@u")]
-    pub(super) desc_xml_escaped: String,
-}
-
-/// Maps the M5 child struct of "b2"
-/// <m5 u="29" />
-/// and is "Page0.LineN" which would seem to indicate this is only for ordering
-/// BUT it maps nicely to the "physical button ID"???
-#[derive(Deserialize, Debug, Clone, PartialEq)]
-pub(super) struct M5 {
-    /// This is the description; xml escaped!
-    #[serde(rename = "@u")]
-    pub(super) physical_button_id: String,
-}
-
 /// Maps eg
 /// <b2 t="22,67718" h="32,12105">
 ///     <p2 t="0" w="113" h="22" ImageIndex="23" Transparent="1" />
+///     <m5 u="9" />
 ///     <m7 t="0" h="32,12105"
-///         u="&#60;font color=&#34;#000000&#34;&#62;Virtual button with SHIFT1 = 63&#13;&#10;Virtual button with SHIFT2 = 92" />
+///         u="&#60;b&#62;#9 (Fire 2-nd stage) &#60;/b&#62;&#60;b&#62;- Button with momentary action&#60;/b&#62;" />
 /// </b2>
+/// <b3 ...>
+/// <b3 ...>
+///
+/// NOTE: only care about
+/// - "m5": <m5 name="Page0.LineN" /> which would seem to indicate this is only for ordering
+///   BUT it maps nicely to the "physical button ID"???
+/// - "m7": <m7 name="Page0.Description1" />
 ///
-/// NOTE: only care about "m7": <m7 name="Page0.Description1" />
 /// "p2" is an image: <p2 name="Page0.Image1" />
-#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub(super) struct B2 {
-    #[serde(rename = "@t")]
-    t: String,
-    #[serde(rename = "@h")]
-    h: String,
-    /// The fields below SHOULD always be there, but sometimes a struct is split
-    /// onto two pages
-    /// grep for "<b2 t="1006,65661" h="8,11023377999998">" and "<b2 t="990,09462" h="34,15801">"
-    pub(super) m5: M5,
-    pub(super) m7: M7,
-}
-
-// impl B2 {
-//     /// Preprocessing step: merge the B2 fields on two different pages `page0`
-//     fn merge_with(&self, next_b2: &Self) {
-//         todo!()
-//     }
-// }
-
-/// Maps the M3 child struct Virtual Button ID ("VBN" in VKB terminology)
-/// <m8 u="95" />
-#[derive(Deserialize, Debug, Clone)]
-pub(super) struct M8 {
-    /// This is the description; xml escaped!
-    #[serde(rename = "@u")]
-    pub(super) _virtual_button_id: String,
-}
-
-/// Maps the description for the M3 child struct
-/// <m9 u="&#60;b&#62;#95 &#60;/b&#62; Joystick button : #95" />
-#[derive(Deserialize, Debug, Clone)]
-pub(super) struct M9 {
-    /// This is the description; xml escaped!
-    #[serde(rename = "@u")]
-    pub(super) desc_xml_escaped: String,
+    /// Byte offset of "<b2" in the report
+    pub(super) offset: usize,
+    /// The "m5" field
+    pub(super) physical_button_id: String,
+    /// The "m7" field; xml unescaped but still containing the `<b>`/`<font>` markup cf `vkb_desc_markup.rs`
+    pub(super) desc: String,
+    /// The b3 fields following this b2, in order
+    pub(super) children: Vec<B3>,
 }
 
 /// Maps eg
@@ -88,69 +58,19 @@ pub(super) struct M9 {
 ///     <m9 u="&#60;b&#62;#95 &#60;/b&#62; Joystick button : #95" />
 ///     <g2 Left="0" Top="4,22046999999998" Width="718,1107" Height="1,13385826771654"
 ///         ShowHint="false" BeginColor="12632256" Style="gsHorizontal" Color="10526880" />
-// </b3>
+/// </b3>
 ///
 /// NOTE: only care about
 /// - "m8": <m8 name="Page0.VBN" /> -> the Virtual Button number?
 /// - "m9": <m9 name="Page0.Decsription2" /> -> description field, same as "m7" for "b2" struct
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub(super) struct B3 {
-    #[serde(rename = "@t")]
-    _t: String,
-    #[serde(rename = "@h")]
-    _h: String,
-    #[serde(rename = "m8")]
-    pub(super) _m8: M8,
-    pub(super) m9: M9,
-}
-
-/// Intro segment, only on the first page0?
-type B1 = ();
-/// also only on the first page0; not sure what that is: "<TfrxNullBand Height="1046,92981" Left="0" Top="0" Width="718,1107" l="0" t="0" />"
-type TfrxNullBand = ();
-/// summary; only on the last page
-type B4 = ();
-type B5 = ();
-type B6 = ();
-
-/// To preserve the relative order fo b2/b3 we need an wrapper struct
-/// thanks phind.com
-#[derive(Deserialize, Debug)]
-pub(super) enum Page0Item {
-    #[serde(rename = "b1")]
-    B1(B1),
-    TfrxNullBand(TfrxNullBand),
-    #[serde(rename = "b2")]
-    B2(B2),
-    #[serde(rename = "b3")]
-    B3(B3),
-    #[serde(rename = "b4")]
-    B4(B4),
-    #[serde(rename = "b5")]
-    B5(B5),
-    #[serde(rename = "b6")]
-    B6(B6),
-}
-
-/// Maps the per-page structure of B2/B3
-/// <page0>
-///     <b2...>
-///     <b3 ...>
-///     <b3 ...>
-///     <b2...>
-///     ...
-/// </page0>
-#[derive(Deserialize, Debug)]
-struct Page0 {
-    /// Option b/c the last page only has b6,b4,b5
-    #[serde(rename = "$value")]
-    b: Vec<Page0Item>,
-}
-
-/// <previewpages>Page0,Page0,...</previewpages>
-#[derive(Deserialize, Debug)]
-struct PreviewPages {
-    page0: Vec<Page0>,
+    /// Byte offset of "<b3" in the report
+    pub(super) offset: usize,
+    /// The "m8" field
+    pub(super) virtual_button_id: String,
+    /// The "m9" field; same as `B2::desc`
+    pub(super) desc: String,
 }
 
 /// Maps the full report eg
@@ -158,6 +78,7 @@ struct PreviewPages {
 /// <preparedreport>
 /// <previewpages>
 ///     <page0>
+///         <b1 ...><m2 u="Report generated by VKB Device Configurator ..." /></b1>
 ///         <b2 ...>
 ///         <b3 ...>
 ///         <b3 ...>
@@ -176,267 +97,241 @@ struct PreviewPages {
 /// ...
 /// </preparedreport>
 ///
-#[derive(Deserialize, Debug)]
+#[derive(Debug, PartialEq)]
 pub(super) struct VkbReport {
-    previewpages: PreviewPages,
+    /// The "b1.m2" field; only on the first page0
+    header: Option<String>,
+    b2s: Vec<B2>,
 }
 
-/// A XML parsed button, matching a "b2" or "b3" xml field
-/// this is mapped directly from the xml field, no logic, no checks, etc
-///
-/// The next step is done by `src/vkb/vkb_button.rs`
-#[derive(Debug, Clone)]
-pub(super) enum VkbXmlButton {
-    B2(B2),
-    B3(B3),
+/// The band ie "b1"/"b2"/"b3" element we are currently in
+enum CurrentBand {
+    B1,
+    B2 {
+        offset: usize,
+        physical_button_id: Option<String>,
+        desc: Option<String>,
+    },
+    B3 {
+        offset: usize,
+        virtual_button_id: Option<String>,
+        desc: Option<String>,
+    },
 }
 
 impl VkbReport {
-    /// Parse a VKB .fp3 report (== .xml)
-    // TODO remove feature "overlapped-lists" and add a wrapper for B2 + Optional<Vec<B3>>
-    pub(super) fn new(xml_path: PathBuf) -> Result<Self, Error> {
-        let xml_str = std::fs::read_to_string(xml_path).map_err(|_| Error::Unknown)?;
+    /// Parse a VKB .fp3 report (== .xml, possibly compressed cf `vkb_fp3.rs`)
+    pub(super) fn new(xml_path: &Path) -> Result<Self, Error> {
+        let xml_str = read_fp3_to_string(xml_path)?;
 
-        // let xml_str = Self::preprocess_fix_pages(&xml_str);
+        Self::from_xml_str(&xml_str)
+    }
 
-        let vkb_report: VkbReport = quick_xml::de::from_str(&xml_str).map_err(|err| {
-            log::error!("report error: {:?}", err);
-            Error::Unknown
-        })?;
+    /// Single pass over the xml events cf mod doc
+    pub(super) fn from_xml_str(xml_str: &str) -> Result<Self, Error> {
+        let mut reader = Reader::from_str(xml_str);
+        reader.trim_text(true);
+
+        let mut vkb_report = VkbReport {
+            header: None,
+            b2s: vec![],
+        };
+        let mut current_band = None;
+
+        loop {
+            // NOTE: `buffer_position` is BEFORE the skipped blanks; point to the "<" instead
+            let offset = reader.buffer_position();
+            let offset = offset + xml_str[offset..].len() - xml_str[offset..].trim_start().len();
+            let event = reader.read_event().map_err(|err| Error::ReportParsing {
+                offset,
+                msg: err.to_string(),
+            })?;
+
+            match event {
+                // The report design and the "name" dictionary ALSO contain b1/b2/m7/etc elements; skip them
+                Event::Start(element)
+                    if matches!(element.name().as_ref(), b"sourcepages" | b"dictionary") =>
+                {
+                    reader
+                        .read_to_end(element.name())
+                        .map_err(|err| Error::ReportParsing {
+                            offset,
+                            msg: err.to_string(),
+                        })?;
+                }
+                Event::Start(element) => {
+                    vkb_report.handle_element(&element, offset, &mut current_band)?;
+                }
+                Event::Empty(element) => {
+                    vkb_report.handle_element(&element, offset, &mut current_band)?;
+                    // eg "<b2 ... />": open and close right away
+                    if matches!(element.name().as_ref(), b"b1" | b"b2" | b"b3") {
+                        vkb_report.close_band(current_band.take())?;
+                    }
+                }
+                Event::End(element) => {
+                    if matches!(element.name().as_ref(), b"b1" | b"b2" | b"b3") {
+                        vkb_report.close_band(current_band.take())?;
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
 
         Ok(vkb_report)
     }
 
-    // /// Preprocessing step
-    // // TODO this is really ugly: it is parsing the whole report first; then doing again with Serde...
-    // fn preprocess_fix_pages(xml_str: &str) -> String {
-    //     use quick_xml::Reader;
-
-    //     let mut reader = Reader::from_str(xml_str);
-    //     reader.trim_text(true).expand_empty_elements(true);
-
-    //     let mut buf = Vec::new();
-    //     let mut buf_nested = Vec::new();
-
-    //     // let mut previewpages = PreviewPages {
-    //     //     page0: Page0 { b: vec![] },
-    //     // };
-
-    //     loop {
-    //         buf.clear();
-    //         match reader.read_event_into(&mut buf) {
-    //             Ok(Event::Start(ref element)) => match element.name().as_ref() {
-    //                 b"b2" => {
-    //                     // let buf_clone = buf.clone();
-    //                     // log::debug!("parsing b2: {:?}", buf.clone());
-    //                     let attrs = element
-    //                         .attributes()
-    //                         .map(|a| a.unwrap().value)
-    //                         .collect::<Vec<_>>();
-    //                     log::debug!("b2 attrs: {:?}", attrs);
-
-    //                     // reader.read_to_end(element.name());
-
-    //                     // buf_nested.clear();
-    //                     loop {
-    //                         buf_nested.clear(); // NO! MUST be outside the loop! else always empty after exiting
-    //                         match reader.read_event_into(&mut buf_nested) {
-    //                             Ok(Event::Start(element)) => match element.name().as_ref() {
-    //                                 b"m7" => {
-    //                                     // stats.rows.push(vec![]);
-    //                                     // row_index = stats.rows.len() - 1;
-    //                                     let attrs_value = element
-    //                                         .attributes()
-    //                                         .map(|a| {
-    //                                             let val = a.unwrap().value.clone().to_vec();
-    //                                             std::str::from_utf8(&val).unwrap().to_string()
-    //                                         })
-    //                                         .collect::<Vec<_>>();
-    //                                     let attrs_keys = element
-    //                                         .attributes()
-    //                                         .map(|a| {
-    //                                             let val = a.unwrap().key.0.to_vec();
-    //                                             std::str::from_utf8(&val).unwrap().to_string()
-    //                                         })
-    //                                         .collect::<Vec<_>>();
-    //                                     log::debug!("b2 nested attrs_value: {:?}", attrs_value);
-    //                                     log::debug!("b2 nested attrs_keys: {:?}", attrs_keys);
-
-    //                                     let attrs =
-    //                                         element.try_get_attribute("m7").unwrap().unwrap();
-    //                                     log::debug!("b2 attrs: {:?}", attrs);
-    //                                 }
-    //                                 b"w:tc" => {
-    //                                     // stats.rows[row_index].push(
-    //                                     //     String::from_utf8(element.name().as_ref().to_vec())
-    //                                     //         .unwrap(),
-    //                                     // );
-    //                                 }
-    //                                 _ => {}
-    //                             },
-    //                             Ok(Event::End(_element)) => {
-    //                                 // if element.name().as_ref() == b"m7" {
-    //                                 //     // found_tables.push(stats);
-    //                                 //     break;
-    //                                 // }
-    //                             }
-    //                             Ok(Event::Eof) => break,
-    //                             _ => {}
-    //                         }
-    //                     }
-
-    //                     log::debug!(
-    //                         "b2: {:?}, nested: {:?}",
-    //                         std::str::from_utf8(&buf).unwrap(),
-    //                         std::str::from_utf8(&buf_nested).unwrap()
-    //                     );
-
-    //                     // let b2: Result<B2, _> = quick_xml::de::from_reader::<_, B2>(buf.as_slice());
-    //                     // match b2 {
-    //                     //     Ok(b2) => {
-    //                     //         // Add the deserialized B2 instance to the PreviewPages.
-    //                     //         // previewpages.page0.b.push(Page0Item::b2(b2));
-    //                     //         log::debug!("b2 ok: {:?}", b2);
-    //                     //     }
-    //                     //     Err(err) => {
-    //                     //         // Handle the special case: peek into the next "b2" element and merge the instances.
-    //                     //         log::debug!(
-    //                     //             "b2 error: {}, data: {:?}",
-    //                     //             err,
-    //                     //             std::str::from_utf8(&buf).unwrap()
-    //                     //         );
-    //                     //         // todo!()
-    //                     //     }
-    //                     // }
-    //                 }
-    //                 _ => {}
-    //             },
-    //             Ok(Event::Eof) => break,
-    //             Err(e) => panic!("Error at position {}: {:?}", reader.buffer_position(), e),
-    //             _ => {}
-    //         }
-    //     }
-
-    //     xml_str.to_owned()
-
-    //     // let mut settings: HashMap<String, String>;
-
-    //     // loop {
-    //     //     let event = reader.read_event_into(&mut buf).map_err(|err| todo!())?;
-
-    //     //     match event {
-    //     //         Event::Start(element) => match element.name().as_ref() {
-    //     //
-    //     //                 // Note: real app would handle errors with good defaults or halt program with nice message
-    //     //                 // This illustrates decoding an attribute's key and value with error handling
-    //     //                 settings = element
-    //     //                     .attributes()
-    //     //                     .map(|attr_result| {
-    //     //                         match attr_result {
-    //     //                             Ok(a) => {
-    //     //                                 let key = reader.decoder().decode(a.key.local_name().as_ref())
-    //     //                                     .or_else(|err| {
-    //     //                                         dbg!("unable to read key in DefaultSettings attribute {:?}, utf8 error {:?}", &a, err);
-    //     //                                         Ok::<Cow<'_, str>, Infallible>(std::borrow::Cow::from(""))
-    //     //                                     })
-    //     //                                     .unwrap().to_string();
-    //     //                                 let value = a.decode_and_unescape_value(&reader).or_else(|err| {
-    //     //                                         dbg!("unable to read key in DefaultSettings attribute {:?}, utf8 error {:?}", &a, err);
-    //     //                                         Ok::<Cow<'_, str>, Infallible>(std::borrow::Cow::from(""))
-    //     //                                 }).unwrap().to_string();
-    //     //                                 (key, value)
-    //     //                             },
-    //     //                             Err(err) => {
-    //     //                                  dbg!("unable to read key in DefaultSettings, err = {:?}", err);
-    //     //                                 (String::new(), String::new())
-    //     //                             }
-    //     //                         }
-    //     //                     })
-    //     //                     .collect();
-    //     //                 // assert_eq!(settings["Language"], "es");
-    //     //                 // assert_eq!(settings["Greeting"], "HELLO");
-    //     //                 reader.read_to_end(element.name()).map_err(|err| todo!())?;
-    //     //             }
-    //     //             b"b3" => {
-    //     //                 // translations.push(B3::new_from_element(&mut reader, element)?);
-    //     //                 // B3::from(element);
-    //     //                 // let b3: B3 = from_str("aaa").map_err(|err| todo!())?;
-    //     //                 // let b3: B3 = from_reader(reader.clone().into_inner()).map_err(|err| {
-    //     //                 //     error!("error: {}", err);
-    //     //                 //     // todo!()
-    //     //                 //     Error::Unknown
-    //     //                 // })?;
-    //     //                 // let span = reader.read_to_end(element.name()).map_err(|err| todo!())?;
-    //     //                 // let text = reader.decoder().decode(&reader.into_inner()[span]);
-
-    //     //                 // FAIL: this contains only the inner part
-    //     //                 // <s1 />
-    //     //                 // <p3 w="113" h="22" ImageIndex="24" Transparent="1" />
-    //     //                 // <m8 u="9" />
-    //     //                 // <s2 />
-    //     //                 // <m9 u="&#60;b&#62;#9 &#60;/b&#62; Joystick button : #2" />
-    //     //                 // <g2 Left="0" Top="4,22046999999998" Width="718,1107" Height="1,13385826771654"
-    //     //                 //     ShowHint="false" BeginColor="12632256" Style="gsHorizontal" Color="10526880" />")
-    //     //                 // let text = reader.read_text(element.name()).unwrap();
-    //     //                 // let end = element.name().to_end().into_owned();
-    //     //                 // let text = reader.read_to_end(element.name()).unwrap();
-
-    //     //                 // let b3: B3 = from_str(&text).map_err(|err| todo!())?;
-
-    //     //                 // serde deserialization
-    //     //                 let mut m8_buf = Vec::new();
-    //     //                 reader
-    //     //                     .read_event_into(&mut m8_buf)
-    //     //                     .map_err(|err| todo!())
-    //     //                     .unwrap();
-    //     //                 let m8_str = String::from_utf8_lossy(&m8_buf);
-    //     //                 let m8: B3 = from_str(&m8_str).unwrap();
-    //     //                 log::debug!("{:?}", m8);
-
-    //     //                 // todo!()
-    //     //             }
-    //     //             _ => (),
-    //     //         },
-
-    //     //         Event::Eof => break, // exits the loop when reaching end of file
-    //     //         _ => (),             // There are `Event` types not considered here
-    //     //     }
-    //     // }
-
-    //     // Ok(VkbReport { previewpages })
-    // }
-
-    /// Return only the b2/b3 list of fields from the VKB report
-    pub(super) fn get_all_buttons(&self) -> Vec<VkbXmlButton> {
-        let mut vkb_buttons = vec![];
-
-        for page in &self.previewpages.page0 {
-            for page_item in &page.b {
-                match page_item {
-                    Page0Item::B2(b2) => vkb_buttons.push(VkbXmlButton::B2(b2.clone())),
-                    Page0Item::B3(b3) => vkb_buttons.push(VkbXmlButton::B3(b3.clone())),
-                    _ => {}
+    fn handle_element(
+        &mut self,
+        element: &BytesStart<'_>,
+        offset: usize,
+        current_band: &mut Option<CurrentBand>,
+    ) -> Result<(), Error> {
+        match (element.name().as_ref(), current_band) {
+            (b"b1", current_band) => *current_band = Some(CurrentBand::B1),
+            (b"b2", current_band) => {
+                *current_band = Some(CurrentBand::B2 {
+                    offset,
+                    physical_button_id: None,
+                    desc: None,
+                });
+            }
+            (b"b3", current_band) => {
+                *current_band = Some(CurrentBand::B3 {
+                    offset,
+                    virtual_button_id: None,
+                    desc: None,
+                });
+            }
+            (b"m2", Some(CurrentBand::B1)) => {
+                self.header = Some(get_attribute_u(element, offset)?);
+            }
+            (
+                b"m5",
+                Some(CurrentBand::B2 {
+                    physical_button_id, ..
+                }),
+            ) => *physical_button_id = Some(get_attribute_u(element, offset)?),
+            (
+                b"m8",
+                Some(CurrentBand::B3 {
+                    virtual_button_id, ..
+                }),
+            ) => *virtual_button_id = Some(get_attribute_u(element, offset)?),
+            (b"m7", Some(CurrentBand::B2 { desc, .. }))
+            | (b"m9", Some(CurrentBand::B3 { desc, .. })) => {
+                *desc = Some(get_attribute_u(element, offset)?);
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn close_band(&mut self, band: Option<CurrentBand>) -> Result<(), Error> {
+        match band {
+            None | Some(CurrentBand::B1) => {}
+            Some(CurrentBand::B2 {
+                offset,
+                physical_button_id,
+                desc,
+            }) => {
+                let desc = desc.ok_or_else(|| Error::ReportParsing {
+                    offset,
+                    msg: "b2 without a m7 description".to_string(),
+                })?;
+
+                if let Some(physical_button_id) = physical_button_id {
+                    self.b2s.push(B2 {
+                        offset,
+                        physical_button_id,
+                        desc,
+                        children: vec![],
+                    });
+                } else {
+                    // A b2 split onto two pages: the second part has no "m5", only the end of the description
+                    // grep for "<b2 t="1006,65661" h="8,11023377999998">" and "<b2 t="990,09462" h="34,15801">"
+                    // -> append it to the first part
+                    let previous_b2 = self.b2s.last_mut().ok_or_else(|| Error::ReportParsing {
+                        offset,
+                        msg: "b2 continuation without a previous b2".to_string(),
+                    })?;
+                    log::debug!(
+                        "merging b2 at {offset} with the previous one at {}",
+                        previous_b2.offset
+                    );
+                    previous_b2.desc.push_str("\r\n");
+                    previous_b2.desc.push_str(&desc);
                 }
             }
+            Some(CurrentBand::B3 {
+                offset,
+                virtual_button_id,
+                desc,
+            }) => {
+                let b3 = B3 {
+                    offset,
+                    virtual_button_id: virtual_button_id.ok_or_else(|| Error::ReportParsing {
+                        offset,
+                        msg: "b3 without a m8 virtual button id".to_string(),
+                    })?,
+                    desc: desc.ok_or_else(|| Error::ReportParsing {
+                        offset,
+                        msg: "b3 without a m9 description".to_string(),
+                    })?,
+                };
+
+                // CHECK when processing a Virtual button, we MUST have a valid parent
+                self.b2s
+                    .last_mut()
+                    .ok_or_else(|| Error::ReportParsing {
+                        offset,
+                        msg: "b3 before any b2 ie parent is not yet set".to_string(),
+                    })?
+                    .children
+                    .push(b3);
+            }
         }
 
-        vkb_buttons
+        Ok(())
+    }
+
+    /// Return the header text of the report ie the "b1.m2" field, if any
+    pub(super) fn get_header(&self) -> Option<&str> {
+        self.header.as_deref()
+    }
+
+    /// Return the b2 fields, each with its b3 children
+    pub(super) fn get_all_buttons(&self) -> &[B2] {
+        &self.b2s
     }
 }
 
+/// Return the unescaped "u" attribute; this is where `FastReport` stores the text of a "mN" field
+fn get_attribute_u(element: &BytesStart<'_>, offset: usize) -> Result<String, Error> {
+    let to_report_parsing_error = |msg: String| Error::ReportParsing { offset, msg };
+
+    let attribute = element
+        .try_get_attribute("u")
+        .map_err(|err| to_report_parsing_error(err.to_string()))?
+        .ok_or_else(|| {
+            to_report_parsing_error(format!(
+                "missing attribute \"u\" in {}",
+                String::from_utf8_lossy(element.name().as_ref())
+            ))
+        })?;
+
+    Ok(attribute
+        .unescape_value()
+        .map_err(|err| to_report_parsing_error(err.to_string()))?
+        .into_owned())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_parse_m7() {
-        let xml_str = r#"
-        <m7 h="20,59895" u="&#60;b&#62;#9 (Fire 2-nd stage) &#60;/b&#62;&#60;b&#62;- Button with momentary action&#60;/b&#62;" />
-        "#;
-
-        quick_xml::de::from_str::<M7>(xml_str).unwrap();
-    }
-
     #[test]
     fn test_parse_b2() {
         let xml_str = r#"
@@ -451,30 +346,25 @@ mod tests {
         </b2>
         "#;
 
-        quick_xml::de::from_str::<B2>(xml_str).unwrap();
-    }
-
-    #[test]
-    fn test_parse_m8() {
-        let xml_str = r#"
-        <m8 u="95" />
-        "#;
-
-        quick_xml::de::from_str::<M8>(xml_str).unwrap();
-    }
-
-    #[test]
-    fn test_parse_m9() {
-        let xml_str = r#"
-        <m9 u="&#60;b&#62;#95 &#60;/b&#62; Joystick button : #95" />
-        "#;
-
-        quick_xml::de::from_str::<M9>(xml_str).unwrap();
+        assert_eq!(
+            VkbReport::from_xml_str(xml_str).unwrap().get_all_buttons(),
+            &[B2 {
+                offset: 9,
+                physical_button_id: "9".to_string(),
+                desc: "<b>#9 (Fire 2-nd stage) </b><b>- Button with momentary action</b>"
+                    .to_string(),
+                children: vec![],
+            }]
+        );
     }
 
     #[test]
     fn test_parse_b3() {
         let xml_str = r#"
+        <b2 t="991,09462" h="33,15801">
+            <m5 u="95" />
+            <m7 u="&#60;b&#62;#95 &#60;/b&#62;&#60;b&#62;- Button with momentary action&#60;/b&#62;" />
+        </b2>
         <b3 t="560,72449" h="33,77953">
                 <s1 />
                 <p3 w="113" h="22" ImageIndex="12" Transparent="1" />
@@ -486,7 +376,21 @@ mod tests {
             </b3>
         "#;
 
-        quick_xml::de::from_str::<B3>(xml_str).unwrap();
+        let vkb_report = VkbReport::from_xml_str(xml_str).unwrap();
+        let children = &vkb_report.get_all_buttons()[0].children;
+        assert_eq!(children.len(), 1);
+        assert_eq!(children[0].virtual_button_id, "95");
+        assert_eq!(children[0].desc, "<b>#95 </b> Joystick button : #95");
+    }
+
+    #[test]
+    fn test_parse_b3_without_parent() {
+        let xml_str = r#"<page0><b3 t="0" h="0"><m8 u="95" /><m9 u="x" /></b3></page0>"#;
+
+        assert!(matches!(
+            VkbReport::from_xml_str(xml_str),
+            Err(Error::ReportParsing { offset: 7, .. })
+        ));
     }
 
     #[test]
@@ -524,7 +428,17 @@ mod tests {
         </page0>
         "#;
 
-        quick_xml::de::from_str::<Page0>(xml_str).unwrap();
+        let vkb_report = VkbReport::from_xml_str(xml_str).unwrap();
+        assert!(vkb_report
+            .get_header()
+            .unwrap()
+            .contains("Number of logical buttons : 128\r\n"));
+        assert_eq!(vkb_report.get_all_buttons().len(), 1);
+        assert_eq!(
+            vkb_report.get_all_buttons()[0].desc,
+            "<b>#1 (E1) </b> / <b>#2  - Encoder 2/4</b>\r\nVirtual buttons : #61 / #62"
+        );
+        assert_eq!(vkb_report.get_all_buttons()[0].children.len(), 1);
     }
 
     #[test]
@@ -534,29 +448,53 @@ mod tests {
                 env!("CARGO_MANIFEST_DIR"),
                 "/tests/data/vkb_report_simplified.fp3"
             )
-            .into(),
+            .as_ref(),
         )
         .is_ok());
     }
 
+    #[test]
+    fn test_get_header() {
+        let vkb_report = VkbReport::new(
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/data/vkb_report_simplified.fp3"
+            )
+            .as_ref(),
+        )
+        .unwrap();
+
+        assert!(vkb_report
+            .get_header()
+            .unwrap()
+            .contains("Number of logical buttons : 128"));
+    }
+
     #[test]
     fn test_parse_report_full_full_r() {
         assert!(VkbReport::new(
-            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/vkb_report_L.fp3").into(),
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/vkb_report_L.fp3").as_ref(),
         )
         .is_ok());
     }
 
     #[test]
-    #[ignore = "VKB report merging not supported anymore, for now"]
     fn test_parse_report_full_b2_merging() {
-        assert!(VkbReport::new(
+        let vkb_report = VkbReport::new(
             concat!(
                 env!("CARGO_MANIFEST_DIR"),
                 "/tests/data/vkb_report_test_b2_merging.fp3"
             )
-            .into(),
+            .as_ref(),
         )
-        .is_ok());
+        .unwrap();
+
+        let b2s = vkb_report.get_all_buttons();
+        assert_eq!(b2s.len(), 1);
+        assert_eq!(
+            b2s[0].desc,
+            "<b>#9 (Fire 2-nd stage) </b><b>- Button with momentary action</b>\r\n<font color=\"#000000\">Virtual button with SHIFT1 = 63\r\nVirtual button with SHIFT2 = 92"
+        );
+        assert_eq!(b2s[0].children.len(), 1);
     }
 }